@@ -21,6 +21,7 @@ fn default_tarantool_crate_path() -> syn::Path {
     parse_quote! { tarantool }
 }
 
+mod space_format;
 mod test;
 
 /// Mark a function as a test.
@@ -496,7 +497,7 @@ mod msgpack {
                     match #tarantool_crate::msgpack::Decode::decode(r, context) {
                         Ok(val) => #var_name = Some(val),
                         Err(err) => {
-                            let markered = err.source.get(err.source.len() - 33..).unwrap_or("") == "failed to read MessagePack marker";
+                            let markered = err.source_message().get(err.source_message().len() - 33..).unwrap_or("") == "failed to read MessagePack marker";
                             let nulled = if err.part.is_some() {
                                 err.part.as_ref().expect("Can't fail after a conditional check") == "got Null"
                             } else {
@@ -639,7 +640,7 @@ mod msgpack {
                 match #tarantool_crate::msgpack::Decode::decode(r, context) {
                     Ok(val) => #var_name = Some(val),
                     Err(err) => {
-                        let markered = err.source.get(err.source.len() - 33..).unwrap_or("")== "failed to read MessagePack marker";
+                        let markered = err.source_message().get(err.source_message().len() - 33..).unwrap_or("")== "failed to read MessagePack marker";
                         let nulled = if err.part.is_some() {
                             err.part.as_ref().expect("Can't fail after a conditional check") == "got Null"
                         } else {
@@ -1121,6 +1122,16 @@ pub fn derive_decode(input: TokenStream) -> TokenStream {
     expanded.into()
 }
 
+/// Macro to automatically derive `tarantool::space::SpaceFormat`.
+/// Deriving this trait generates the space format and index definitions from
+/// the struct's fields, so that they are always in sync with the rust code.
+///
+/// For more information see `tarantool::space::SpaceFormat`
+#[proc_macro_derive(SpaceFormat, attributes(space_format))]
+pub fn derive_space_format(input: TokenStream) -> TokenStream {
+    space_format::impl_derive(input)
+}
+
 /// Create a tarantool stored procedure.
 ///
 /// See `tarantool::proc` doc-comments in tarantool crate for details.
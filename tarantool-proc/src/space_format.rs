@@ -0,0 +1,265 @@
+use darling::{ast, util::PathList, FromDeriveInput, FromField, FromMeta};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{GenericArgument, Ident, Path, PathArguments, Type};
+
+use crate::default_tarantool_crate_path;
+
+#[derive(FromDeriveInput)]
+#[darling(attributes(space_format), supports(struct_named))]
+struct Args {
+    ident: Ident,
+    generics: syn::Generics,
+    data: ast::Data<(), FieldArgs>,
+    /// Path to tarantool crate.
+    tarantool: Option<String>,
+    /// Indexes of the space, the first one is the primary key.
+    #[darling(multiple)]
+    index: Vec<IndexArgs>,
+}
+
+#[derive(FromField)]
+#[darling(attributes(space_format))]
+struct FieldArgs {
+    ident: Option<Ident>,
+    ty: Type,
+    /// Name of the field in the space format, defaults to the rust field name.
+    rename: Option<String>,
+    /// Explicit field type, overrides the one inferred from the rust type.
+    field_type: Option<String>,
+    /// Whether the field is nullable, overrides the one inferred from the
+    /// rust type.
+    is_nullable: Option<bool>,
+}
+
+#[derive(FromMeta)]
+struct IndexArgs {
+    name: String,
+    /// Rust names of the fields which make up the index.
+    parts: PathList,
+    unique: Option<bool>,
+    index_type: Option<String>,
+}
+
+const FIELD_TYPES: &[(&str, &str)] = &[
+    ("any", "Any"),
+    ("unsigned", "Unsigned"),
+    ("string", "String"),
+    ("number", "Number"),
+    ("double", "Double"),
+    ("integer", "Integer"),
+    ("boolean", "Boolean"),
+    ("varbinary", "Varbinary"),
+    ("scalar", "Scalar"),
+    ("decimal", "Decimal"),
+    ("uuid", "Uuid"),
+    ("datetime", "Datetime"),
+    ("interval", "Interval"),
+    ("array", "Array"),
+    ("map", "Map"),
+];
+
+const INDEX_TYPES: &[(&str, &str)] = &[
+    ("hash", "Hash"),
+    ("tree", "Tree"),
+    ("bitset", "Bitset"),
+    ("rtree", "Rtree"),
+];
+
+fn variant_by_name(
+    known: &[(&str, &str)],
+    name: &str,
+    what: &str,
+    span: Span,
+) -> Result<Ident, syn::Error> {
+    let lowercase = name.to_lowercase();
+    known
+        .iter()
+        .find(|(n, _)| *n == lowercase)
+        .map(|(_, variant)| Ident::new(variant, span))
+        .ok_or_else(|| {
+            let expected: Vec<_> = known.iter().map(|(n, _)| *n).collect();
+            syn::Error::new(
+                span,
+                format!(
+                    "unknown {what} '{name}', expected one of: {}",
+                    expected.join(", ")
+                ),
+            )
+        })
+}
+
+/// Returns the inner type if `ty` is an `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let segment = match ty {
+        Type::Path(type_path) => type_path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(inner) => Some(inner),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Infers the tarantool field type from the rust type. Returns the name of a
+/// `space::FieldType` variant.
+fn infer_field_type(ty: &Type) -> &'static str {
+    match ty {
+        Type::Reference(r) => infer_field_type(&r.elem),
+        Type::Group(g) => infer_field_type(&g.elem),
+        Type::Paren(p) => infer_field_type(&p.elem),
+        Type::Array(_) | Type::Slice(_) | Type::Tuple(_) => "Array",
+        Type::Path(type_path) => {
+            let segment = match type_path.path.segments.last() {
+                Some(segment) => segment,
+                None => return "Any",
+            };
+            match segment.ident.to_string().as_str() {
+                "bool" => "Boolean",
+                "u8" | "u16" | "u32" | "u64" | "usize" => "Unsigned",
+                "i8" | "i16" | "i32" | "i64" | "isize" => "Integer",
+                "f32" | "f64" => "Double",
+                "String" | "str" | "char" => "String",
+                "Decimal" => "Decimal",
+                "Uuid" => "Uuid",
+                "Datetime" => "Datetime",
+                "ByteBuf" | "Bytes" => "Varbinary",
+                "Vec" | "VecDeque" | "HashSet" | "BTreeSet" => "Array",
+                "HashMap" | "BTreeMap" => "Map",
+                "Box" | "Rc" | "Arc" | "Cow" => match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => args
+                        .args
+                        .iter()
+                        .find_map(|arg| match arg {
+                            GenericArgument::Type(inner) => Some(infer_field_type(inner)),
+                            _ => None,
+                        })
+                        .unwrap_or("Any"),
+                    _ => "Any",
+                },
+                _ => "Any",
+            }
+        }
+        _ => "Any",
+    }
+}
+
+pub fn impl_derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = syn::parse_macro_input!(input as syn::DeriveInput);
+    let args = match Args::from_derive_input(&input) {
+        Ok(args) => args,
+        Err(e) => return e.write_errors().into(),
+    };
+    match expand(args) {
+        Ok(ts) => ts.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(args: Args) -> Result<TokenStream, syn::Error> {
+    let tarantool: Path = match &args.tarantool {
+        Some(path) => syn::parse_str(path)?,
+        None => default_tarantool_crate_path(),
+    };
+    let fields = args
+        .data
+        .take_struct()
+        .expect("only named structs are supported")
+        .fields;
+
+    let mut names = Vec::with_capacity(fields.len());
+    let mut format = Vec::with_capacity(fields.len());
+    for field in &fields {
+        let ident = field
+            .ident
+            .as_ref()
+            .expect("only named fields are supported");
+        let span = ident.span();
+        let name = field
+            .rename
+            .clone()
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").into());
+
+        let inner = option_inner(&field.ty);
+        let is_nullable = field.is_nullable.unwrap_or(inner.is_some());
+        let field_type = match &field.field_type {
+            Some(field_type) => variant_by_name(FIELD_TYPES, field_type, "field type", span)?,
+            None => Ident::new(infer_field_type(inner.unwrap_or(&field.ty)), span),
+        };
+
+        format.push(quote! {
+            #tarantool::space::Field::from((
+                #name,
+                #tarantool::space::FieldType::#field_type,
+            ))
+            .is_nullable(#is_nullable)
+        });
+        names.push((ident, name, is_nullable));
+    }
+
+    let mut indexes = Vec::with_capacity(args.index.len());
+    for index in &args.index {
+        let index_name = &index.name;
+        if index.parts.is_empty() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                format!("index '{index_name}' must have at least one part"),
+            ));
+        }
+
+        let mut parts = Vec::with_capacity(index.parts.len());
+        for part in index.parts.iter() {
+            let (_, name, is_nullable) = names
+                .iter()
+                .find(|(ident, ..)| part.get_ident() == Some(*ident))
+                .ok_or_else(|| {
+                    syn::Error::new_spanned(
+                        part,
+                        format!("index '{index_name}' refers to an unknown field"),
+                    )
+                })?;
+            let nullable = is_nullable.then(|| quote! { .is_nullable(true) });
+            parts.push(quote! { #tarantool::index::Part::field(#name) #nullable });
+        }
+
+        let unique = index.unique.map(|v| quote! { unique: Some(#v), });
+        let index_type = match &index.index_type {
+            Some(t) => {
+                let variant = variant_by_name(INDEX_TYPES, t, "index type", Span::call_site())?;
+                Some(quote! { r#type: Some(#tarantool::index::IndexType::#variant), })
+            }
+            None => None,
+        };
+        indexes.push(quote! {
+            (
+                #index_name,
+                #tarantool::index::IndexOptions {
+                    parts: Some(vec![#(#parts),*]),
+                    #unique
+                    #index_type
+                    ..Default::default()
+                },
+            )
+        });
+    }
+
+    let name = &args.ident;
+    let (impl_generics, ty_generics, where_clause) = args.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics #tarantool::space::SpaceFormat for #name #ty_generics #where_clause {
+            fn format() -> ::std::vec::Vec<#tarantool::space::Field> {
+                vec![#(#format),*]
+            }
+
+            fn indexes() -> ::std::vec::Vec<(&'static str, #tarantool::index::IndexOptions)> {
+                vec![#(#indexes),*]
+            }
+        }
+    })
+}
@@ -0,0 +1,460 @@
+//! A broadcast channel from a cord (typically tx thread) to arbitrary threads.
+//!
+//! This is the opposite direction to the `cbus` channels: there are
+//! potentially many producers in the cord and many consumers in any other
+//! threads (OS threads or async tasks of any runtime, e.g. tokio). Every
+//! [`Receiver`] gets every message sent after it was subscribed.
+//!
+//! Unlike the `cbus` channels this one isn't built on cbus. A cbus pipe
+//! delivers messages into a cord, where they are handled by an endpoint
+//! fiber, while the receivers of this channel are plain threads and async
+//! tasks which have no cbus endpoint. They are woken up via a [`Condvar`] or
+//! a [`Waker`] instead, which never blocks the sending cord.
+//!
+//! The channel has a bounded buffer. Sending never blocks the cord, instead
+//! when the buffer is full the oldest message is discarded. A receiver which
+//! lags behind by more than the buffer capacity will get a
+//! [`RecvError::Lagged`] error with the number of skipped messages, after
+//! which it continues receiving from the oldest message still in the buffer.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use tarantool::broadcast;
+//! use std::num::NonZeroUsize;
+//!
+//! let (tx, mut rx) = broadcast::channel::<String>(NonZeroUsize::new(16).unwrap());
+//! let worker = std::thread::spawn(move || {
+//!     while let Ok(config) = rx.recv() {
+//!         println!("new config: {config}");
+//!     }
+//! });
+//!
+//! // In the tx thread, doesn't block.
+//! tx.send("log_level = debug".into()).unwrap();
+//! drop(tx);
+//! worker.join().unwrap();
+//! ```
+use std::collections::VecDeque;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, thiserror::Error)]
+#[error("all receivers are dropped")]
+pub struct SendError<T>(pub T);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RecvError {
+    #[error("all senders are disconnected")]
+    Closed,
+    #[error("receiver lagged behind, {0} messages were skipped")]
+    Lagged(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum TryRecvError {
+    #[error("channel is empty")]
+    Empty,
+    #[error("all senders are disconnected")]
+    Closed,
+    #[error("receiver lagged behind, {0} messages were skipped")]
+    Lagged(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum RecvTimeoutError {
+    #[error("timed out waiting on channel")]
+    Timeout,
+    #[error("all senders are disconnected")]
+    Closed,
+    #[error("receiver lagged behind, {0} messages were skipped")]
+    Lagged(u64),
+}
+
+/// The state shared between all the senders and receivers.
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    /// Wakes up the receivers blocked in [`Receiver::recv`].
+    condvar: Condvar,
+}
+
+struct State<T> {
+    /// The last `cap` sent messages.
+    buffer: VecDeque<T>,
+    cap: usize,
+    /// Sequence number of the first message in `buffer`.
+    head: u64,
+    sender_count: usize,
+    receiver_count: usize,
+    /// Wakers of the receivers blocked in [`Receiver::recv_async`].
+    wakers: Vec<Waker>,
+}
+
+impl<T> State<T> {
+    /// Sequence number of the next sent message.
+    #[inline(always)]
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+}
+
+impl<T> Shared<T> {
+    #[inline(always)]
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // The state is never left inconsistent (the only user code called
+        // under the lock is `T::clone`), so poisoning can be ignored.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Wakes up all the receivers waiting for a new message.
+    fn notify_all(&self, mut state: MutexGuard<'_, State<T>>) {
+        let wakers = std::mem::take(&mut state.wakers);
+        drop(state);
+        self.condvar.notify_all();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Creates a new broadcast channel, returning the sender and the first receiver.
+/// More receivers can be created via [`Sender::subscribe`] or
+/// [`Receiver::clone`].
+///
+/// # Arguments
+///
+/// * `cap`: the maximum number of messages retained for the receivers which
+///   lag behind.
+pub fn channel<T: Clone>(cap: NonZeroUsize) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            buffer: VecDeque::with_capacity(cap.get()),
+            cap: cap.get(),
+            head: 0,
+            sender_count: 1,
+            receiver_count: 1,
+            wakers: Vec::new(),
+        }),
+        condvar: Condvar::new(),
+    });
+    let r = Receiver {
+        shared: Arc::clone(&shared),
+        next: 0,
+    };
+    (Sender { shared }, r)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Sender
+////////////////////////////////////////////////////////////////////////////////
+
+/// A sending-half of broadcast channel. Is meant to be used in the cord, but
+/// works in any thread. Sending never blocks and never yields.
+/// Clone the sender if you need one more producer.
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value to all the current receivers. Returns the number of
+    /// receivers the value was sent to.
+    ///
+    /// If the buffer is full the oldest message is discarded, receivers which
+    /// haven't seen it yet will get a [`RecvError::Lagged`].
+    ///
+    /// Returns an error if there are no receivers, in this case the value is
+    /// returned back.
+    pub fn send(&self, msg: T) -> Result<usize, SendError<T>> {
+        let mut state = self.shared.lock();
+        if state.receiver_count == 0 {
+            return Err(SendError(msg));
+        }
+
+        if state.buffer.len() == state.cap {
+            state.buffer.pop_front();
+            state.head += 1;
+        }
+        state.buffer.push_back(msg);
+        let receiver_count = state.receiver_count;
+
+        self.shared.notify_all(state);
+        Ok(receiver_count)
+    }
+
+    /// Creates a new receiver which will get all the messages sent after this
+    /// call.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut state = self.shared.lock();
+        state.receiver_count += 1;
+        Receiver {
+            shared: Arc::clone(&self.shared),
+            next: state.tail(),
+        }
+    }
+
+    /// Returns the number of currently existing receivers.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.lock().receiver_count
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().sender_count += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.sender_count -= 1;
+        if state.sender_count == 0 {
+            // Wake up the receivers, so that they can see the channel is closed.
+            self.shared.notify_all(state);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Receiver
+////////////////////////////////////////////////////////////////////////////////
+
+/// A receiving-half of broadcast channel. Can be used in any thread, but the
+/// blocking methods must not be called in the cord, because they would block
+/// the whole cord. Use [`Receiver::recv_async`] in async runtimes.
+///
+/// Cloning the receiver creates a new one which starts at the same position
+/// in the channel.
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    /// Sequence number of the next message to be received.
+    next: u64,
+}
+
+impl<T: Clone> Receiver<T> {
+    fn try_recv_locked(&mut self, state: &State<T>) -> Result<T, TryRecvError> {
+        if self.next < state.head {
+            let skipped = state.head - self.next;
+            self.next = state.head;
+            return Err(TryRecvError::Lagged(skipped));
+        }
+
+        if self.next < state.tail() {
+            let msg = state.buffer[(self.next - state.head) as usize].clone();
+            self.next += 1;
+            return Ok(msg);
+        }
+
+        if state.sender_count == 0 {
+            return Err(TryRecvError::Closed);
+        }
+
+        Err(TryRecvError::Empty)
+    }
+
+    /// Attempts to receive a value without blocking.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let shared = Arc::clone(&self.shared);
+        let state = shared.lock();
+        self.try_recv_locked(&state)
+    }
+
+    /// Blocks the current thread until a value is received.
+    ///
+    /// Returns [`RecvError::Closed`] once all senders are dropped and all the
+    /// buffered messages are received.
+    ///
+    /// **Must not be called in the cord**.
+    pub fn recv(&mut self) -> Result<T, RecvError> {
+        let shared = Arc::clone(&self.shared);
+        let mut state = shared.lock();
+        loop {
+            match self.try_recv_locked(&state) {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Closed) => return Err(RecvError::Closed),
+                Err(TryRecvError::Lagged(n)) => return Err(RecvError::Lagged(n)),
+                Err(TryRecvError::Empty) => {}
+            }
+            state = shared
+                .condvar
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+    }
+
+    /// Blocks the current thread until a value is received or `timeout`
+    /// passes.
+    ///
+    /// **Must not be called in the cord**.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = Instant::now() + timeout;
+        let shared = Arc::clone(&self.shared);
+        let mut state = shared.lock();
+        loop {
+            match self.try_recv_locked(&state) {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Closed) => return Err(RecvTimeoutError::Closed),
+                Err(TryRecvError::Lagged(n)) => return Err(RecvTimeoutError::Lagged(n)),
+                Err(TryRecvError::Empty) => {}
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+            state = shared
+                .condvar
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Receives a value asynchronously. Works with any async runtime.
+    #[inline(always)]
+    pub fn recv_async(&mut self) -> Recv<'_, T> {
+        Recv { receiver: self }
+    }
+
+    /// Returns the number of messages this receiver hasn't received yet.
+    pub fn len(&self) -> usize {
+        let state = self.shared.lock();
+        (state.tail() - self.next.max(state.head)) as usize
+    }
+
+    /// Return true if there are no messages for this receiver to receive.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().receiver_count += 1;
+        Self {
+            shared: Arc::clone(&self.shared),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_count -= 1;
+    }
+}
+
+/// Future returned by [`Receiver::recv_async`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Recv<'a, T> {
+    receiver: &'a mut Receiver<T>,
+}
+
+impl<T: Clone> Future for Recv<'_, T> {
+    type Output = Result<T, RecvError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let receiver = &mut *self.get_mut().receiver;
+        let shared = Arc::clone(&receiver.shared);
+        let mut state = shared.lock();
+        match receiver.try_recv_locked(&state) {
+            Ok(msg) => Poll::Ready(Ok(msg)),
+            Err(TryRecvError::Closed) => Poll::Ready(Err(RecvError::Closed)),
+            Err(TryRecvError::Lagged(n)) => Poll::Ready(Err(RecvError::Lagged(n))),
+            Err(TryRecvError::Empty) => {
+                if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                    state.wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn cap(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn broadcast_to_many_threads() {
+        let (tx, rx) = channel::<i32>(cap(1000));
+
+        let threads: Vec<_> = (0..3)
+            .map(|_| {
+                let mut rx = rx.clone();
+                thread::spawn(move || {
+                    let mut res = vec![];
+                    while let Ok(msg) = rx.recv() {
+                        res.push(msg);
+                    }
+                    res
+                })
+            })
+            .collect();
+        let async_thread = {
+            let mut rx = tx.subscribe();
+            thread::spawn(move || {
+                futures::executor::block_on(async move {
+                    let mut res = vec![];
+                    while let Ok(msg) = rx.recv_async().await {
+                        res.push(msg);
+                    }
+                    res
+                })
+            })
+        };
+        drop(rx);
+
+        for i in 0..100 {
+            assert_eq!(tx.send(i).unwrap(), 4);
+        }
+        drop(tx);
+
+        for jh in threads {
+            assert_eq!(jh.join().unwrap(), (0..100).collect::<Vec<_>>());
+        }
+        assert_eq!(async_thread.join().unwrap(), (0..100).collect::<Vec<_>>());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn broadcast_lagged() {
+        let (tx, mut rx) = channel(cap(2));
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.len(), 2);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Lagged(3)));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        let mut late = tx.subscribe();
+        assert!(late.is_empty());
+        drop(tx);
+        assert_eq!(late.recv(), Err(RecvError::Closed));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn broadcast_no_receivers() {
+        let (tx, rx) = channel(cap(1));
+        assert_eq!(tx.receiver_count(), 1);
+        drop(rx);
+        assert_eq!(tx.receiver_count(), 0);
+        assert!(matches!(tx.send(1), Err(SendError(1))));
+    }
+}
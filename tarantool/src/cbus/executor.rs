@@ -0,0 +1,597 @@
+//! Running closures in the cord (typically tx thread) from arbitrary threads.
+//!
+//! An [`Executor`] is created in the cord. It occupies a cbus endpoint and
+//! a fiber for the cbus loop. An [`ExecutorHandle`] is `Send + Clone` and can
+//! be used from any thread to submit closures (or futures) which are run in
+//! a separate fiber each. The result is returned to the calling thread either
+//! by blocking it ([`ExecutorHandle::run`]) or asynchronously
+//! ([`ExecutorHandle::run_async`]), which works with any async runtime.
+//!
+//! The number of jobs which are submitted but not yet finished is bounded.
+//! Once the limit is reached, the submitting threads block (or wait
+//! asynchronously) until some of the jobs finish.
+//!
+//! When the executor is shut down (explicitly, by dropping it or on tarantool
+//! shutdown) no new jobs are accepted. The jobs which are already submitted
+//! are given some time to finish, after which the callers still waiting for
+//! results get [`ExecutorError::Shutdown`].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! #[cfg(feature = "picodata")] {
+//! use tarantool::cbus::executor::Executor;
+//! use tarantool::space::Space;
+//! use std::num::NonZeroUsize;
+//!
+//! // In the tx thread.
+//! let executor = Executor::new("my_executor", NonZeroUsize::new(128).unwrap()).unwrap();
+//! let handle = executor.handle();
+//!
+//! std::thread::spawn(move || {
+//!     let len = handle
+//!         .run(|| Space::find("users").map(|s| s.len().unwrap()))
+//!         .unwrap();
+//!     println!("there are {len:?} users");
+//! });
+//! }
+//! ```
+use super::{Endpoint, LCPipe, Message};
+use crate::fiber::unwind::panic_message;
+use crate::fiber::{self, FiberId};
+use std::collections::HashMap;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, Weak};
+use std::task::{Poll, Waker};
+use std::time::Duration;
+
+/// How long [`Executor`] waits for the submitted jobs to finish when it's
+/// dropped or tarantool shuts down. See also [`Executor::shutdown`].
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ExecutorError {
+    #[error("executor is shut down")]
+    Shutdown,
+    #[error("job was dropped without being executed")]
+    Dropped,
+    #[error("job panicked: {0}")]
+    Panicked(String),
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Shared
+////////////////////////////////////////////////////////////////////////////////
+
+/// The state shared between the executor and all the handles.
+struct Shared {
+    state: Mutex<State>,
+    /// Wakes up the threads blocked waiting for a free slot.
+    condvar: Condvar,
+}
+
+struct State {
+    is_shutdown: bool,
+    max_in_flight: usize,
+    next_id: u64,
+    /// Jobs which were submitted but haven't finished yet.
+    pending: HashMap<u64, Arc<dyn Abort>>,
+    /// Wakers of the async submitters waiting for a free slot.
+    wakers: Vec<Waker>,
+}
+
+impl Shared {
+    #[inline(always)]
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // No user code is called under the lock, so poisoning can be ignored.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Removes the job from the pending list and wakes up the submitters.
+    fn finish(&self, id: u64) {
+        let mut state = self.lock();
+        if state.pending.remove(&id).is_none() {
+            // Has already been aborted.
+            return;
+        }
+        self.notify_all(state);
+    }
+
+    fn notify_all(&self, mut state: MutexGuard<'_, State>) {
+        let wakers = std::mem::take(&mut state.wakers);
+        drop(state);
+        self.condvar.notify_all();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Stops the executor. Must be called in the cord, **yields**.
+fn shutdown(shared: &Shared, fiber_id: FiberId, timeout: Duration) {
+    let mut state = shared.lock();
+    if state.is_shutdown {
+        return;
+    }
+    state.is_shutdown = true;
+    // Submitters waiting for a free slot will see the executor is shut down.
+    shared.notify_all(state);
+
+    // Let the already submitted jobs finish. The cbus loop must be running
+    // for them to be delivered.
+    let deadline = fiber::clock().saturating_add(timeout);
+    while !shared.lock().pending.is_empty() && fiber::clock() < deadline {
+        fiber::sleep(Duration::from_millis(1));
+    }
+
+    let pending = std::mem::take(&mut shared.lock().pending);
+    for job in pending.values() {
+        job.abort();
+    }
+
+    fiber::cancel(fiber_id);
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Slot
+////////////////////////////////////////////////////////////////////////////////
+
+/// A place for the job result to be passed to the submitter.
+struct Slot<T> {
+    state: Mutex<SlotState<T>>,
+    condvar: Condvar,
+}
+
+enum SlotState<T> {
+    Pending(Option<Waker>),
+    Ready(Result<T, ExecutorError>),
+    Taken,
+}
+
+impl<T> Slot<T> {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(SlotState::Pending(None)),
+            condvar: Condvar::new(),
+        }
+    }
+
+    #[inline(always)]
+    fn lock(&self) -> MutexGuard<'_, SlotState<T>> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sets the result unless it's already set.
+    fn complete(&self, res: Result<T, ExecutorError>) {
+        let mut state = self.lock();
+        let SlotState::Pending(waker) = &mut *state else {
+            return;
+        };
+        let waker = waker.take();
+        *state = SlotState::Ready(res);
+        drop(state);
+        self.condvar.notify_one();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn take(state: &mut SlotState<T>) -> Result<T, ExecutorError> {
+        match std::mem::replace(state, SlotState::Taken) {
+            SlotState::Ready(res) => res,
+            _ => unreachable!("result is only taken once after it's ready"),
+        }
+    }
+
+    /// Blocks the current thread until the result is set.
+    fn wait(&self) -> Result<T, ExecutorError> {
+        let mut state = self.lock();
+        while let SlotState::Pending(_) = &*state {
+            state = self.condvar.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        Self::take(&mut state)
+    }
+
+    fn poll(&self, waker: &Waker) -> Poll<Result<T, ExecutorError>> {
+        let mut state = self.lock();
+        if let SlotState::Pending(w) = &mut *state {
+            *w = Some(waker.clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(Self::take(&mut state))
+    }
+}
+
+trait Abort: Send + Sync {
+    fn abort(&self);
+}
+
+impl<T: Send> Abort for Slot<T> {
+    fn abort(&self) {
+        self.complete(Err(ExecutorError::Shutdown));
+    }
+}
+
+/// Is passed along with the job to the cord. Sets the result of the job or
+/// [`ExecutorError::Dropped`] if it's dropped without being executed.
+struct Completer<T: Send> {
+    slot: Arc<Slot<T>>,
+    shared: Arc<Shared>,
+    id: u64,
+}
+
+impl<T: Send> Completer<T> {
+    fn run(self, f: impl FnOnce() -> T) {
+        let res = std::panic::catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+            let msg = panic_message(&*e).unwrap_or("Box<dyn Any>");
+            ExecutorError::Panicked(msg.into())
+        });
+        self.slot.complete(res);
+    }
+}
+
+impl<T: Send> Drop for Completer<T> {
+    fn drop(&mut self) {
+        self.slot.complete(Err(ExecutorError::Dropped));
+        self.shared.finish(self.id);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Executor
+////////////////////////////////////////////////////////////////////////////////
+
+/// Runs closures submitted via [`ExecutorHandle`]s in the cord. Must be
+/// created and dropped in the cord.
+///
+/// The executor is shut down when it's dropped (see [`Executor::shutdown`])
+/// or on tarantool shutdown whichever happens first.
+pub struct Executor {
+    shared: Arc<Shared>,
+    endpoint_name: String,
+    fiber_id: FiberId,
+}
+
+impl Executor {
+    /// Creates a new executor with a cbus endpoint named `endpoint_name`.
+    /// At most `max_in_flight` jobs can be submitted but not yet finished at
+    /// any time.
+    ///
+    /// Returns an error if the endpoint with the given name already exists.
+    pub fn new(endpoint_name: &str, max_in_flight: NonZeroUsize) -> crate::Result<Self> {
+        let endpoint = Endpoint::new(endpoint_name).map_err(crate::error::Error::other)?;
+        let fiber_id = fiber::Builder::new()
+            .name(format!("cbus_executor:{endpoint_name}"))
+            .func(move || endpoint.cbus_loop())
+            .start_non_joinable()?;
+
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                is_shutdown: false,
+                max_in_flight: max_in_flight.get(),
+                next_id: 0,
+                pending: HashMap::new(),
+                wakers: Vec::new(),
+            }),
+            condvar: Condvar::new(),
+        });
+
+        let weak = Arc::downgrade(&shared);
+        let res = crate::trigger::on_shutdown(move || {
+            if let Some(shared) = Weak::upgrade(&weak) {
+                shutdown(&shared, fiber_id, DEFAULT_SHUTDOWN_TIMEOUT);
+            }
+        });
+        if let Err(e) = res {
+            fiber::cancel(fiber_id);
+            return Err(e.into());
+        }
+
+        Ok(Self {
+            shared,
+            endpoint_name: endpoint_name.into(),
+            fiber_id,
+        })
+    }
+
+    /// Returns a new handle which can be used to submit jobs from other threads.
+    #[inline]
+    pub fn handle(&self) -> ExecutorHandle {
+        ExecutorHandle {
+            shared: Arc::clone(&self.shared),
+            lcpipe: Mutex::new(LCPipe::new(&self.endpoint_name)),
+            endpoint_name: self.endpoint_name.clone(),
+        }
+    }
+
+    /// Returns the number of submitted jobs which haven't finished yet.
+    #[inline]
+    pub fn in_flight(&self) -> usize {
+        self.shared.lock().pending.len()
+    }
+
+    /// Stops accepting new jobs and waits at most `timeout` for the submitted
+    /// jobs to finish. The callers still waiting for results after that get
+    /// [`ExecutorError::Shutdown`].
+    ///
+    /// This function **yields**.
+    #[inline]
+    pub fn shutdown(self, timeout: Duration) {
+        shutdown(&self.shared, self.fiber_id, timeout);
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        shutdown(&self.shared, self.fiber_id, DEFAULT_SHUTDOWN_TIMEOUT);
+    }
+}
+
+impl std::fmt::Debug for Executor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Executor")
+            .field("endpoint_name", &self.endpoint_name)
+            .field("fiber_id", &self.fiber_id)
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ExecutorHandle
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle for submitting jobs to an [`Executor`] from any thread.
+///
+/// The blocking methods must not be called in the cord, because they would
+/// block the whole cord.
+pub struct ExecutorHandle {
+    shared: Arc<Shared>,
+    /// An LCPipe instance, unique for each handle.
+    lcpipe: Mutex<LCPipe>,
+    endpoint_name: String,
+}
+
+impl Clone for ExecutorHandle {
+    fn clone(&self) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            lcpipe: Mutex::new(LCPipe::new(&self.endpoint_name)),
+            endpoint_name: self.endpoint_name.clone(),
+        }
+    }
+}
+
+impl ExecutorHandle {
+    /// Submits the job if there's a free slot. If `waker` is `None` blocks
+    /// the current thread until there is one.
+    fn poll_submit<F, T>(
+        &self,
+        f: &mut Option<F>,
+        waker: Option<&Waker>,
+    ) -> Poll<Result<Arc<Slot<T>>, ExecutorError>>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let mut state = self.shared.lock();
+        loop {
+            if state.is_shutdown {
+                return Poll::Ready(Err(ExecutorError::Shutdown));
+            }
+            if state.pending.len() < state.max_in_flight {
+                break;
+            }
+            if let Some(waker) = waker {
+                if !state.wakers.iter().any(|w| w.will_wake(waker)) {
+                    state.wakers.push(waker.clone());
+                }
+                return Poll::Pending;
+            }
+            state = self
+                .shared
+                .condvar
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+
+        let f = f.take().expect("job is submitted at most once");
+        let id = state.next_id;
+        state.next_id += 1;
+        let slot = Arc::new(Slot::new());
+        state.pending.insert(id, slot.clone());
+        let completer = Completer {
+            slot: slot.clone(),
+            shared: Arc::clone(&self.shared),
+            id,
+        };
+
+        let msg = Message::new(move || {
+            // The job is run in a separate fiber, so that it doesn't block
+            // the cbus loop.
+            let res = fiber::Builder::new()
+                .name("cbus_executor_job")
+                .func(move || completer.run(f))
+                .start_non_joinable();
+            if let Err(e) = res {
+                // The completer is dropped, the submitter gets `Dropped`.
+                crate::say_error!("failed to start a fiber for cbus executor job: {e}");
+            }
+        });
+        // The message is pushed under the lock, so that the executor cannot
+        // be shut down in between the check above and the push.
+        self.lcpipe
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push_message(msg);
+
+        Poll::Ready(Ok(slot))
+    }
+
+    /// Runs `f` in a new fiber in the cord and blocks the current thread
+    /// until it finishes. Returns the result of `f`.
+    ///
+    /// Blocks if there are too many jobs in flight.
+    ///
+    /// Returns an error if
+    /// - the executor is shut down,
+    /// - `f` panics.
+    ///
+    /// **Must not be called in the cord**.
+    pub fn run<F, T>(&self, f: F) -> Result<T, ExecutorError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let Poll::Ready(slot) = self.poll_submit(&mut Some(f), None) else {
+            unreachable!("blocking submit never returns Pending");
+        };
+        slot?.wait()
+    }
+
+    /// Async version of [`Self::run`]. Works with any async runtime.
+    pub async fn run_async<F, T>(&self, f: F) -> Result<T, ExecutorError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let mut f = Some(f);
+        let slot =
+            futures::future::poll_fn(|cx| self.poll_submit(&mut f, Some(cx.waker()))).await?;
+        futures::future::poll_fn(|cx| slot.poll(cx.waker())).await
+    }
+
+    /// Calls `f` in a new fiber in the cord and runs the returned future to
+    /// completion via [`fiber::block_on`]. Blocks the current thread until
+    /// then.
+    ///
+    /// The future itself doesn't need to be `Send`, because it's created in
+    /// the cord.
+    ///
+    /// **Must not be called in the cord**.
+    #[inline]
+    pub fn run_future<F, Fut>(&self, f: F) -> Result<Fut::Output, ExecutorError>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future + 'static,
+        Fut::Output: Send + 'static,
+    {
+        self.run(move || fiber::block_on(f()))
+    }
+
+    /// Async version of [`Self::run_future`]. Works with any async runtime.
+    #[inline]
+    pub async fn run_future_async<F, Fut>(&self, f: F) -> Result<Fut::Output, ExecutorError>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future + 'static,
+        Fut::Output: Send + 'static,
+    {
+        self.run_async(move || fiber::block_on(f())).await
+    }
+
+    /// Returns `true` if the executor is shut down and no more jobs can be
+    /// submitted.
+    #[inline]
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.lock().is_shutdown
+    }
+}
+
+impl std::fmt::Debug for ExecutorHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ExecutorHandle")
+            .field("endpoint_name", &self.endpoint_name)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn cap(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn executor_run() {
+        let executor = Executor::new("executor_run", cap(2)).unwrap();
+        let tx_thread = thread::current().id();
+
+        let threads: Vec<_> = (0..3)
+            .map(|i| {
+                let handle = executor.handle();
+                thread::spawn(move || {
+                    let mut res = vec![];
+                    for j in 0..10 {
+                        let thread_id = handle.run(|| thread::current().id()).unwrap();
+                        res.push(thread_id);
+                        let v = handle
+                            .run_future(move || async move {
+                                fiber::r#async::sleep(Duration::from_millis(1)).await;
+                                i * 10 + j
+                            })
+                            .unwrap();
+                        assert_eq!(v, i * 10 + j);
+                    }
+                    let e = handle.run(|| panic!("oops")).unwrap_err();
+                    assert_eq!(e, ExecutorError::Panicked("oops".into()));
+                    res
+                })
+            })
+            .collect();
+
+        while !threads.iter().all(|t| t.is_finished()) {
+            assert!(executor.in_flight() <= 2);
+            fiber::sleep(Duration::from_millis(1));
+        }
+        for t in threads {
+            assert!(t.join().unwrap().iter().all(|id| *id == tx_thread));
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn executor_run_async() {
+        let executor = Executor::new("executor_run_async", cap(8)).unwrap();
+        let handle = executor.handle();
+
+        let thread = thread::spawn(move || {
+            futures::executor::block_on(async {
+                let a = handle.run_async(|| 1);
+                let b = handle.run_future_async(|| async { 2 });
+                let (a, b) = futures::join!(a, b);
+                a.unwrap() + b.unwrap()
+            })
+        });
+        while !thread.is_finished() {
+            fiber::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(thread.join().unwrap(), 3);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn executor_shutdown() {
+        let executor = Executor::new("executor_shutdown", cap(8)).unwrap();
+        let handle = executor.handle();
+
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let thread = thread::spawn(move || {
+            let res = handle.run(move || {
+                started_tx.send(()).unwrap();
+                fiber::sleep(Duration::from_secs(1));
+            });
+            (res, handle.run(|| ()))
+        });
+        while started_rx.try_recv().is_err() {
+            fiber::sleep(Duration::from_millis(1));
+        }
+
+        executor.shutdown(Duration::from_millis(10));
+        let (first, second) = thread.join().unwrap();
+        assert_eq!(first, Err(ExecutorError::Shutdown));
+        assert_eq!(second, Err(ExecutorError::Shutdown));
+    }
+}
@@ -48,7 +48,16 @@
 //! Unlock consumer always means that there is a new data for consuming, but consumer not always locking
 //! on try to receiver, if data is already available - lock is redundant.
 //! For implementing a consumer lock and unlock a [`crate::fiber::Cond`] is used.
+//!
+//! Receivers of [`unbounded`] and [`sync`] channels can also be used in the
+//! fiber based async runtime (see [`crate::fiber::block_on`]) either via
+//! `recv_async` or as a [`futures::Stream`].
+//!
+//! For the opposite direction, i.e. from the cord to arbitrary threads, see
+//! [`broadcast`](crate::broadcast) channel. To run closures in the cord and
+//! get the results back see [`executor`].
 
+pub mod executor;
 pub mod oneshot;
 pub mod sync;
 pub mod unbounded;
@@ -58,6 +67,7 @@ use crate::ffi::tarantool::{
     cbus_endpoint_delete, cbus_endpoint_new, cbus_loop, lcpipe_delete, lcpipe_new, lcpipe_push_now,
 };
 use crate::fiber::Cond;
+use std::cell::RefCell;
 use std::ffi::CString;
 use std::os::raw::c_void;
 use std::ptr;
@@ -68,6 +78,22 @@ pub enum RecvError {
     Disconnected,
 }
 
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TryRecvError {
+    #[error("channel is empty")]
+    Empty,
+    #[error("sending half of a channel is disconnected")]
+    Disconnected,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum RecvTimeoutError {
+    #[error("timed out waiting on channel")]
+    Timeout,
+    #[error("sending half of a channel is disconnected")]
+    Disconnected,
+}
+
 #[derive(Debug, thiserror::Error)]
 #[error("receiving half of a channel is disconnected")]
 pub struct SendError<T>(pub T);
@@ -233,6 +259,43 @@ unsafe impl Send for UnsafeCond {}
 
 unsafe impl Sync for UnsafeCond {}
 
+/// This is a wrapper over a [`std::task::Waker`] of a task running in the
+/// fiber based async runtime (see [`crate::fiber::block_on`]) for sending it
+/// between threads.
+///
+/// # Safety.
+/// Same as for [`UnsafeCond`], `UnsafeTaskWaker` must be dereferenced and
+/// dropped only in the cord thread.
+#[derive(Default)]
+struct UnsafeTaskWaker(RefCell<Option<std::task::Waker>>);
+
+impl UnsafeTaskWaker {
+    /// Register a waker to be woken up by [`UnsafeTaskWaker::wake`].
+    ///
+    /// # Safety.
+    /// Must only be called in the cord thread.
+    unsafe fn register(&self, waker: &std::task::Waker) {
+        let mut current = self.0.borrow_mut();
+        if !matches!(&*current, Some(w) if w.will_wake(waker)) {
+            *current = Some(waker.clone());
+        }
+    }
+
+    /// Wake up the registered waker if any.
+    ///
+    /// # Safety.
+    /// Must only be called in the cord thread.
+    unsafe fn wake(&self) {
+        if let Some(waker) = self.0.borrow_mut().take() {
+            waker.wake();
+        }
+    }
+}
+
+unsafe impl Send for UnsafeTaskWaker {}
+
+unsafe impl Sync for UnsafeTaskWaker {}
+
 #[cfg(feature = "internal_test")]
 mod tests {
     use crate::cbus;
@@ -1,11 +1,14 @@
 #![cfg(any(feature = "tokio_components", doc))]
 
-use crate::cbus::{LCPipe, RecvError, SendError};
+use crate::cbus::{LCPipe, RecvError, RecvTimeoutError, SendError, TryRecvError};
+use crate::fiber;
 use crate::fiber::Cond;
 use std::cell::RefCell;
 use std::num::NonZeroUsize;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
 use std::thread;
 use std::time::Duration;
 use tokio::sync::Notify;
@@ -277,6 +280,79 @@ impl<T> EndpointReceiver<T> {
         }
     }
 
+    /// Attempts to receive a value without blocking, returns a
+    /// [`TryRecvError::Disconnected`] when all of producers are dropped and
+    /// the buffer is empty.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut msg = self.chan.list.pop();
+        if msg.is_none() && self.chan.disconnected.load(Ordering::Acquire) {
+            // A message could have been sent right before the disconnect.
+            msg = self.chan.list.pop();
+            if msg.is_none() {
+                return Err(TryRecvError::Disconnected);
+            }
+        }
+
+        match msg {
+            Some(msg) => {
+                self.task_waker.wakeup_one();
+                Ok(msg)
+            }
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Like [`EndpointReceiver::receive`] but returns a
+    /// [`RecvTimeoutError::Timeout`] if no value is received within `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = fiber::clock().saturating_add(timeout);
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+
+            if fiber::clock() >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+
+            self.cord_waker
+                .as_ref()
+                .expect("unreachable: waker must exists")
+                .wait();
+        }
+    }
+
+    /// Attempts to receive a value, registering the current task for a
+    /// wakeup if there's none. Must only be polled within the
+    /// [`fiber::block_on`] runtime.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Poll::Ready(Ok(msg)),
+                Err(TryRecvError::Disconnected) => {
+                    return Poll::Ready(Err(RecvError::Disconnected))
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let cord_waker = self
+                .cord_waker
+                .as_ref()
+                .expect("unreachable: waker must exists");
+            if cord_waker.poll_wait(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    /// Async version of [`EndpointReceiver::receive`]. Must only be used
+    /// within the [`fiber::block_on`] runtime.
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        futures::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
     /// Return message count in receiver buffer.
     pub fn len(&self) -> usize {
         self.chan.list.len()
@@ -288,13 +364,25 @@ impl<T> EndpointReceiver<T> {
     }
 }
 
+/// The stream ends when all of producers are dropped.
+impl<T> futures::Stream for EndpointReceiver<T> {
+    type Item = T;
+
+    #[inline(always)]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_recv(cx).map(Result::ok)
+    }
+}
+
 #[cfg(feature = "internal_test")]
 mod tests {
     use crate::cbus::sync;
     use crate::cbus::tests::run_cbus_endpoint;
-    use crate::cbus::RecvError;
+    use crate::cbus::{RecvError, RecvTimeoutError, TryRecvError};
     use crate::fiber;
+    use crate::fiber::r#async::timeout::IntoTimeout as _;
     use crate::fiber::{check_yield, YieldResult};
+    use futures::StreamExt;
     use std::num::NonZeroUsize;
     use std::sync::atomic::{AtomicU64, Ordering};
     use std::thread;
@@ -551,4 +639,75 @@ mod tests {
         tokio_rt.join().unwrap();
         assert!(fiber::cancel(cbus_fiber_id));
     }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn try_recv_and_timeout() {
+        let cbus_fiber_id = run_cbus_endpoint("tokio_try_recv_and_timeout");
+
+        let cap = NonZeroUsize::new(1).unwrap();
+        let (tx, rx) = sync::tokio::channel("tokio_try_recv_and_timeout", cap);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        let tokio_rt = thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async move {
+                    _ = tx.send(1).await;
+                });
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(1));
+        tokio_rt.join().unwrap();
+
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert!(fiber::cancel(cbus_fiber_id));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn async_recv() {
+        let cbus_fiber_id = run_cbus_endpoint("tokio_async_recv");
+
+        let cap = NonZeroUsize::new(10).unwrap();
+        let (tx, mut rx) = sync::tokio::channel("tokio_async_recv", cap);
+
+        fiber::block_on(async {
+            let res = rx.recv_async().timeout(Duration::from_millis(10)).await;
+            assert!(res.is_err());
+        });
+
+        let tokio_rt = thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async move {
+                    for i in 0..100 {
+                        _ = tx.send(i).await;
+                        if i % 10 == 0 {
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                        }
+                    }
+                });
+        });
+
+        assert!(matches!(fiber::block_on(rx.recv_async()), Ok(0)));
+        let rest: Vec<_> = fiber::block_on((&mut rx).collect());
+        assert_eq!(rest, (1..100).collect::<Vec<_>>());
+        assert!(matches!(
+            fiber::block_on(rx.recv_async()),
+            Err(RecvError::Disconnected)
+        ));
+
+        tokio_rt.join().unwrap();
+        assert!(fiber::cancel(cbus_fiber_id));
+    }
 }
@@ -1,15 +1,25 @@
-use super::{LCPipe, Message, SendError, UnsafeCond};
-use crate::cbus::RecvError;
+use super::{LCPipe, Message, SendError, UnsafeCond, UnsafeTaskWaker};
+use crate::cbus::{RecvError, RecvTimeoutError, TryRecvError};
+use crate::fiber;
+use crate::fiber::r#async::context::ContextExt;
 use crate::fiber::Cond;
 use std::cell::RefCell;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
 use std::time::Duration;
 
+/// How long the receiver waits for a wakeup before checking the channel
+/// state again.
+const WAKEUP_CHECK_INTERVAL: Duration = Duration::from_millis(1);
+
 /// A synchronization component between producers and a consumer.
 pub(super) struct Waker {
     /// synchronize a waker, signal when waker is up to date
     condition: Option<Arc<UnsafeCond>>,
+    /// an async task waiting in [`Waker::poll_wait`], woken up along with the condition
+    task_waker: Arc<UnsafeTaskWaker>,
     /// indicate that waker already up to date
     woken: AtomicBool,
 }
@@ -18,16 +28,21 @@ impl Waker {
     pub(super) fn new(cond: Cond) -> Self {
         Self {
             condition: Some(Arc::new(UnsafeCond(cond))),
+            task_waker: Arc::default(),
             woken: AtomicBool::new(false),
         }
     }
 
-    /// Send wakeup signal to a [`Waker::wait`] caller.
+    /// Send wakeup signal to a [`Waker::wait`] or [`Waker::poll_wait`] caller.
     pub(super) fn force_wakeup(&self, cond: Arc<UnsafeCond>, pipe: &mut LCPipe) {
+        let task_waker = Arc::clone(&self.task_waker);
         let msg = Message::new(move || {
-            // SAFETY: it is ok to call as_ref() here because this callback will be invoked
-            // on the thread that created the channel with this cond
-            unsafe { (*cond).as_ref().signal() };
+            // SAFETY: it is ok to call as_ref() and wake() here because this callback will be
+            // invoked on the thread that created the channel with this cond
+            unsafe {
+                (*cond).as_ref().signal();
+                task_waker.wake();
+            }
         });
         pipe.push_message(msg);
     }
@@ -61,8 +76,30 @@ impl Waker {
                 .expect("unreachable: condition never empty");
 
             // SAFETY: it is ok to call wait() here because we're on original thread that created the cond
-            unsafe { (**cond).as_ref().wait_timeout(Duration::from_millis(1)) };
+            unsafe { (**cond).as_ref().wait_timeout(WAKEUP_CHECK_INTERVAL) };
+        }
+    }
+
+    /// Async version of [`Waker::wait`]. Must only be polled within the
+    /// [`fiber::block_on`] runtime.
+    pub(super) fn poll_wait(&self, cx: &mut Context<'_>) -> Poll<()> {
+        if self
+            .woken
+            .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+        {
+            return Poll::Ready(());
         }
+
+        // SAFETY: it is ok to call register() here because we're on original thread that created the waker
+        unsafe { self.task_waker.register(cx.waker()) };
+        // Same as in `wait`, the channel state is checked again after a short
+        // timeout even if no wakeup is received.
+        // SAFETY: This is safe as long as the `Context` really is the
+        // `ContextExt`. It's always true within provided `block_on` async
+        // runtime.
+        unsafe { ContextExt::set_deadline(cx, fiber::clock() + WAKEUP_CHECK_INTERVAL) };
+        Poll::Pending
     }
 }
 
@@ -250,6 +287,70 @@ impl<T> EndpointReceiver<T> {
         }
     }
 
+    /// Attempts to receive a value without blocking, returns a
+    /// [`TryRecvError::Disconnected`] when all of producers are dropped and
+    /// the buffer is empty.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        if let Some(msg) = self.chan.list.pop() {
+            return Ok(msg);
+        }
+
+        if self.chan.disconnected.load(Ordering::Acquire) {
+            // A message could have been sent right before the disconnect.
+            return self.chan.list.pop().ok_or(TryRecvError::Disconnected);
+        }
+
+        Err(TryRecvError::Empty)
+    }
+
+    /// Like [`EndpointReceiver::receive`] but returns a
+    /// [`RecvTimeoutError::Timeout`] if no value is received within `timeout`.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, RecvTimeoutError> {
+        let deadline = fiber::clock().saturating_add(timeout);
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Ok(msg),
+                Err(TryRecvError::Disconnected) => return Err(RecvTimeoutError::Disconnected),
+                Err(TryRecvError::Empty) => {}
+            }
+
+            if fiber::clock() >= deadline {
+                return Err(RecvTimeoutError::Timeout);
+            }
+
+            self.waker
+                .as_ref()
+                .expect("unreachable: waker must exists")
+                .wait();
+        }
+    }
+
+    /// Attempts to receive a value, registering the current task for a
+    /// wakeup if there's none. Must only be polled within the
+    /// [`fiber::block_on`] runtime.
+    pub fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Poll::Ready(Ok(msg)),
+                Err(TryRecvError::Disconnected) => {
+                    return Poll::Ready(Err(RecvError::Disconnected))
+                }
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let waker = self.waker.as_ref().expect("unreachable: waker must exists");
+            if waker.poll_wait(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+
+    /// Async version of [`EndpointReceiver::receive`]. Must only be used
+    /// within the [`fiber::block_on`] runtime.
+    pub async fn recv_async(&self) -> Result<T, RecvError> {
+        futures::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
     /// Return message count in receiver buffer.
     pub fn len(&self) -> usize {
         self.chan.list.len()
@@ -261,13 +362,25 @@ impl<T> EndpointReceiver<T> {
     }
 }
 
+/// The stream ends when all of producers are dropped.
+impl<T> futures::Stream for EndpointReceiver<T> {
+    type Item = T;
+
+    #[inline(always)]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.poll_recv(cx).map(Result::ok)
+    }
+}
+
 #[cfg(feature = "internal_test")]
 #[allow(clippy::redundant_pattern_matching)]
 mod tests {
     use super::super::tests::run_cbus_endpoint;
-    use crate::cbus::{unbounded, RecvError};
+    use crate::cbus::{unbounded, RecvError, RecvTimeoutError, TryRecvError};
     use crate::fiber;
+    use crate::fiber::r#async::timeout::IntoTimeout as _;
     use crate::fiber::{check_yield, YieldResult};
+    use futures::StreamExt;
     use std::thread;
     use std::thread::JoinHandle;
     use std::time::Duration;
@@ -374,4 +487,61 @@ mod tests {
         jh3.join().unwrap();
         assert!(fiber::cancel(cbus_fiber_id));
     }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn unbounded_try_recv_and_timeout_test() {
+        let cbus_fiber_id = run_cbus_endpoint("unbounded_try_recv_and_timeout_test");
+
+        let (tx, rx) = unbounded::channel("unbounded_try_recv_and_timeout_test");
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        assert_eq!(
+            rx.recv_timeout(Duration::from_millis(10)),
+            Err(RecvTimeoutError::Timeout)
+        );
+
+        let thread = thread::spawn(move || {
+            _ = tx.send(1);
+        });
+        assert_eq!(rx.recv_timeout(Duration::from_secs(5)), Ok(1));
+        thread.join().unwrap();
+
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)),
+            Err(RecvTimeoutError::Disconnected)
+        );
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+        assert!(fiber::cancel(cbus_fiber_id));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    pub fn unbounded_async_test() {
+        let cbus_fiber_id = run_cbus_endpoint("unbounded_async_test");
+
+        let (tx, mut rx) = unbounded::channel("unbounded_async_test");
+
+        fiber::block_on(async {
+            let res = rx.recv_async().timeout(Duration::from_millis(10)).await;
+            assert!(res.is_err());
+        });
+
+        let thread = thread::spawn(move || {
+            for i in 0..100 {
+                _ = tx.send(i);
+                if i % 10 == 0 {
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+        });
+
+        assert!(matches!(fiber::block_on(rx.recv_async()), Ok(0)));
+        let rest: Vec<_> = fiber::block_on((&mut rx).collect());
+        assert_eq!(rest, (1..100).collect::<Vec<_>>());
+        assert!(matches!(
+            fiber::block_on(rx.recv_async()),
+            Err(RecvError::Disconnected)
+        ));
+
+        thread.join().unwrap();
+        assert!(fiber::cancel(cbus_fiber_id));
+    }
 }
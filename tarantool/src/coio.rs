@@ -1,16 +1,22 @@
 //! Cooperative input/output
 //!
+//! - [`fs`] - fiber friendly file system operations
+//!
 //! See also:
 //! - [C API reference: Module coio](https://www.tarantool.io/en/doc/latest/dev_guide/reference_capi/coio/)
 use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::convert::TryFrom;
-use std::ffi::c_void;
+use std::ffi::{c_void, CString};
+use std::future::Future;
 use std::io::{self, Read, Write};
 use std::mem::forget;
-use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddrV4, SocketAddrV6};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
 use std::os::unix::io::{AsRawFd, IntoRawFd, RawFd};
+use std::pin::Pin;
 use std::rc::Rc;
+use std::task::{Context, Poll};
 use std::time::Duration;
 
 use core::ptr::null_mut;
@@ -19,6 +25,8 @@ use crate::error::{Error, TarantoolError};
 use crate::ffi::tarantool as ffi;
 use crate::fiber::{unpack_callback, Cond};
 
+pub mod fs;
+
 const TIMEOUT_INFINITY: f64 = 365.0 * 86400.0 * 100.0;
 
 /// Uses CoIO main loop to poll read/write events from wrapped socket
@@ -46,8 +54,11 @@ impl CoIOStream {
     }
 
     /// Connect to remote TCP socket
-    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<CoIOStream, io::Error> {
-        let inner_stream = TcpStream::connect(addr)?;
+    ///
+    /// Domain names in `addr` are resolved in the coio thread pool, see
+    /// [`ResolveAddrs`].
+    pub fn connect<A: ResolveAddrs>(addr: A) -> Result<CoIOStream, io::Error> {
+        let inner_stream = TcpStream::connect(&*addr.resolve_addrs()?)?;
         inner_stream.set_nonblocking(true)?;
         Ok(CoIOStream {
             fd: inner_stream.into_raw_fd(),
@@ -160,6 +171,168 @@ impl TryFrom<TcpListener> for CoIOListener {
     }
 }
 
+/// Uses CoIO main loop to wait until the wrapped UDP socket is ready for
+/// sending or receiving datagrams.
+///
+/// All the methods yield the current fiber instead of blocking the thread.
+/// The `*_with_timeout` variants return an error of kind
+/// [`io::ErrorKind::TimedOut`] if the operation couldn't complete in time.
+///
+/// See [`crate::network::client::udp::UdpSocket`] for an async version.
+///
+/// ```no_run
+/// use tarantool::coio::CoIOUdpSocket;
+///
+/// let socket = CoIOUdpSocket::bind("127.0.0.1:0").unwrap();
+/// socket.send_to(b"requests:1|c", "127.0.0.1:8125").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct CoIOUdpSocket {
+    inner: UdpSocket,
+}
+
+impl CoIOUdpSocket {
+    /// Creates a UDP socket bound to the given address.
+    /// See [`UdpSocket::bind`].
+    ///
+    /// `addr` is resolved in the coio thread pool, see [`ResolveAddrs`].
+    pub fn bind<A: ResolveAddrs>(addr: A) -> Result<CoIOUdpSocket, io::Error> {
+        Self::try_from(UdpSocket::bind(&*addr.resolve_addrs()?)?)
+    }
+
+    /// Sets the default destination of the socket, after which [`Self::send`]
+    /// and [`Self::recv`] can be used. See [`UdpSocket::connect`].
+    ///
+    /// `addr` is resolved in the coio thread pool, see [`ResolveAddrs`].
+    pub fn connect<A: ResolveAddrs>(&self, addr: A) -> Result<(), io::Error> {
+        self.inner.connect(&*addr.resolve_addrs()?)
+    }
+
+    /// Sends a datagram to the given address. Returns the number of bytes
+    /// sent.
+    ///
+    /// `addr` is resolved in the coio thread pool, see [`ResolveAddrs`].
+    pub fn send_to<A: ResolveAddrs>(&self, buf: &[u8], addr: A) -> Result<usize, io::Error> {
+        self.send_to_with_timeout(buf, addr, None)
+    }
+
+    /// Sends a datagram to the given address waiting at most `timeout` for
+    /// the socket to become writable.
+    pub fn send_to_with_timeout<A>(
+        &self,
+        buf: &[u8],
+        addr: A,
+        timeout: Option<Duration>,
+    ) -> Result<usize, io::Error>
+    where
+        A: ResolveAddrs,
+    {
+        let addr = addr.resolve_addrs()?.into_iter().next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no addresses to send to")
+        })?;
+        self.wait_io(ffi::CoIOFlags::WRITE, timeout, || {
+            self.inner.send_to(buf, addr)
+        })
+    }
+
+    /// Receives a datagram. Returns the number of bytes read and the address
+    /// of the sender.
+    ///
+    /// If `buf` is too small to hold the datagram, the excess bytes are
+    /// discarded.
+    pub fn recv_from(&self, buf: &mut [u8]) -> Result<(usize, SocketAddr), io::Error> {
+        self.recv_from_with_timeout(buf, None)
+    }
+
+    /// Receives a datagram waiting at most `timeout` for it to arrive.
+    pub fn recv_from_with_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<(usize, SocketAddr), io::Error> {
+        self.wait_io(ffi::CoIOFlags::READ, timeout, || self.inner.recv_from(buf))
+    }
+
+    /// Sends a datagram to the address the socket is connected to.
+    pub fn send(&self, buf: &[u8]) -> Result<usize, io::Error> {
+        self.send_with_timeout(buf, None)
+    }
+
+    /// Sends a datagram to the address the socket is connected to waiting at
+    /// most `timeout` for the socket to become writable.
+    pub fn send_with_timeout(
+        &self,
+        buf: &[u8],
+        timeout: Option<Duration>,
+    ) -> Result<usize, io::Error> {
+        self.wait_io(ffi::CoIOFlags::WRITE, timeout, || self.inner.send(buf))
+    }
+
+    /// Receives a datagram from the address the socket is connected to.
+    pub fn recv(&self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.recv_with_timeout(buf, None)
+    }
+
+    /// Receives a datagram from the address the socket is connected to
+    /// waiting at most `timeout` for it to arrive.
+    pub fn recv_with_timeout(
+        &self,
+        buf: &mut [u8],
+        timeout: Option<Duration>,
+    ) -> Result<usize, io::Error> {
+        self.wait_io(ffi::CoIOFlags::READ, timeout, || self.inner.recv(buf))
+    }
+
+    /// Returns the address the socket is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr, io::Error> {
+        self.inner.local_addr()
+    }
+
+    /// Returns a mutable reference to the underlying [`UdpSocket`]. The
+    /// socket is in the nonblocking mode, which must not be changed.
+    pub fn inner_socket(&mut self) -> &mut UdpSocket {
+        &mut self.inner
+    }
+
+    /// Calls `f` until it stops returning [`io::ErrorKind::WouldBlock`],
+    /// waiting for `flags` events on the socket in between.
+    fn wait_io<T>(
+        &self,
+        flags: ffi::CoIOFlags,
+        timeout: Option<Duration>,
+        mut f: impl FnMut() -> Result<T, io::Error>,
+    ) -> Result<T, io::Error> {
+        let deadline = timeout.map(|t| crate::fiber::clock().saturating_add(t));
+        loop {
+            match f() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                res => return res,
+            }
+            let timeout = match deadline {
+                None => TIMEOUT_INFINITY,
+                Some(deadline) => deadline.duration_since(crate::fiber::clock()).as_secs_f64(),
+            };
+            coio_wait(self.inner.as_raw_fd(), flags, timeout)?;
+        }
+    }
+}
+
+impl AsRawFd for CoIOUdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+impl TryFrom<UdpSocket> for CoIOUdpSocket {
+    type Error = io::Error;
+
+    fn try_from(value: UdpSocket) -> Result<Self, Self::Error> {
+        value.set_nonblocking(true)?;
+        Ok(Self { inner: value })
+    }
+}
+
 /// Wait until `READ` or `WRITE` event on socket (`fd`). Yields.
 ///
 /// - `fd` - non-blocking socket file description
@@ -201,6 +374,167 @@ where
     unsafe { ffi::coio_call(trampoline, callback_ptr, Box::into_raw(Box::<T>::new(arg))) }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// spawn_blocking
+////////////////////////////////////////////////////////////////////////////////
+
+/// Runs the closure `f` in the coio thread pool and returns a handle which can
+/// be used to get the result.
+///
+/// Use this to offload CPU-heavy or blocking (in the OS sense) work from the
+/// TX thread, so that other fibers can run while it's in progress. The work
+/// starts right away, the handle doesn't need to be polled for that.
+///
+/// The result can be obtained either by calling [`BlockingHandle::join`],
+/// which yields the current fiber until the work is done, or by `.await`ing
+/// the handle from an async context.
+///
+/// If `f` panics, the panic is caught in the worker thread and resumed in the
+/// fiber which joins (or awaits) the handle.
+///
+/// Note that `f` is executed in a separate OS thread, so it must not call any
+/// tarantool api (e.g. access spaces or start fibers).
+///
+/// ```no_run
+/// use tarantool::coio::spawn_blocking;
+///
+/// let handle = spawn_blocking(|| (1..=20_u64).product::<u64>());
+/// // Other fibers can run while the product is being computed.
+/// assert_eq!(handle.join(), 2432902008176640000);
+/// ```
+///
+/// # Panics
+/// Panics if the helper fiber could not be started.
+pub fn spawn_blocking<F, T>(f: F) -> BlockingHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = crate::fiber::r#async::oneshot::channel();
+    crate::fiber::Builder::new()
+        .name("spawn_blocking")
+        .func(move || {
+            // If the coio task could not be created, the sender is dropped
+            // and the handle reports it when joined.
+            if let Some(result) = call_blocking(f) {
+                // The handle may have been dropped, which is fine.
+                _ = tx.send(result);
+            }
+        })
+        .start_non_joinable()
+        .expect("failed to start a fiber for spawn_blocking");
+    BlockingHandle { rx }
+}
+
+/// Runs `f` in the coio thread pool yielding the current fiber until it's
+/// done. Returns `None` if the coio task could not be created.
+fn call_blocking<F, T>(f: F) -> Option<std::thread::Result<T>>
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Shared between the calling fiber and the coio worker, each of them
+    /// owning a reference, so that it stays valid even if `coio_call` returns
+    /// before the task is complete.
+    struct Job<F, T> {
+        f: Mutex<Option<F>>,
+        result: Mutex<Option<std::thread::Result<T>>>,
+        done: AtomicBool,
+    }
+
+    unsafe extern "C" fn trampoline<F, T>(mut args: ffi::VaList) -> i32
+    where
+        F: FnOnce() -> T,
+    {
+        // Takes over the worker's reference, it's released once `f` is done.
+        let job = Arc::from_raw(args.get::<*const c_void>() as *const Job<F, T>);
+        let f = job.f.lock().unwrap().take();
+        if let Some(f) = f {
+            // Panics must not unwind into the coio worker thread.
+            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(f));
+            *job.result.lock().unwrap() = Some(result);
+        }
+        job.done.store(true, Ordering::Release);
+        0
+    }
+
+    let job = Arc::new(Job {
+        f: Mutex::new(Some(f)),
+        result: Mutex::new(None),
+        done: AtomicBool::new(false),
+    });
+    let worker_job = Arc::into_raw(job.clone());
+    // Safety: the worker side owns its own reference to `job`, so the job is
+    // never freed from under it.
+    let rc = unsafe { ffi::coio_call(Some(trampoline::<F, T>), worker_job as *mut c_void) };
+    if rc < 0 && !job.done.load(Ordering::Acquire) {
+        if let Err(e) = TarantoolError::maybe_last() {
+            if e.error_type() == "OutOfMemory" {
+                // The task was never created, so the worker's reference is
+                // never going to be taken over.
+                // Safety: `worker_job` came from `Arc::into_raw` above.
+                drop(unsafe { Arc::from_raw(worker_job) });
+                return None;
+            }
+        }
+    }
+    // `coio_call` may return early if the fiber is woken up before the task
+    // is complete. `f` may borrow data from the caller, so we must not return
+    // until the worker is done with it.
+    while !job.done.load(Ordering::Acquire) {
+        crate::fiber::sleep(Duration::from_millis(1));
+    }
+    let result = job.result.lock().unwrap().take();
+    result
+}
+
+/// A handle to the result of a closure running in the coio thread pool.
+///
+/// Returned from [`spawn_blocking`]. Dropping the handle doesn't stop the
+/// closure, the result is just discarded.
+#[must_use = "the result of spawn_blocking is discarded if the handle is dropped"]
+pub struct BlockingHandle<T> {
+    rx: crate::fiber::r#async::oneshot::Receiver<std::thread::Result<T>>,
+}
+
+impl<T> BlockingHandle<T> {
+    /// Yields the current fiber until the closure is done and returns its
+    /// result.
+    ///
+    /// # Panics
+    /// Resumes the panic if the closure panicked. Also panics if the coio task
+    /// could not be created (e.g. due to memory shortage).
+    #[inline(always)]
+    pub fn join(self) -> T {
+        crate::fiber::block_on(self)
+    }
+}
+
+impl<T> Future for BlockingHandle<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let result = match Pin::new(&mut self.rx).poll(cx) {
+            Poll::Ready(result) => result,
+            Poll::Pending => return Poll::Pending,
+        };
+        match result {
+            Ok(Ok(value)) => Poll::Ready(value),
+            Ok(Err(payload)) => std::panic::resume_unwind(payload),
+            Err(_) => panic!("failed to create a coio task for spawn_blocking"),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for BlockingHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockingHandle").finish_non_exhaustive()
+    }
+}
+
 /// Fiber-friendly version of `getaddrinfo(3)`.
 ///
 /// - `host` - host name, i.e. "tarantool.org"
@@ -232,6 +566,196 @@ pub unsafe fn getaddrinfo(
     }
 }
 
+/// Resolves `host` into a list of socket addresses with the given `port`.
+/// Yields the current fiber until the addresses are resolved or `timeout`
+/// expires.
+///
+/// Unlike [`ToSocketAddrs`] this doesn't block the thread, because the
+/// resolution is done in the coio thread pool via [`coio_getaddrinfo`].
+///
+/// Returns an error of kind [`io::ErrorKind::TimedOut`] if the timeout
+/// expires.
+///
+/// ```no_run
+/// use tarantool::coio::resolve;
+/// use std::time::Duration;
+///
+/// let addrs = resolve("localhost", 3301, Duration::from_secs(3)).unwrap();
+/// ```
+///
+/// [`coio_getaddrinfo`]: ffi::coio_getaddrinfo
+pub fn resolve(host: &str, port: u16, timeout: Duration) -> Result<Vec<SocketAddr>, io::Error> {
+    let c_host = CString::new(host).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    // SAFETY: all zeroes is a valid value for addrinfo.
+    let mut hints: libc::addrinfo = unsafe { std::mem::zeroed() };
+    hints.ai_family = libc::AF_UNSPEC;
+    hints.ai_socktype = libc::SOCK_STREAM;
+
+    let list = match unsafe { getaddrinfo(&c_host, None, &hints, timeout.as_secs_f64()) } {
+        Ok(list) => list,
+        Err(e) if is_timeout(&e) => return Err(io::ErrorKind::TimedOut.into()),
+        Err(e) => {
+            let msg = format!("failed to resolve domain name '{host}': {e}");
+            return Err(io::Error::new(io::ErrorKind::Other, msg));
+        }
+    };
+
+    let mut addrs = Vec::with_capacity(4);
+    let mut current = list;
+    while !current.is_null() {
+        // SAFETY: the list is valid until freeaddrinfo is called.
+        let ai = unsafe { &*current };
+        match ai.ai_family {
+            libc::AF_INET => {
+                let sa = unsafe { &*(ai.ai_addr as *const libc::sockaddr_in) };
+                let ip = Ipv4Addr::from(u32::from_be(sa.sin_addr.s_addr));
+                addrs.push(SocketAddrV4::new(ip, port).into());
+            }
+            libc::AF_INET6 => {
+                let sa = unsafe { &*(ai.ai_addr as *const libc::sockaddr_in6) };
+                let ip = Ipv6Addr::from(sa.sin6_addr.s6_addr);
+                addrs.push(SocketAddrV6::new(ip, port, sa.sin6_flowinfo, sa.sin6_scope_id).into());
+            }
+            // Neither ipv4 nor ipv6, can't be represented as a SocketAddr.
+            _ => {}
+        }
+        current = ai.ai_next;
+    }
+    unsafe { libc::freeaddrinfo(list) };
+
+    Ok(addrs)
+}
+
+/// Async version of [`resolve`].
+///
+/// The resolution is done in a separate fiber, so this future can be used
+/// with [`futures::join`] and the like.
+pub async fn resolve_async(
+    host: &str,
+    port: u16,
+    timeout: Duration,
+) -> Result<Vec<SocketAddr>, io::Error> {
+    let (tx, rx) = crate::fiber::r#async::oneshot::channel();
+    let host = host.to_owned();
+    crate::fiber::Builder::new()
+        .name("coio_resolve")
+        .func(move || {
+            _ = tx.send(resolve(&host, port, timeout));
+        })
+        .start_non_joinable()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    rx.await
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "resolve fiber exited unexpectedly"))?
+}
+
+/// Resolves `addr` into a list of socket addresses without blocking the
+/// thread. The resolution is done in the coio thread pool.
+///
+/// Use this instead of [`ToSocketAddrs::to_socket_addrs`] when the address
+/// may contain a domain name. See also [`resolve`].
+pub fn to_socket_addrs<A>(addr: A) -> Result<Vec<SocketAddr>, io::Error>
+where
+    A: ToSocketAddrs + Send,
+{
+    match call_blocking(move || addr.to_socket_addrs().map(Iterator::collect)) {
+        Some(Ok(result)) => result,
+        Some(Err(payload)) => std::panic::resume_unwind(payload),
+        // coio_call sets errno if the task could not be created.
+        None => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Types which can be resolved into socket addresses without blocking the
+/// thread.
+///
+/// Implemented for the same types as [`ToSocketAddrs`]. Addresses with an IP
+/// are converted in place, domain names are resolved in the coio thread pool
+/// (see [`to_socket_addrs`]), which makes the fiber **yield**.
+pub trait ResolveAddrs {
+    /// Resolves `self` into a list of socket addresses.
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error>;
+}
+
+macro_rules! impl_resolve_addrs_for_ip {
+    ($($t:ty,)*) => {
+        $(
+            impl ResolveAddrs for $t {
+                #[inline(always)]
+                fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error> {
+                    // Never does a domain name lookup, so it doesn't block.
+                    Ok(self.to_socket_addrs()?.collect())
+                }
+            }
+        )*
+    };
+}
+
+impl_resolve_addrs_for_ip! {
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    (IpAddr, u16),
+    (Ipv4Addr, u16),
+    (Ipv6Addr, u16),
+}
+
+impl ResolveAddrs for [SocketAddr] {
+    #[inline(always)]
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error> {
+        Ok(self.to_vec())
+    }
+}
+
+impl ResolveAddrs for str {
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error> {
+        if let Ok(addr) = self.parse::<SocketAddr>() {
+            return Ok(vec![addr]);
+        }
+        to_socket_addrs(self.to_owned())
+    }
+}
+
+impl ResolveAddrs for String {
+    #[inline(always)]
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error> {
+        self.as_str().resolve_addrs()
+    }
+}
+
+impl ResolveAddrs for (&str, u16) {
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error> {
+        let (host, port) = *self;
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+        to_socket_addrs((host.to_owned(), port))
+    }
+}
+
+impl ResolveAddrs for (String, u16) {
+    #[inline(always)]
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error> {
+        (self.0.as_str(), self.1).resolve_addrs()
+    }
+}
+
+impl<T: ResolveAddrs + ?Sized> ResolveAddrs for &T {
+    #[inline(always)]
+    fn resolve_addrs(&self) -> Result<Vec<SocketAddr>, io::Error> {
+        (**self).resolve_addrs()
+    }
+}
+
+/// Checks if the error returned from [`getaddrinfo`] is a timeout.
+fn is_timeout(e: &Error) -> bool {
+    match e {
+        Error::IO(e) => e.kind() == io::ErrorKind::TimedOut,
+        Error::Tarantool(e) => e.error_type.as_deref() == Some("TimedOut"),
+        _ => false,
+    }
+}
+
 #[inline(always)]
 pub(crate) fn read(
     fd: RawFd,
@@ -0,0 +1,378 @@
+//! Fiber friendly file system operations.
+//!
+//! Calling [`std::fs`] functions from the TX thread blocks it, so no other
+//! fiber can run until the operation is done. The functions in this module
+//! execute the same operations in the coio thread pool (the same way the lua
+//! `fio` module does), yielding only the current fiber.
+//!
+//! Most functions have an async counterpart with the `_async` suffix, which can
+//! be used with [`fiber::block_on`] or any other async runtime running in the
+//! TX thread.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::coio::fs::{self, File};
+//! use std::io::Write;
+//!
+//! let mut file = File::create("export.csv").unwrap();
+//! file.write_all(b"id,name\n1,foo\n").unwrap();
+//! file.sync_all().unwrap();
+//!
+//! fs::rename("export.csv", "export-1.csv").unwrap();
+//! assert_eq!(fs::read_to_string("export-1.csv").unwrap(), "id,name\n1,foo\n");
+//! ```
+//!
+//! [`fiber::block_on`]: crate::fiber::block_on
+
+use std::fs::{DirEntry, Metadata, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Runs `f` in the coio thread pool yielding the current fiber until it's done.
+fn blocking<F, T>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send,
+    T: Send,
+{
+    match super::call_blocking(f) {
+        Some(Ok(result)) => result,
+        Some(Err(payload)) => std::panic::resume_unwind(payload),
+        // coio_call sets errno if the task could not be created.
+        None => Err(io::Error::last_os_error()),
+    }
+}
+
+/// Runs `f` in the coio thread pool, the returned future resolves when it's
+/// done.
+async fn blocking_async<F, T>(f: F) -> io::Result<T>
+where
+    F: FnOnce() -> io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    super::spawn_blocking(f).await
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// File
+////////////////////////////////////////////////////////////////////////////////
+
+/// A reference to an open file, all operations on which are executed in the
+/// coio thread pool.
+///
+/// Implements [`Read`], [`Write`] and [`Seek`], all of which yield the current
+/// fiber instead of blocking the thread.
+///
+/// The file is closed when the last clone of the value is dropped. Note that
+/// closing a file is done in the current thread, because it's normally fast.
+#[derive(Debug, Clone)]
+pub struct File {
+    inner: Arc<std::fs::File>,
+}
+
+impl File {
+    /// Opens a file in read-only mode. See [`std::fs::File::open`].
+    #[inline]
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        blocking(|| std::fs::File::open(path)).map(Self::from_std)
+    }
+
+    /// Async version of [`File::open`].
+    #[inline]
+    pub async fn open_async(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        blocking_async(move || std::fs::File::open(path))
+            .await
+            .map(Self::from_std)
+    }
+
+    /// Opens a file in write-only mode, creating it if it doesn't exist and
+    /// truncating it if it does. See [`std::fs::File::create`].
+    #[inline]
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        blocking(|| std::fs::File::create(path)).map(Self::from_std)
+    }
+
+    /// Async version of [`File::create`].
+    #[inline]
+    pub async fn create_async(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        blocking_async(move || std::fs::File::create(path))
+            .await
+            .map(Self::from_std)
+    }
+
+    /// Opens a file with the given `options`. See [`OpenOptions::open`].
+    #[inline]
+    pub fn open_with(path: impl AsRef<Path>, options: &OpenOptions) -> io::Result<Self> {
+        let path = path.as_ref();
+        blocking(|| options.open(path)).map(Self::from_std)
+    }
+
+    /// Async version of [`File::open_with`].
+    #[inline]
+    pub async fn open_with_async(
+        path: impl AsRef<Path>,
+        options: &OpenOptions,
+    ) -> io::Result<Self> {
+        let path = path.as_ref().to_owned();
+        let options = options.clone();
+        blocking_async(move || options.open(path))
+            .await
+            .map(Self::from_std)
+    }
+
+    /// Wraps a [`std::fs::File`].
+    #[inline(always)]
+    pub fn from_std(file: std::fs::File) -> Self {
+        Self {
+            inner: Arc::new(file),
+        }
+    }
+
+    /// Returns a reference to the underlying [`std::fs::File`]. Note that
+    /// calling its methods directly will block the thread.
+    #[inline(always)]
+    pub fn as_std(&self) -> &std::fs::File {
+        &self.inner
+    }
+
+    /// Reads the rest of the file into a vector.
+    ///
+    /// Async version of [`Read::read_to_end`].
+    #[inline]
+    pub async fn read_to_end_async(&self) -> io::Result<Vec<u8>> {
+        let file = self.inner.clone();
+        blocking_async(move || {
+            let mut buf = Vec::new();
+            (&*file).read_to_end(&mut buf)?;
+            Ok(buf)
+        })
+        .await
+    }
+
+    /// Reads at most `len` bytes from the file.
+    ///
+    /// Async version of [`Read::read`]. Returns an empty vector if the end of
+    /// file is reached.
+    #[inline]
+    pub async fn read_async(&self, len: usize) -> io::Result<Vec<u8>> {
+        let file = self.inner.clone();
+        blocking_async(move || {
+            let mut buf = vec![0; len];
+            let n = (&*file).read(&mut buf)?;
+            buf.truncate(n);
+            Ok(buf)
+        })
+        .await
+    }
+
+    /// Async version of [`Write::write_all`].
+    #[inline]
+    pub async fn write_all_async(&self, data: impl Into<Vec<u8>>) -> io::Result<()> {
+        let file = self.inner.clone();
+        let data = data.into();
+        blocking_async(move || (&*file).write_all(&data)).await
+    }
+
+    /// Flushes all the data and metadata to disk (i.e. calls `fsync(2)`). See
+    /// [`std::fs::File::sync_all`].
+    #[inline]
+    pub fn sync_all(&self) -> io::Result<()> {
+        let file = &*self.inner;
+        blocking(|| file.sync_all())
+    }
+
+    /// Async version of [`File::sync_all`].
+    #[inline]
+    pub async fn sync_all_async(&self) -> io::Result<()> {
+        let file = self.inner.clone();
+        blocking_async(move || file.sync_all()).await
+    }
+
+    /// Flushes the data to disk, without the metadata (i.e. calls
+    /// `fdatasync(2)`). See [`std::fs::File::sync_data`].
+    #[inline]
+    pub fn sync_data(&self) -> io::Result<()> {
+        let file = &*self.inner;
+        blocking(|| file.sync_data())
+    }
+
+    /// Async version of [`File::sync_data`].
+    #[inline]
+    pub async fn sync_data_async(&self) -> io::Result<()> {
+        let file = self.inner.clone();
+        blocking_async(move || file.sync_data()).await
+    }
+
+    /// Truncates or extends the file. See [`std::fs::File::set_len`].
+    #[inline]
+    pub fn set_len(&self, size: u64) -> io::Result<()> {
+        let file = &*self.inner;
+        blocking(|| file.set_len(size))
+    }
+
+    /// Queries metadata about the file. See [`std::fs::File::metadata`].
+    #[inline]
+    pub fn metadata(&self) -> io::Result<Metadata> {
+        let file = &*self.inner;
+        blocking(|| file.metadata())
+    }
+
+    /// Async version of [`File::metadata`].
+    #[inline]
+    pub async fn metadata_async(&self) -> io::Result<Metadata> {
+        let file = self.inner.clone();
+        blocking_async(move || file.metadata()).await
+    }
+}
+
+impl Read for File {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let file = &*self.inner;
+        blocking(|| (&*file).read(buf))
+    }
+
+    #[inline]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let file = &*self.inner;
+        blocking(|| (&*file).read_to_end(buf))
+    }
+}
+
+impl Write for File {
+    #[inline]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let file = &*self.inner;
+        blocking(|| (&*file).write(buf))
+    }
+
+    #[inline]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        let file = &*self.inner;
+        blocking(|| (&*file).write_all(buf))
+    }
+
+    #[inline(always)]
+    fn flush(&mut self) -> io::Result<()> {
+        // std::fs::File is not buffered
+        Ok(())
+    }
+}
+
+impl Seek for File {
+    #[inline]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let file = &*self.inner;
+        blocking(|| (&*file).seek(pos))
+    }
+}
+
+impl From<std::fs::File> for File {
+    #[inline(always)]
+    fn from(file: std::fs::File) -> Self {
+        Self::from_std(file)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// functions
+////////////////////////////////////////////////////////////////////////////////
+
+macro_rules! define_fs_functions {
+    (
+        $(
+            $(#[$meta:meta])*
+            fn $name:ident / $name_async:ident ( $($arg:ident),* ) -> $ret:ty;
+        )+
+    ) => {
+        $(
+            $(#[$meta])*
+            #[doc = concat!("\n\nSee [`std::fs::", stringify!($name), "`].")]
+            #[inline]
+            pub fn $name($($arg: impl AsRef<Path>),*) -> io::Result<$ret> {
+                $( let $arg = $arg.as_ref(); )*
+                blocking(|| std::fs::$name($($arg),*))
+            }
+
+            #[doc = concat!("Async version of [`", stringify!($name), "`].")]
+            #[inline]
+            pub async fn $name_async($($arg: impl AsRef<Path>),*) -> io::Result<$ret> {
+                $( let $arg = $arg.as_ref().to_owned(); )*
+                blocking_async(move || std::fs::$name($($arg),*)).await
+            }
+        )+
+    };
+}
+
+define_fs_functions! {
+    /// Reads the entire contents of a file into a vector.
+    fn read / read_async(path) -> Vec<u8>;
+
+    /// Reads the entire contents of a file into a string.
+    fn read_to_string / read_to_string_async(path) -> String;
+
+    /// Renames a file or directory, replacing `to` if it exists.
+    fn rename / rename_async(from, to) -> ();
+
+    /// Queries metadata about a file or directory following symlinks (i.e.
+    /// calls `stat(2)`).
+    fn metadata / metadata_async(path) -> Metadata;
+
+    /// Queries metadata about a file or directory without following symlinks
+    /// (i.e. calls `lstat(2)`).
+    fn symlink_metadata / symlink_metadata_async(path) -> Metadata;
+
+    /// Removes a file.
+    fn remove_file / remove_file_async(path) -> ();
+
+    /// Creates a new empty directory.
+    fn create_dir / create_dir_async(path) -> ();
+
+    /// Creates a directory and all of its missing parents.
+    fn create_dir_all / create_dir_all_async(path) -> ();
+
+    /// Removes an empty directory.
+    fn remove_dir / remove_dir_async(path) -> ();
+}
+
+/// Writes `contents` to a file, creating it if it doesn't exist and truncating
+/// it if it does.
+///
+/// See [`std::fs::write`].
+#[inline]
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    let contents = contents.as_ref();
+    blocking(|| std::fs::write(path, contents))
+}
+
+/// Async version of [`write`](fn@write).
+#[inline]
+pub async fn write_async(path: impl AsRef<Path>, contents: impl Into<Vec<u8>>) -> io::Result<()> {
+    let path = path.as_ref().to_owned();
+    let contents = contents.into();
+    blocking_async(move || std::fs::write(path, contents)).await
+}
+
+/// Returns the entries of a directory. The order of the entries is
+/// unspecified.
+///
+/// Unlike [`std::fs::read_dir`] all the entries are read at once. Note that
+/// some of the [`DirEntry`] methods (e.g. [`DirEntry::metadata`]) may access
+/// the file system and block the thread, use [`metadata`] instead.
+#[inline]
+pub fn read_dir(path: impl AsRef<Path>) -> io::Result<Vec<DirEntry>> {
+    let path = path.as_ref();
+    blocking(|| std::fs::read_dir(path)?.collect())
+}
+
+/// Async version of [`read_dir`].
+#[inline]
+pub async fn read_dir_async(path: impl AsRef<Path>) -> io::Result<Vec<DirEntry>> {
+    let path = path.as_ref().to_owned();
+    blocking_async(move || std::fs::read_dir(path)?.collect()).await
+}
@@ -4,12 +4,21 @@
 //! - create, run and manage [fibers](Builder),
 //! - use a synchronization mechanism for fibers, similar to “condition variables” and similar to operating-system
 //! functions such as `pthread_cond_wait()` plus `pthread_cond_signal()`,
-//! - spawn a fiber based [async runtime](async).
+//! - spawn a fiber based [async runtime](async),
+//! - run jobs on a fixed size [pool](pool) of worker fibers,
+//! - run periodic background tasks via a [scheduler](scheduler),
+//! - wait on several channels, conds and timeouts at once (see [`select!`]),
+//! - detect fibers which run for too long without yielding (see [`slice`](mod@slice)),
+//! - inspect all the existing fibers (see [`info`] and [`top`]),
+//! - cancel trees of fibers with [cancellation tokens](cancellation),
+//! - handle panics in fibers (see [`JoinError`] and [`set_panic_hook`]).
 //!
 //! See also:
 //! - [Threads, fibers and yields](https://www.tarantool.io/en/doc/latest/book/box/atomic/#threads-fibers-and-yields)
 //! - [Lua reference: Module fiber](https://www.tarantool.io/en/doc/latest/reference/reference_lua/fiber/)
 //! - [C API reference: Module fiber](https://www.tarantool.io/en/doc/latest/dev_guide/reference_capi/fiber/)
+#[doc(inline)]
+pub use crate::__fiber_select as select;
 use crate::error::{TarantoolError, TarantoolErrorCode};
 use crate::ffi::has_fiber_id;
 use crate::ffi::tarantool::fiber_sleep;
@@ -20,6 +29,7 @@ use crate::tlua::{self as tlua, AsLua};
 use crate::unwrap_ok_or;
 use crate::{c_ptr, set_error};
 use ::va_list::VaList;
+pub use cancellation::{CancellationToken, Cancelled};
 pub use channel::Channel;
 pub use channel::RecvError;
 pub use channel::RecvTimeout;
@@ -29,8 +39,13 @@ pub use channel::TryRecvError;
 pub use channel::TrySendError;
 pub use csw::check_yield;
 pub use csw::YieldResult;
+pub use info::{info, info_with_backtrace, FiberInfo, FiberMemory};
+pub use info::{top, top_disable, top_enable, FiberCpuUsage, FiberTop};
 pub use mutex::Mutex;
+pub use pool::Pool;
 pub use r#async::block_on;
+pub use select::Select;
+pub use slice::{check_slice, FiberSlice};
 use std::cell::UnsafeCell;
 use std::ffi::CString;
 use std::future::Future;
@@ -44,9 +59,17 @@ use std::time::Duration;
 pub mod r#async;
 pub mod safety;
 pub use safety::*;
+pub mod cancellation;
 pub mod channel;
 mod csw;
+mod info;
 pub mod mutex;
+pub mod pool;
+pub mod scheduler;
+pub mod select;
+pub mod slice;
+pub(crate) mod unwind;
+pub use unwind::{set_panic_hook, take_panic_hook, JoinError, PanicHook};
 
 /// Type alias for a fiber id.
 pub type FiberId = u64;
@@ -300,6 +323,7 @@ impl<'a, T> Fiber<'a, T> {
 ///
 /// * `name`:       specifies an associated name for the fiber
 /// * `stack_size`: specifies the desired stack size for the fiber
+/// * `cancellation_token`: specifies the [`CancellationToken`] attached to the fiber
 /// * `func`:       specifies the fiber function
 ///
 /// The [`start`](#method.start) and [`defer`](#method.defer) methods will
@@ -311,6 +335,7 @@ impl<'a, T> Fiber<'a, T> {
 pub struct Builder<F> {
     name: Option<String>,
     attr: Option<FiberAttr>,
+    cancellation_token: Option<CancellationToken>,
     f: F,
 }
 
@@ -328,6 +353,7 @@ impl Builder<NoFunc> {
         Builder {
             name: None,
             attr: None,
+            cancellation_token: None,
             f: NoFunc,
         }
     }
@@ -342,6 +368,7 @@ impl Builder<NoFunc> {
         Builder {
             name: self.name,
             attr: self.attr,
+            cancellation_token: self.cancellation_token,
             f,
         }
     }
@@ -409,6 +436,17 @@ impl<F> Builder<F> {
         self.attr = Some(attr);
         Ok(self)
     }
+
+    /// Attaches the cancellation `token` to the new fiber. When the token is
+    /// cancelled, the fiber is cancelled and woken up.
+    ///
+    /// If this isn't called, the new fiber gets a child of the current
+    /// fiber's token (see [`CancellationToken::current`]) if there's one.
+    #[inline(always)]
+    pub fn cancellation_token(mut self, token: CancellationToken) -> Self {
+        self.cancellation_token = Some(token);
+        self
+    }
 }
 
 impl<'f, F, T> Builder<F>
@@ -510,11 +548,12 @@ where
         Fyber::spawn_lua(name, f, attr.as_ref())
     }
 
-    fn into_fiber_args(self) -> (String, F, Option<FiberAttr>) {
+    fn into_fiber_args(self) -> (String, impl FnOnce() -> T + 'f, Option<FiberAttr>) {
         #[rustfmt::skip]
-        let Self { name, attr, f } = self;
+        let Self { name, attr, cancellation_token, f } = self;
 
         let name = name.unwrap_or_else(|| "<rust>".into());
+        let f = cancellation::wrap_fiber_func(f, cancellation_token);
 
         (name, f, attr)
     }
@@ -676,7 +715,7 @@ where
             ffi::fiber_set_joinable(inner.as_ptr(), is_joinable);
 
             // Prepare the storage for rust closure & result value.
-            let result_cell = needs_returning::<T>().then(FiberResultCell::default);
+            let result_cell = is_joinable.then(FiberResultCell::default);
 
             // Prepare fiber context for passing fiber arguments.
             let mut ctx = Context::default();
@@ -761,7 +800,7 @@ where
             ffi::fiber_set_joinable(inner.as_ptr(), is_joinable);
 
             // Prepare the storage for rust closure & result value.
-            let result_cell = needs_returning::<T>().then(FiberResultCell::default);
+            let result_cell = is_joinable.then(FiberResultCell::default);
 
             // Prepare fiber context.
             let mut ctx = Context::default();
@@ -824,15 +863,18 @@ where
         let f = std::mem::replace(&mut ctx.fiber_rust_closure, std::ptr::null_mut());
         let f = Box::from_raw(f.cast::<F>());
 
-        // Call `f` and drop the closure.
-        let t = (f)();
-
-        // Write results into the join handle if needed.
-        if needs_returning::<T>() {
-            assert!(!ctx.fiber_result_ptr.is_null());
-            std::ptr::write(ctx.fiber_result_ptr.cast(), Some(t));
-        } else {
-            debug_assert!(ctx.fiber_result_ptr.is_null());
+        // Call `f` and drop the closure. Panics must not unwind into
+        // tarantool, so they're caught here.
+        let res = unwind::catch_unwind(f);
+
+        if !ctx.fiber_result_ptr.is_null() {
+            // Write results into the join handle. The panic if any is
+            // returned from `JoinHandle::join`.
+            std::ptr::write(ctx.fiber_result_ptr.cast(), Some(res));
+        } else if let Err(e) = res {
+            // Nobody is going to join this fiber, so the panic hook is the
+            // only one who can report it.
+            unwind::call_panic_hook(&e);
         }
 
         // The only thing this return value controls is wether the last error
@@ -909,16 +951,12 @@ where
                 // userdata originally contained None
                 tlua::error!(l, "rust FnOnce callback was called more than once"));
 
-        // call f and drop it afterwards
-        let res = f();
+        // call f and drop it afterwards, panics must not unwind into lua
+        let res = unwind::catch_unwind(f);
 
         // return results to lua
-        if needs_returning::<T>() {
-            impl_details::push_userdata(l, res);
-            1
-        } else {
-            0
-        }
+        impl_details::push_userdata(l, res);
+        1
     }
 }
 
@@ -1080,7 +1118,7 @@ enum JoinHandleImpl<T> {
     },
 }
 
-type FiberResultCell<T> = Box<UnsafeCell<Option<T>>>;
+type FiberResultCell<T> = Box<UnsafeCell<Option<Result<T, JoinError>>>>;
 
 impl<'f, T> JoinHandle<'f, T> {
     #[inline(always)]
@@ -1100,8 +1138,12 @@ impl<'f, T> JoinHandle<'f, T> {
     }
 
     /// Block until the fiber's termination and return it's result value.
+    ///
+    /// Returns an error if the fiber function panicked. The error carries
+    /// the panic payload, which can be used to propagate the panic via
+    /// [`std::panic::resume_unwind`] if needed.
     #[rustfmt::skip]
-    pub fn join(mut self) -> T {
+    pub fn join(mut self) -> Result<T, JoinError> {
         let inner = self
             .inner
             .take()
@@ -1113,36 +1155,22 @@ impl<'f, T> JoinHandle<'f, T> {
                 let code = unsafe { ffi::fiber_join(fiber.as_ptr()) };
                 debug_assert_eq!(code, 0, "rust fiber functions always return 0");
 
-                if needs_returning::<T>() {
-                    let mut result_cell = result_cell.take().expect("should not be None for non unit types");
-                    let res = result_cell.get_mut().take().expect("should have been set by the fiber function");
-                    return res;
-                }
-
-                debug_assert!(result_cell.is_none());
+                let mut result_cell = result_cell.take().expect("should not be None for joinable fibers");
+                result_cell.get_mut().take().expect("should have been set by the fiber function")
             }
             JoinHandleImpl::Lua { fiber_id } => unsafe {
                 let guard = impl_details::lua_fiber_join(fiber_id)
                     .map_err(|e| panic!("Unrecoverable lua failure: {}", e))
                     .unwrap();
 
-                if needs_returning::<T>() {
-                    let ud_ptr = lua::lua_touserdata(guard.as_lua(), -1);
-                    let res = (ud_ptr as *mut Option<T>)
-                        .as_mut()
-                        .expect("fiber:join must return correct userdata")
-                        .take()
-                        .expect("data can only be taken once from the UDBox");
-                    return res;
-                }
-
-                debug_assert!(lua::lua_isnil(guard.as_lua(), -1));
+                let ud_ptr = lua::lua_touserdata(guard.as_lua(), -1);
+                (ud_ptr as *mut Option<Result<T, JoinError>>)
+                    .as_mut()
+                    .expect("fiber:join must return correct userdata")
+                    .take()
+                    .expect("data can only be taken once from the UDBox")
             },
         }
-
-        // SAFETY: this is safe because () is a zero sized type.
-        #[allow(clippy::uninit_assumed_init)]
-        unsafe { std::mem::MaybeUninit::uninit().assume_init() }
     }
 
     /// Returns the underlying fiber id.
@@ -1885,6 +1913,7 @@ impl Drop for FiberAttr {
 #[derive(Debug)]
 pub struct Cond {
     inner: *mut ffi::FiberCond,
+    select_waiters: select::SelectWaiters,
 }
 
 /// - call [Cond::new()](#method.new) to create a named condition variable, which will be called `cond` for examples in this section.
@@ -1897,6 +1926,7 @@ impl Cond {
     pub fn new() -> Self {
         Cond {
             inner: unsafe { ffi::fiber_cond_new() },
+            select_waiters: Default::default(),
         }
     }
 
@@ -1904,6 +1934,7 @@ impl Cond {
     /// Does nothing if no one is waiting. Does not yield.
     #[inline(always)]
     pub fn signal(&self) {
+        self.select_waiters.notify();
         unsafe { ffi::fiber_cond_signal(self.inner) }
     }
 
@@ -1911,9 +1942,16 @@ impl Cond {
     /// Does not yield.
     #[inline(always)]
     pub fn broadcast(&self) {
+        self.select_waiters.notify();
         unsafe { ffi::fiber_cond_broadcast(self.inner) }
     }
 
+    /// Returns the fibers waiting on this cond in a [`Select`].
+    #[inline(always)]
+    pub(crate) fn select_waiters(&self) -> &select::SelectWaiters {
+        &self.select_waiters
+    }
+
     /// Suspend the execution of the current fiber (i.e. yield) until
     /// [`Self::signal`] or [`Self::broadcast`] is called or a `timeout` is
     /// exceeded.
@@ -2219,8 +2257,8 @@ where
 /// Returns `true` if a fiber function with this return type needs to return the
 /// value to the caller when joined.
 ///
-/// This is used for optimizations. Basically if this function returns `false`
-/// for the return type of a fiber then we save on some overhead.
+/// Fibers whose function returns such a value can only be joinable, because
+/// otherwise there's nobody to pass the value to.
 const fn needs_returning<T>() -> bool {
     std::mem::size_of::<T>() != 0 || std::mem::needs_drop::<T>()
 }
@@ -2256,7 +2294,7 @@ mod tests {
     #[crate::test(tarantool = "crate")]
     fn builder_async_func() {
         let jh = Builder::new().func_async(async { 69 }).start().unwrap();
-        let res = jh.join();
+        let res = jh.join().unwrap();
         assert_eq!(res, 69);
     }
 
@@ -2271,7 +2309,7 @@ mod tests {
             })
             .start()
             .unwrap();
-        jh.join();
+        jh.join().unwrap();
         assert_eq!(*res.borrow(), 1);
     }
 
@@ -2312,7 +2350,7 @@ mod tests {
             assert!(jh.id_checked().is_none());
         }
 
-        jh.join();
+        jh.join().unwrap();
     }
 
     #[crate::test(tarantool = "crate")]
@@ -2338,7 +2376,7 @@ mod tests {
             assert_eq!(fiber::name_of(f_id).unwrap(), NAME2);
 
             assert!(fiber::exists(f_id));
-            jh.join();
+            jh.join().unwrap();
             assert!(!fiber::exists(f_id));
 
             // After the fiber has been joined, it no longer exists.
@@ -2361,7 +2399,7 @@ mod tests {
 
             let f_id = f_id.get().unwrap();
             assert!(fiber::exists(f_id));
-            jh.join();
+            jh.join().unwrap();
             assert!(!fiber::exists(f_id));
         }
     }
@@ -2386,7 +2424,7 @@ mod tests {
             assert_eq!(fiber::csw(), csw_parent_0 + 1);
             assert_eq!(fiber::csw_of(child_id).unwrap(), csw_child_0 + 1);
 
-            assert_eq!(jh.join(), 1337);
+            assert_eq!(jh.join().unwrap(), 1337);
 
             assert_eq!(fiber::csw(), csw_parent_0 + 2);
             // After the fiber has been joined, it no longer exists.
@@ -2408,7 +2446,7 @@ mod tests {
 
             assert_eq!(fiber::csw(), csw_parent_0 + 1);
 
-            assert_eq!(jh.join(), 1337);
+            assert_eq!(jh.join().unwrap(), 1337);
 
             assert_eq!(fiber::csw(), csw_parent_0 + 2);
 
@@ -2583,11 +2621,11 @@ mod tests {
         let _guard = LuaStackIntegrityGuard::global("defer_lua");
 
         let jh = Builder::new().func(|| 42).defer_lua().unwrap();
-        let res = jh.join();
+        let res = jh.join().unwrap();
         assert_eq!(res, 42);
 
         let jh = Builder::new().func(|| ()).defer_lua().unwrap();
-        jh.join();
+        jh.join().unwrap();
     }
 
     #[crate::test(tarantool = "crate")]
@@ -2659,6 +2697,6 @@ mod tests {
         // Return value from cond.wait_deadline() after fiber::wakeup was called on a cancelled fiber
         assert_eq!(ch.recv().unwrap(), false);
 
-        jh.join();
+        jh.join().unwrap();
     }
 }
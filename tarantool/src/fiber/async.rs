@@ -21,6 +21,9 @@
 //! - Channels
 //!   - [`oneshot`]
 //!   - [`watch`]
+//! - Timers
+//!   - [`sleep`]
+//!   - [`interval()`]
 //! - Extension Traits:
 //!   - [`timeout::IntoTimeout`]
 //!   - [`IntoOnDrop`]
@@ -29,11 +32,13 @@ use std::{future::Future, pin::Pin, rc::Rc, task::Poll, time::Duration};
 
 use futures::pin_mut;
 
+pub mod interval;
 pub mod mutex;
 pub mod oneshot;
 pub mod timeout;
 pub mod watch;
 
+pub use interval::{interval, interval_at, Interval};
 pub use mutex::Mutex;
 
 #[cfg(feature = "async-std")]
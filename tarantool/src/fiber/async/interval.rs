@@ -0,0 +1,170 @@
+//! A periodic timer for the fiber based async runtime.
+//!
+//! See [`interval`] documentation for more details.
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use super::context::ContextExt;
+use crate::fiber;
+use crate::time::Instant;
+
+pub use crate::fiber::scheduler::MissedTickBehavior;
+
+/// A stream of periodic ticks returned by [`interval`] and [`interval_at`].
+///
+/// Implements [`futures::Stream`] yielding the scheduled [`Instant`] of each
+/// tick. The stream never ends.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Interval {
+    next: Instant,
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+/// Creates a new [`Interval`] which yields every `period`. The first tick
+/// completes immediately.
+///
+/// The default [`MissedTickBehavior`] is [`MissedTickBehavior::Burst`], use
+/// [`Interval::set_missed_tick_behavior`] to change it.
+///
+/// ```no_run
+/// use tarantool::fiber::r#async::interval::interval;
+/// use tarantool::fiber;
+/// use std::time::Duration;
+///
+/// fiber::block_on(async {
+///     let mut interval = interval(Duration::from_secs(1));
+///     for _ in 0..3 {
+///         interval.tick().await;
+///         println!("tick");
+///     }
+/// });
+/// ```
+#[inline(always)]
+pub fn interval(period: Duration) -> Interval {
+    interval_at(fiber::clock(), period)
+}
+
+/// Creates a new [`Interval`] which yields every `period` with the first
+/// tick completing at `start`.
+#[inline(always)]
+pub fn interval_at(start: Instant, period: Duration) -> Interval {
+    Interval {
+        next: start,
+        period,
+        missed_tick_behavior: MissedTickBehavior::Burst,
+    }
+}
+
+impl Interval {
+    /// Completes when the next tick is reached. Returns the scheduled time
+    /// of the tick.
+    #[inline(always)]
+    pub fn tick(&mut self) -> Tick<'_> {
+        Tick { interval: self }
+    }
+
+    /// Resets the interval so that the next tick completes after one
+    /// `period` from now.
+    #[inline(always)]
+    pub fn reset(&mut self) {
+        self.next = fiber::clock() + self.period;
+    }
+
+    /// Returns the period of the interval.
+    #[inline(always)]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+
+    /// Returns the current [`MissedTickBehavior`].
+    #[inline(always)]
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Sets the [`MissedTickBehavior`].
+    #[inline(always)]
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<Instant> {
+        let now = fiber::clock();
+        if now < self.next {
+            // SAFETY: This is safe as long as the `Context` really
+            // is the `ContextExt`. It's always true within provided
+            // `block_on` async runtime.
+            unsafe { ContextExt::set_deadline(cx, self.next) };
+            return Poll::Pending;
+        }
+
+        let tick = self.next;
+        self.next = self.missed_tick_behavior.next_tick(tick, now, self.period);
+        Poll::Ready(tick)
+    }
+}
+
+impl futures::Stream for Interval {
+    type Item = Instant;
+
+    #[inline(always)]
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Instant>> {
+        self.get_mut().poll_tick(cx).map(Some)
+    }
+}
+
+/// Future returned by [`Interval::tick`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Tick<'a> {
+    interval: &'a mut Interval,
+}
+
+impl Future for Tick<'_> {
+    type Output = Instant;
+
+    #[inline(always)]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Instant> {
+        self.get_mut().interval.poll_tick(cx)
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    const PERIOD: Duration = Duration::from_millis(10);
+
+    #[crate::test(tarantool = "crate")]
+    fn ticks() {
+        let start = fiber::clock();
+        let ticks: Vec<_> = fiber::block_on(interval(PERIOD).take(3).collect());
+        assert_eq!(ticks.len(), 3);
+        assert!(ticks[0] >= start);
+        assert_eq!(ticks[1], ticks[0] + PERIOD);
+        assert_eq!(ticks[2], ticks[1] + PERIOD);
+        assert!(start.elapsed() >= PERIOD * 2);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn missed_ticks() {
+        fiber::block_on(async {
+            let mut interval = interval(PERIOD);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let first = interval.tick().await;
+            fiber::r#async::sleep(PERIOD * 3 + PERIOD / 2).await;
+            // The late tick completes immediately...
+            let second = interval.tick().await;
+            assert_eq!(second, first + PERIOD);
+            // ...and the missed ones are skipped.
+            let third = interval.tick().await;
+            assert_eq!(third, first + PERIOD * 4);
+        });
+    }
+}
@@ -60,7 +60,7 @@ impl<T: ?Sized> Mutex<T> {
     ///
     /// start_async(async move {
     ///     *c_mutex.lock().await = 10;
-    /// }).join();
+    /// }).join().unwrap();
     /// block_on(async { assert_eq!(*mutex.lock().await, 10) });
     /// ```
     pub async fn lock(&self) -> MutexGuard<'_, T> {
@@ -108,7 +108,7 @@ impl<T: ?Sized> Mutex<T> {
     ///     } else {
     ///         println!("try_lock failed");
     ///     }
-    /// }).join();
+    /// }).join().unwrap();
     /// assert_eq!(*mutex.try_lock().unwrap(), 10);
     /// ```
     pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
@@ -321,7 +321,7 @@ mod tests {
             fiber::r#yield().unwrap();
         }
         for handle in handles.into_iter() {
-            handle.join();
+            handle.join().unwrap();
         }
         fiber::block_on(async {
             let lock = mutex.lock().await;
@@ -360,6 +360,6 @@ mod tests {
             let lock = mutex.lock().await;
             assert_eq!(num_tasks, *lock);
         });
-        handle.join();
+        handle.join().unwrap();
     }
 }
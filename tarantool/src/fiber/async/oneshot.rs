@@ -224,7 +224,7 @@ mod tests {
         let (tx, rx) = channel::<i32>();
         let jh = fiber::start_async(rx);
         tx.send(39).unwrap();
-        assert_eq!(jh.join(), Ok(39));
+        assert_eq!(jh.join().unwrap(), Ok(39));
     }
 
     #[crate::test(tarantool = "crate")]
@@ -232,7 +232,7 @@ mod tests {
         let (tx, rx) = channel::<i32>();
         let jh = fiber::start_async(rx);
         drop(tx);
-        assert_eq!(jh.join(), Err(RecvError));
+        assert_eq!(jh.join().unwrap(), Err(RecvError));
     }
 
     #[crate::test(tarantool = "crate")]
@@ -261,7 +261,7 @@ mod tests {
         tx1.send(201).unwrap();
         fiber::sleep(Duration::ZERO);
         tx2.send(202).unwrap();
-        assert_eq!(jh.join(), (Ok(201), Ok(202)));
+        assert_eq!(jh.join().unwrap(), (Ok(201), Ok(202)));
     }
 
     #[crate::test(tarantool = "crate")]
@@ -273,6 +273,6 @@ mod tests {
         tx1.send(301).unwrap();
         fiber::sleep(Duration::ZERO);
         drop(tx2);
-        assert_eq!(jh.join(), (Ok(301), Err(RecvError)));
+        assert_eq!(jh.join().unwrap(), (Ok(301), Err(RecvError)));
     }
 }
@@ -162,7 +162,7 @@ mod tests {
         let fut = async move { rx.timeout(_0_SEC).await };
 
         let jh = fiber::start_async(fut);
-        assert_eq!(jh.join(), Err(Error::Expired));
+        assert_eq!(jh.join().unwrap(), Err(Error::Expired));
         drop(tx);
     }
 
@@ -173,7 +173,7 @@ mod tests {
 
         let jh = fiber::start(move || fiber::block_on(fut));
         drop(tx);
-        assert_eq!(jh.join(), Err(Error::Failed(RecvError)));
+        assert_eq!(jh.join().unwrap(), Err(Error::Failed(RecvError)));
     }
 
     #[crate::test(tarantool = "crate")]
@@ -183,7 +183,7 @@ mod tests {
 
         let jh = fiber::start(move || fiber::block_on(fut));
         tx.send(400).unwrap();
-        assert_eq!(jh.join(), Ok(400));
+        assert_eq!(jh.join().unwrap(), Ok(400));
     }
 
     #[crate::test(tarantool = "crate")]
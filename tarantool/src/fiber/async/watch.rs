@@ -392,7 +392,7 @@ mod tests {
             (*rx_1.borrow(), *rx_2.borrow(), *rx_3.borrow())
         });
         tx.send(20).unwrap();
-        assert_eq!(jh.join(), (20, 20, 20))
+        assert_eq!(jh.join().unwrap(), (20, 20, 20))
     }
 
     #[crate::test(tarantool = "crate")]
@@ -403,13 +403,13 @@ mod tests {
             *rx_1.borrow()
         });
         tx.send(1).unwrap();
-        assert_eq!(jh.join(), 1);
+        assert_eq!(jh.join().unwrap(), 1);
         let jh = fiber::start_async(async {
             rx_1.changed().await.unwrap();
             *rx_1.borrow()
         });
         tx.send(2).unwrap();
-        assert_eq!(jh.join(), 2);
+        assert_eq!(jh.join().unwrap(), 2);
     }
 
     #[crate::test(tarantool = "crate")]
@@ -435,7 +435,7 @@ mod tests {
         let (tx, mut rx_1) = channel::<i32>(10);
         let jh = fiber::start_async(rx_1.changed());
         drop(tx);
-        assert_eq!(jh.join(), Err(RecvError));
+        assert_eq!(jh.join().unwrap(), Err(RecvError));
     }
 
     #[crate::test(tarantool = "crate")]
@@ -445,8 +445,8 @@ mod tests {
         let jh_1 = fiber::start_async(rx_1.changed());
         let jh_2 = fiber::start_async(rx_2.changed());
         tx.send(1).unwrap();
-        assert!(jh_1.join().is_ok());
-        assert!(jh_2.join().is_ok());
+        assert!(jh_1.join().unwrap().is_ok());
+        assert!(jh_2.join().unwrap().is_ok());
     }
 
     #[crate::test(tarantool = "crate")]
@@ -457,7 +457,7 @@ mod tests {
             rx.get_cloned()
         });
         tx.send_modify(|v| v.push(37)).unwrap();
-        assert_eq!(jh.join(), [13, 37]);
+        assert_eq!(jh.join().unwrap(), [13, 37]);
     }
 
     #[crate::test(tarantool = "crate")]
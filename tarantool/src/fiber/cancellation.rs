@@ -0,0 +1,520 @@
+//! Structured cancellation for fibers.
+//!
+//! [`fiber::cancel`] is flat: cancelling a fiber doesn't affect the fibers it
+//! has spawned. A [`CancellationToken`] on the other hand can be organized
+//! into a tree: cancelling a token also cancels all of its child tokens
+//! (see [`CancellationToken::child_token`]).
+//!
+//! A token can be attached to a fiber via [`fiber::Builder::cancellation_token`].
+//! When the token is cancelled the fiber is [cancelled](crate::fiber::cancel).
+//! Fibers waiting via [`CancellationToken::wait_cond`] or
+//! [`CancellationToken::recv`] are also woken up, so the wait is interrupted.
+//! Fibers spawned via [`fiber::Builder`] from a fiber with an attached token
+//! automatically get a child of that token, so cancellation propagates to the
+//! whole tree of fibers.
+//!
+//! When the last handle of a token is dropped all of its child tokens are
+//! cancelled, so the sub-work of a request handler is aborted as soon as the
+//! handler returns.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber::{self, CancellationToken};
+//!
+//! let token = CancellationToken::new();
+//! let jh = fiber::Builder::new()
+//!     .cancellation_token(token.child_token())
+//!     .func(|| {
+//!         let cond = fiber::Cond::new();
+//!         // This is woken up as soon as the token is cancelled.
+//!         let token = CancellationToken::current().unwrap();
+//!         token.wait_cond(&cond).unwrap_err();
+//!     })
+//!     .start()
+//!     .unwrap();
+//!
+//! token.cancel();
+//! jh.join().unwrap();
+//! ```
+//!
+//! [`fiber::cancel`]: crate::fiber::cancel
+//! [`fiber::Builder`]: crate::fiber::Builder
+//! [`fiber::Builder::cancellation_token`]: crate::fiber::Builder::cancellation_token
+//! [`Cond`]: crate::fiber::Cond
+//! [`Channel`]: crate::fiber::Channel
+
+use super::{Channel, Cond, FiberId};
+use crate::error::{BoxError, TarantoolErrorCode};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+
+/// Error returned when an operation is interrupted because the corresponding
+/// [`CancellationToken`] was cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("operation was cancelled")]
+pub struct Cancelled;
+
+impl From<Cancelled> for crate::error::Error {
+    #[inline]
+    #[track_caller]
+    fn from(e: Cancelled) -> Self {
+        BoxError::new(TarantoolErrorCode::ProcLua, e.to_string()).into()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// CancellationToken
+////////////////////////////////////////////////////////////////////////////////
+
+/// A token which can be used to cancel a tree of fibers and async
+/// operations. See [module level documentation](self) for details.
+///
+/// Cloning a token produces another handle to the same token.
+#[derive(Clone)]
+pub struct CancellationToken {
+    node: Rc<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+    is_cancelled: Cell<bool>,
+    children: RefCell<Vec<Weak<Node>>>,
+    fibers: RefCell<Vec<FiberId>>,
+    /// Fibers blocked in [`CancellationToken::wait_cond`] or
+    /// [`CancellationToken::recv`], which are woken up on cancellation.
+    waiting: RefCell<Vec<FiberId>>,
+    wakers: RefCell<Vec<Waker>>,
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        for child in self.children.take() {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+impl Node {
+    fn cancel(&self) {
+        if self.is_cancelled.replace(true) {
+            return;
+        }
+
+        for waker in self.wakers.take() {
+            waker.wake();
+        }
+
+        let fibers = self.fibers.borrow().clone();
+        for id in fibers {
+            super::cancel(id);
+        }
+        // Only the fibers waiting via the token are woken up explicitly, other
+        // waits (e.g. `coio_call`) must not be interrupted spuriously.
+        let waiting = self.waiting.borrow().clone();
+        if !waiting.is_empty() {
+            let current = super::id();
+            for id in waiting {
+                if id != current {
+                    super::wakeup(id);
+                }
+            }
+        }
+
+        for child in self.children.take() {
+            if let Some(child) = child.upgrade() {
+                child.cancel();
+            }
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<HashMap<FiberId, CancellationToken>> = RefCell::default();
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("is_cancelled", &self.is_cancelled())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for CancellationToken {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancellationToken {
+    /// Creates a new root token which is not cancelled.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            node: Default::default(),
+        }
+    }
+
+    /// Returns the token attached to the current fiber, if there's one.
+    ///
+    /// A token is attached to a fiber if it was spawned with
+    /// [`fiber::Builder::cancellation_token`], or if it was spawned from
+    /// another fiber with an attached token, or while inside
+    /// [`CancellationToken::scope`].
+    ///
+    /// [`fiber::Builder::cancellation_token`]: crate::fiber::Builder::cancellation_token
+    #[inline]
+    pub fn current() -> Option<Self> {
+        let id = super::id();
+        CURRENT.with(|current| current.borrow().get(&id).cloned())
+    }
+
+    /// Creates a child token. The child is cancelled when this token is
+    /// cancelled or when the last handle of this token is dropped, but
+    /// cancelling the child doesn't affect this token.
+    ///
+    /// If this token is already cancelled, the child is also cancelled.
+    pub fn child_token(&self) -> Self {
+        let child = Self::new();
+        if self.is_cancelled() {
+            child.node.is_cancelled.set(true);
+            return child;
+        }
+
+        let mut children = self.node.children.borrow_mut();
+        children.retain(|c| c.strong_count() > 0);
+        children.push(Rc::downgrade(&child.node));
+        child
+    }
+
+    /// Cancels this token and all of its descendants. All the fibers attached
+    /// to the cancelled tokens are [cancelled], the ones waiting in
+    /// [`Self::wait_cond`] or [`Self::recv`] are woken up, all the pending
+    /// [`Self::cancelled`] futures are resolved.
+    ///
+    /// **Does NOT yield**.
+    ///
+    /// [cancelled]: crate::fiber::cancel
+    #[inline(always)]
+    pub fn cancel(&self) {
+        self.node.cancel()
+    }
+
+    /// Returns `true` if the token has been cancelled.
+    #[inline(always)]
+    pub fn is_cancelled(&self) -> bool {
+        self.node.is_cancelled.get()
+    }
+
+    /// Returns [`Cancelled`] if the token has been cancelled.
+    #[inline]
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            return Err(Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Calls `f` with this token attached to the current fiber. While `f` is
+    /// running [`CancellationToken::current`] returns this token and
+    /// cancelling it cancels the current fiber.
+    ///
+    /// If the token is already cancelled the current fiber is cancelled
+    /// before calling `f`.
+    ///
+    /// **NOTE**: tarantool fibers cannot be "uncancelled", so the current
+    /// fiber stays cancelled even after `f` returns.
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let _guard = ScopeGuard::new(self);
+        f()
+    }
+
+    /// Waits on the `cond` until it's signalled or the token is cancelled.
+    ///
+    /// Returns [`Cancelled`] if the token was cancelled.
+    ///
+    /// This function **yields**.
+    #[inline]
+    pub fn wait_cond(&self, cond: &Cond) -> Result<(), Cancelled> {
+        self.check()?;
+        self.scope(|| {
+            let _guard = WaitGuard::new(&self.node);
+            cond.wait()
+        });
+        self.check()
+    }
+
+    /// Receives a message from the `channel` waiting until either there's a
+    /// message, the channel is closed or the token is cancelled.
+    ///
+    /// Returns `Ok(None)` if the channel was closed and [`Cancelled`] if the
+    /// token was cancelled.
+    ///
+    /// This function may **yield**.
+    #[inline]
+    pub fn recv<T>(&self, channel: &Channel<T>) -> Result<Option<T>, Cancelled> {
+        self.check()?;
+        let res = self.scope(|| {
+            let _guard = WaitGuard::new(&self.node);
+            channel.recv()
+        });
+        self.check()?;
+        Ok(res)
+    }
+
+    /// Returns a future which is resolved when the token is cancelled.
+    #[inline(always)]
+    pub fn cancelled(&self) -> WaitForCancellation {
+        WaitForCancellation {
+            token: self.clone(),
+        }
+    }
+
+    /// Runs the future `f` until it completes or the token is cancelled. In
+    /// the latter case the future is dropped and [`Cancelled`] is returned.
+    pub async fn run_until_cancelled<F: Future>(&self, f: F) -> Result<F::Output, Cancelled> {
+        use futures::future::{select, Either};
+
+        futures::pin_mut!(f);
+        match select(f, self.cancelled()).await {
+            Either::Left((t, _)) => Ok(t),
+            Either::Right(((), _)) => Err(Cancelled),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ScopeGuard
+////////////////////////////////////////////////////////////////////////////////
+
+struct ScopeGuard {
+    fiber_id: FiberId,
+    node: Rc<Node>,
+    previous: Option<CancellationToken>,
+}
+
+impl ScopeGuard {
+    fn new(token: &CancellationToken) -> Self {
+        let fiber_id = super::id();
+        token.node.fibers.borrow_mut().push(fiber_id);
+        let previous = CURRENT.with(|current| current.borrow_mut().insert(fiber_id, token.clone()));
+        if token.is_cancelled() {
+            super::cancel(fiber_id);
+        }
+        Self {
+            fiber_id,
+            node: token.node.clone(),
+            previous,
+        }
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let mut fibers = self.node.fibers.borrow_mut();
+        if let Some(i) = fibers.iter().rposition(|&id| id == self.fiber_id) {
+            fibers.swap_remove(i);
+        }
+        drop(fibers);
+
+        let previous = self.previous.take();
+        // Drop the removed token outside of the `CURRENT` borrow, because
+        // dropping the last handle cancels the child tokens.
+        let _removed = CURRENT.with(|current| {
+            let mut current = current.borrow_mut();
+            match previous {
+                Some(previous) => current.insert(self.fiber_id, previous),
+                None => current.remove(&self.fiber_id),
+            }
+        });
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// WaitGuard
+////////////////////////////////////////////////////////////////////////////////
+
+/// Registers the current fiber as waiting via the token, see [`Node::waiting`].
+struct WaitGuard {
+    fiber_id: FiberId,
+    node: Rc<Node>,
+}
+
+impl WaitGuard {
+    fn new(node: &Rc<Node>) -> Self {
+        let fiber_id = super::id();
+        node.waiting.borrow_mut().push(fiber_id);
+        Self {
+            fiber_id,
+            node: node.clone(),
+        }
+    }
+}
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        let mut waiting = self.node.waiting.borrow_mut();
+        if let Some(i) = waiting.iter().rposition(|&id| id == self.fiber_id) {
+            waiting.swap_remove(i);
+        }
+    }
+}
+
+/// Wraps the fiber function `f` so that it runs in the scope of `token` or
+/// a child of the current fiber's token.
+pub(super) fn wrap_fiber_func<'f, F, T>(
+    f: F,
+    token: Option<CancellationToken>,
+) -> impl FnOnce() -> T + 'f
+where
+    F: FnOnce() -> T + 'f,
+{
+    // Don't look up the current fiber id if there are no tokens at all, it
+    // may be slow on some versions of tarantool.
+    let token = token.or_else(|| {
+        if CURRENT.with(|current| current.borrow().is_empty()) {
+            return None;
+        }
+        CancellationToken::current().map(|t| t.child_token())
+    });
+    move || match token {
+        Some(token) => token.scope(f),
+        None => f(),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// WaitForCancellation
+////////////////////////////////////////////////////////////////////////////////
+
+/// Future returned by [`CancellationToken::cancelled`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct WaitForCancellation {
+    token: CancellationToken,
+}
+
+impl Future for WaitForCancellation {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let node = &self.token.node;
+        if node.is_cancelled.get() {
+            return Poll::Ready(());
+        }
+
+        let mut wakers = node.wakers.borrow_mut();
+        if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn tree_cancellation() {
+        let root = CancellationToken::new();
+        let child = root.child_token();
+        let grandchild = child.child_token();
+        let other = root.child_token();
+
+        child.cancel();
+        assert!(!root.is_cancelled());
+        assert!(child.is_cancelled());
+        assert!(grandchild.is_cancelled());
+        assert!(!other.is_cancelled());
+        assert_eq!(grandchild.check(), Err(Cancelled));
+
+        let late = child.child_token();
+        assert!(late.is_cancelled());
+
+        // Dropping the last handle cancels the subtree.
+        drop(root);
+        assert!(other.is_cancelled());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cancel_wakes_up_fibers() {
+        let token = CancellationToken::new();
+        let cond = Rc::new(Cond::new());
+        let ch = Channel::<i32>::new(1);
+
+        let c = cond.clone();
+        let waiter = fiber::Builder::new()
+            .cancellation_token(token.child_token())
+            .func(move || {
+                let res = CancellationToken::current().unwrap().wait_cond(&c);
+                (res, fiber::is_cancelled())
+            })
+            .start()
+            .unwrap();
+
+        let reader = if crate::ffi::has_fiber_channel() {
+            let ch = ch.clone();
+            let jh = fiber::Builder::new()
+                .cancellation_token(token.child_token())
+                .func(move || {
+                    // Nested fibers inherit the token.
+                    fiber::start(move || ch.recv()).join().unwrap()
+                })
+                .start()
+                .unwrap();
+            Some(jh)
+        } else {
+            None
+        };
+
+        token.cancel();
+        assert_eq!(waiter.join().unwrap(), (Err(Cancelled), true));
+        if let Some(reader) = reader {
+            assert_eq!(reader.join().unwrap(), None);
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn current_token_scope() {
+        assert!(CancellationToken::current().is_none());
+        let token = CancellationToken::new();
+        fiber::start(|| {
+            token.scope(|| {
+                assert!(CancellationToken::current().is_some());
+                assert!(!fiber::is_cancelled());
+            });
+            assert!(CancellationToken::current().is_none());
+        })
+        .join()
+        .unwrap();
+        assert!(CancellationToken::current().is_none());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn async_cancellation() {
+        let token = CancellationToken::new();
+        let t = token.clone();
+        let jh = fiber::start(move || {
+            fiber::block_on(t.run_until_cancelled(fiber::r#async::sleep(Duration::from_secs(100))))
+        });
+        token.cancel();
+        assert_eq!(jh.join().unwrap(), Err(Cancelled));
+
+        // Child of a cancelled token is resolved immediately.
+        fiber::block_on(token.child_token().cancelled());
+    }
+}
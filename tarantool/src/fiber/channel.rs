@@ -1,5 +1,6 @@
 use std::{marker::PhantomData, mem::MaybeUninit, ptr::NonNull, rc::Rc, time::Duration};
 
+use super::select::SelectWaiters;
 use crate::{error::TarantoolErrorCode, ffi::tarantool as ffi};
 
 ////////////////////////////////////////////////////////////////////////////////
@@ -43,6 +44,7 @@ impl<T> Channel<T> {
             .expect("Memory allocation failure when creating fiber::Channel");
         Self(Rc::new(ChannelBox {
             inner,
+            select_waiters: Default::default(),
             marker: PhantomData,
         }))
     }
@@ -52,8 +54,17 @@ impl<T> Channel<T> {
         self.0.inner.as_ptr()
     }
 
+    /// Returns the fibers waiting on this channel in a [`Select`].
+    ///
+    /// [`Select`]: crate::fiber::select::Select
+    #[inline(always)]
+    pub(crate) fn select_waiters(&self) -> &SelectWaiters {
+        &self.0.select_waiters
+    }
+
     #[inline(always)]
     pub fn close(self) {
+        self.0.select_waiters.notify();
         unsafe { ffi::fiber_channel_close(self.as_ptr()) }
     }
 
@@ -93,6 +104,9 @@ impl<T> SendTimeout<T> for Channel<T> {
     where
         T: 'static,
     {
+        // The selects are woken up before the message is sent, because we
+        // may block until a reader appears.
+        self.0.select_waiters.notify();
         unsafe {
             let ipc_value_ptr = ffi::ipc_value_new();
             let ipc_value = &mut *ipc_value_ptr;
@@ -132,6 +146,9 @@ impl<T> SendTimeout<T> for Channel<T> {
 
 impl<T> RecvTimeout<T> for Channel<T> {
     fn recv_maybe_timeout(&self, timeout: Option<Duration>) -> Result<T, RecvError> {
+        // The selects are woken up before the message is received, because
+        // we may block until a writer appears.
+        self.0.select_waiters.notify();
         unsafe {
             let mut ipc_msg_ptr_uninit = MaybeUninit::uninit();
             let ret_code = ffi::fiber_channel_get_msg_timeout(
@@ -400,6 +417,7 @@ impl<T> IntoIterator for Channel<T> {
 
 struct ChannelBox<T> {
     inner: NonNull<ffi::fiber_channel>,
+    select_waiters: SelectWaiters,
     marker: PhantomData<T>,
 }
 
@@ -489,6 +507,6 @@ mod tests {
         assert_eq!(send_res, Err(420));
         assert_eq!(try_send_res, Err(TrySendError::Disconnected(420)));
 
-        jh.join();
+        jh.join().unwrap();
     }
 }
@@ -0,0 +1,261 @@
+//! Introspection of all the fibers of the current cord.
+//!
+//! The data is obtained via the lua `fiber.info()` & `fiber.top()` functions,
+//! so these apis are relatively expensive and are intended for diagnostics
+//! (e.g. health-check stored procedures), not for the hot path.
+//!
+//! See also:
+//! - [Lua reference: fiber.info](https://www.tarantool.io/en/doc/latest/reference/reference_lua/fiber/#fiber-info)
+//! - [Lua reference: fiber.top](https://www.tarantool.io/en/doc/latest/reference/reference_lua/fiber/#fiber-top)
+
+use super::FiberId;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+////////////////////////////////////////////////////////////////////////////////
+// info
+////////////////////////////////////////////////////////////////////////////////
+
+/// Information about a single fiber. See [`info`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, tlua::LuaRead)]
+pub struct FiberInfo {
+    /// Fiber id.
+    pub id: FiberId,
+    /// Fiber name.
+    pub name: String,
+    /// Number of context switches of the fiber.
+    pub csw: u64,
+    /// Memory usage of the fiber.
+    pub memory: FiberMemory,
+    /// Backtrace of the fiber, one string per frame starting from the
+    /// innermost one.
+    ///
+    /// Is `None` if the backtrace wasn't requested (see [`info_with_backtrace`])
+    /// or if the current tarantool executable was built without backtrace
+    /// support.
+    pub backtrace: Option<Vec<String>>,
+}
+
+/// Memory usage of a fiber. See [`FiberInfo`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, tlua::LuaRead)]
+pub struct FiberMemory {
+    /// Total memory in bytes occupied by the fiber (including its stack).
+    pub total: u64,
+    /// Memory in bytes actually used by the fiber.
+    pub used: u64,
+}
+
+/// Returns information about all the fibers of the current cord sorted by
+/// fiber id. Backtraces are not collected, use [`info_with_backtrace`] if
+/// you need them.
+///
+/// This is the equivalent of lua's `fiber.info({ bt = false })`.
+#[inline(always)]
+pub fn info() -> crate::Result<Vec<FiberInfo>> {
+    info_impl(false)
+}
+
+/// Same as [`info`] but also collects the backtrace of each fiber (if
+/// supported by the current tarantool executable). This is significantly
+/// slower than [`info`].
+///
+/// This is the equivalent of lua's `fiber.info({ bt = true })`.
+#[inline(always)]
+pub fn info_with_backtrace() -> crate::Result<Vec<FiberInfo>> {
+    info_impl(true)
+}
+
+fn info_impl(backtrace: bool) -> crate::Result<Vec<FiberInfo>> {
+    let lua = crate::global_lua();
+    let mut res: Vec<FiberInfo> = lua
+        .eval_with(
+            "local bt = ...
+            local res = {}
+            for id, f in pairs(require('fiber').info({ bt = bt })) do
+                local backtrace
+                if bt and f.backtrace ~= nil then
+                    backtrace = {}
+                    for _, frame in ipairs(f.backtrace) do
+                        table.insert(backtrace, frame.C or frame.L or tostring(frame))
+                    end
+                end
+                table.insert(res, {
+                    id = id,
+                    name = f.name,
+                    csw = f.csw,
+                    memory = f.memory,
+                    backtrace = backtrace,
+                })
+            end
+            return res",
+            backtrace,
+        )
+        .map_err(tlua::LuaError::from)?;
+    res.sort_unstable_by_key(|f| f.id);
+    Ok(res)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// top
+////////////////////////////////////////////////////////////////////////////////
+
+/// A snapshot of per fiber cpu usage statistics. See [`top`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FiberTop {
+    /// Statistics for each fiber of the current cord sorted by fiber id.
+    pub fibers: Vec<FiberCpuUsage>,
+    /// Number of times the cpu time accounting was skipped due to the fiber
+    /// being migrated to a different cpu core.
+    pub cpu_misses: u64,
+}
+
+/// Cpu usage statistics of a single fiber. See [`top`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FiberCpuUsage {
+    /// Fiber id.
+    pub id: FiberId,
+    /// Fiber name.
+    pub name: String,
+    /// Percentage of the cord's cpu time spent in this fiber during the last
+    /// event loop iteration.
+    pub instant: f64,
+    /// Moving average of [`Self::instant`] percentage over all event loop
+    /// iterations.
+    pub average: f64,
+    /// Total cpu time spent in this fiber since [`top_enable`] was called.
+    pub time: Duration,
+}
+
+#[derive(tlua::LuaRead)]
+struct RawTop {
+    fibers: Vec<RawCpuUsage>,
+    cpu_misses: u64,
+}
+
+#[derive(tlua::LuaRead)]
+struct RawCpuUsage {
+    id: FiberId,
+    name: String,
+    instant: f64,
+    average: f64,
+    time: f64,
+}
+
+/// Returns cpu usage statistics of all the fibers of the current cord.
+///
+/// Returns an error if the statistics collection is disabled (see
+/// [`top_enable`]) or not supported by the current tarantool executable.
+///
+/// This is the equivalent of lua's `fiber.top()`.
+pub fn top() -> crate::Result<FiberTop> {
+    let lua = crate::global_lua();
+    let raw: RawTop = lua.eval(
+        "local top = require('fiber').top()
+        local fibers = {}
+        for key, usage in pairs(top.cpu) do
+            local id, name = key:match('^(%d+)/(.*)$')
+            table.insert(fibers, {
+                id = tonumber(id),
+                name = name,
+                instant = usage.instant,
+                average = usage.average,
+                time = usage.time,
+            })
+        end
+        return { fibers = fibers, cpu_misses = top.cpu_misses }",
+    )?;
+
+    let mut fibers: Vec<_> = raw
+        .fibers
+        .into_iter()
+        .map(|f| FiberCpuUsage {
+            id: f.id,
+            name: f.name,
+            instant: f.instant,
+            average: f.average,
+            time: Duration::from_secs_f64(f.time.max(0.0)),
+        })
+        .collect();
+    fibers.sort_unstable_by_key(|f| f.id);
+
+    Ok(FiberTop {
+        fibers,
+        cpu_misses: raw.cpu_misses,
+    })
+}
+
+/// Enables collection of per fiber cpu usage statistics. See [`top`].
+///
+/// Returns an error if this is not supported by the current tarantool
+/// executable.
+///
+/// **NOTE**: statistics collection has a small but noticeable performance
+/// overhead, so consider disabling it when it's not needed.
+#[inline]
+pub fn top_enable() -> crate::Result<()> {
+    crate::global_lua().exec("require('fiber').top_enable()")?;
+    Ok(())
+}
+
+/// Disables collection of per fiber cpu usage statistics. See [`top`].
+#[inline]
+pub fn top_disable() -> crate::Result<()> {
+    crate::global_lua().exec("require('fiber').top_disable()")?;
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+
+    #[crate::test(tarantool = "crate")]
+    fn info_lists_fibers() {
+        let jh = fiber::Builder::new()
+            .name("test_fiber_info")
+            .func(fiber::reschedule)
+            .start()
+            .unwrap();
+
+        let fibers = info().unwrap();
+        let me = fibers.iter().find(|f| f.id == fiber::id()).unwrap();
+        assert_eq!(me.name, fiber::name());
+        assert!(me.memory.total >= me.memory.used);
+        assert!(me.backtrace.is_none());
+        assert!(fibers.iter().any(|f| f.name == "test_fiber_info"));
+        assert!(fibers.windows(2).all(|w| w[0].id < w[1].id));
+
+        jh.join().unwrap();
+
+        let fibers = info_with_backtrace().unwrap();
+        assert!(!fibers.iter().any(|f| f.name == "test_fiber_info"));
+        assert!(fibers.iter().any(|f| f.id == fiber::id()));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn top_cpu_usage() {
+        let has_top: bool = crate::global_lua()
+            .eval("return require('fiber').top_enable ~= nil")
+            .unwrap();
+        if !has_top {
+            assert!(top_enable().is_err());
+            return;
+        }
+
+        top_disable().unwrap();
+        assert!(top().is_err());
+
+        top_enable().unwrap();
+        fiber::reschedule();
+        let stats = top().unwrap();
+        top_disable().unwrap();
+
+        let me = stats.fibers.iter().find(|f| f.id == fiber::id()).unwrap();
+        assert_eq!(me.name, fiber::name());
+        assert!(me.instant >= 0.0);
+    }
+}
@@ -70,7 +70,7 @@ impl<T: ?Sized> Mutex<T> {
     ///
     /// start_proc(move || {
     ///     *c_mutex.lock() = 10;
-    /// }).join();
+    /// }).join().unwrap();
     /// assert_eq!(*mutex.lock(), 10);
     /// ```
     #[track_caller]
@@ -116,7 +116,7 @@ impl<T: ?Sized> Mutex<T> {
     ///     } else {
     ///         println!("try_lock failed");
     ///     }
-    /// }).join();
+    /// }).join().unwrap();
     /// assert_eq!(*mutex.lock(), 10);
     /// ```
     #[track_caller]
@@ -0,0 +1,588 @@
+//! A fixed size pool of worker fibers.
+//!
+//! The [`Pool`] spawns a number of worker fibers which take jobs from a shared
+//! [`fiber::Channel`] and execute them one by one. This is useful for limiting
+//! the number of concurrently running jobs of some kind (e.g. requests to an
+//! external service).
+//!
+//! This api is backed by the tarantool `fiber_channel` api which is not
+//! available in all versions of tarantool. Use
+//! [`tarantool::ffi::has_fiber_channel`] to check if it is supported in your
+//! case.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber::pool::Pool;
+//!
+//! let pool = Pool::builder().name("workers").workers(4).queue_len(16).build().unwrap();
+//!
+//! let task = pool.submit(|| 2 + 2).unwrap();
+//! assert_eq!(task.join().unwrap(), 4);
+//!
+//! // Wait for all the queued jobs to finish and stop the workers.
+//! pool.shutdown();
+//! ```
+//!
+//! [`fiber::Channel`]: crate::fiber::Channel
+//! [`tarantool::ffi::has_fiber_channel`]: crate::ffi::has_fiber_channel
+
+use super::r#async::oneshot;
+use super::{Builder, Channel, JoinError, JoinHandle, SendError, TrySendError};
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Default number of worker fibers in a [`Pool`].
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// Default capacity of the job queue of a [`Pool`].
+pub const DEFAULT_QUEUE_LEN: u32 = 1024;
+
+////////////////////////////////////////////////////////////////////////////////
+// Pool
+////////////////////////////////////////////////////////////////////////////////
+
+/// A pool of worker fibers executing submitted jobs.
+///
+/// Use [`Pool::builder`] to configure and create a pool.
+///
+/// When the pool is dropped it is gracefully [shut down](Pool::shutdown),
+/// which means that the drop **yields** until all the queued jobs are done.
+pub struct Pool {
+    name: String,
+    queue: Channel<Message>,
+    workers: Vec<JoinHandle<'static, ()>>,
+    state: Rc<State>,
+}
+
+type Job = Box<dyn FnOnce()>;
+
+enum Message {
+    Job(Job),
+    Stop,
+}
+
+#[derive(Default)]
+struct State {
+    busy: Cell<usize>,
+    is_cancelled: Cell<bool>,
+}
+
+impl std::fmt::Debug for Pool {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Pool")
+            .field("name", &self.name)
+            .field("workers", &self.workers.len())
+            .field("busy_workers", &self.busy_workers())
+            .field("queue_depth", &self.queue_depth())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Pool {
+    /// Returns a [`PoolBuilder`] with the default configuration.
+    #[inline(always)]
+    pub fn builder() -> PoolBuilder {
+        PoolBuilder::new()
+    }
+
+    /// Creates a pool with `workers` worker fibers and default configuration.
+    #[inline(always)]
+    pub fn new(workers: usize) -> crate::Result<Self> {
+        Self::builder().workers(workers).build()
+    }
+
+    /// Returns the name of the pool. Worker fibers are named after it.
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Submit a job `f` for execution in one of the worker fibers.
+    ///
+    /// This function may **yield** if the job queue is full.
+    ///
+    /// Returns a [`TaskHandle`] which can be used to get the job's result.
+    /// Returns an error if the pool is shutting down or the current fiber was
+    /// cancelled while waiting for a place in the queue.
+    pub fn submit<F, T>(&self, f: F) -> Result<TaskHandle<T>, PoolError>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        let (job, task) = Self::prepare(f);
+        if self.queue.send(Message::Job(job)).is_err() {
+            return Err(PoolError::ShutDown);
+        }
+        Ok(task)
+    }
+
+    /// Like [`Self::submit`] but waits for at most `timeout` for a place in
+    /// the job queue.
+    pub fn submit_timeout<F, T>(&self, f: F, timeout: Duration) -> Result<TaskHandle<T>, PoolError>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        let (job, task) = Self::prepare(f);
+        match self.queue.send_timeout(Message::Job(job), timeout) {
+            Ok(()) => Ok(task),
+            Err(SendError::Timeout(_)) => Err(PoolError::Full),
+            Err(SendError::Disconnected(_)) => Err(PoolError::ShutDown),
+        }
+    }
+
+    /// Like [`Self::submit`] but never **yields**. Returns
+    /// [`PoolError::Full`] if there's no place in the job queue.
+    pub fn try_submit<F, T>(&self, f: F) -> Result<TaskHandle<T>, PoolError>
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        let (job, task) = Self::prepare(f);
+        match self.queue.try_send(Message::Job(job)) {
+            Ok(()) => Ok(task),
+            Err(TrySendError::Full(_)) => Err(PoolError::Full),
+            Err(TrySendError::Disconnected(_)) => Err(PoolError::ShutDown),
+        }
+    }
+
+    fn prepare<F, T>(f: F) -> (Job, TaskHandle<T>)
+    where
+        F: FnOnce() -> T + 'static,
+        T: 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let job = Box::new(move || {
+            // A panic is returned to the task's owner instead of killing the
+            // worker fiber.
+            let result = super::unwind::catch_unwind(f);
+            // The receiver may have been dropped, which means nobody is
+            // interested in the result.
+            _ = tx.send(result);
+        });
+        (job, TaskHandle { rx })
+    }
+
+    /// Returns the number of worker fibers in the pool.
+    #[inline(always)]
+    pub fn worker_count(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns the number of worker fibers currently executing a job.
+    #[inline(always)]
+    pub fn busy_workers(&self) -> usize {
+        self.state.busy.get()
+    }
+
+    /// Returns the number of jobs waiting in the queue.
+    #[inline(always)]
+    pub fn queue_depth(&self) -> usize {
+        self.queue.count() as _
+    }
+
+    /// Returns the maximum number of jobs which can wait in the queue before
+    /// [`Self::submit`] starts blocking.
+    #[inline(always)]
+    pub fn queue_capacity(&self) -> usize {
+        self.queue.size() as _
+    }
+
+    /// Returns a snapshot of the pool's metrics.
+    #[inline]
+    pub fn stats(&self) -> PoolStats {
+        PoolStats {
+            workers: self.worker_count(),
+            busy_workers: self.busy_workers(),
+            queue_depth: self.queue_depth(),
+            queue_capacity: self.queue_capacity(),
+        }
+    }
+
+    /// Gracefully shut down the pool. All the jobs which were submitted
+    /// before this call will be executed.
+    ///
+    /// This function **yields** until all the worker fibers are done.
+    ///
+    /// **NOTE**: calling this from one of the pool's worker fibers will result
+    /// in a deadlock.
+    #[inline(always)]
+    pub fn shutdown(mut self) {
+        self.stop();
+    }
+
+    /// Shut down the pool discarding all the jobs which are still in the
+    /// queue. Corresponding [`TaskHandle`]s will return
+    /// [`PoolError::Cancelled`]. Jobs which are already being executed are
+    /// allowed to finish.
+    ///
+    /// This function **yields** until all the worker fibers are done.
+    #[inline]
+    pub fn shutdown_now(mut self) {
+        self.state.is_cancelled.set(true);
+        while let Ok(msg) = self.queue.try_recv() {
+            drop(msg);
+        }
+        self.stop();
+    }
+
+    fn stop(&mut self) {
+        for _ in 0..self.workers.len() {
+            if self.queue.send(Message::Stop).is_err() {
+                // The current fiber was cancelled, wake up the workers
+                // the hard way.
+                for worker in &self.workers {
+                    worker.cancel();
+                }
+                break;
+            }
+        }
+        for worker in self.workers.drain(..) {
+            if let Err(e) = worker.join() {
+                crate::say_error!("{e}");
+            }
+        }
+    }
+}
+
+impl Drop for Pool {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn worker_main(queue: Channel<Message>, state: Rc<State>) {
+    while let Some(msg) = queue.recv() {
+        match msg {
+            Message::Job(job) => {
+                if state.is_cancelled.get() {
+                    continue;
+                }
+                state.busy.set(state.busy.get() + 1);
+                // Never panics, see `Pool::prepare`.
+                job();
+                state.busy.set(state.busy.get() - 1);
+            }
+            Message::Stop => break,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PoolBuilder
+////////////////////////////////////////////////////////////////////////////////
+
+/// Factory for [`Pool`] which can be used to configure its properties.
+#[derive(Debug, Clone)]
+pub struct PoolBuilder {
+    name: Option<String>,
+    workers: usize,
+    queue_len: u32,
+    stack_size: Option<usize>,
+}
+
+impl Default for PoolBuilder {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PoolBuilder {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            workers: DEFAULT_WORKERS,
+            queue_len: DEFAULT_QUEUE_LEN,
+            stack_size: None,
+        }
+    }
+
+    /// Sets the name of the pool. Worker fibers will be named
+    /// `"<name>/<index>"`.
+    ///
+    /// The name must not contain null bytes (`\0`).
+    #[inline(always)]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the number of worker fibers.
+    #[inline(always)]
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// Sets the capacity of the job queue.
+    #[inline(always)]
+    pub fn queue_len(mut self, queue_len: u32) -> Self {
+        self.queue_len = queue_len;
+        self
+    }
+
+    /// Sets the size of the stack (in bytes) for the worker fibers.
+    ///
+    /// The value is validated when [`Self::build`] is called, see also
+    /// [`fiber::Builder::stack_size`].
+    ///
+    /// [`fiber::Builder::stack_size`]: crate::fiber::Builder::stack_size
+    #[inline(always)]
+    pub fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = Some(stack_size);
+        self
+    }
+
+    /// Creates the pool and starts the worker fibers.
+    ///
+    /// Returns an error if
+    /// - spawning a fiber failed,
+    /// - the stack size is invalid,
+    /// - the pool name contains a nul byte.
+    ///
+    /// The current fiber **yields** to each new worker fiber.
+    pub fn build(self) -> crate::Result<Pool> {
+        let Self {
+            name,
+            workers,
+            queue_len,
+            stack_size,
+        } = self;
+        let name = name.unwrap_or_else(|| "<rust pool>".into());
+
+        let mut pool = Pool {
+            name,
+            queue: Channel::new(queue_len),
+            workers: Vec::with_capacity(workers),
+            state: Default::default(),
+        };
+
+        for i in 0..workers {
+            let queue = pool.queue.clone();
+            let state = pool.state.clone();
+            let mut builder = Builder::new()
+                .name(format!("{}/{}", pool.name, i))
+                .func(move || worker_main(queue, state));
+            if let Some(stack_size) = stack_size {
+                builder = builder.stack_size(stack_size)?;
+            }
+            // If this fails the already started workers are stopped when
+            // `pool` is dropped.
+            let jh = builder.start()?;
+            pool.workers.push(jh);
+        }
+
+        Ok(pool)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TaskHandle
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle to a job submitted into a [`Pool`].
+///
+/// Can be used to block on the job's result via [`TaskHandle::join`] or can
+/// be `.await`ed in async code.
+///
+/// Dropping the handle doesn't cancel the job.
+#[must_use = "the job's result is lost if the handle is dropped"]
+pub struct TaskHandle<T> {
+    rx: oneshot::Receiver<Result<T, JoinError>>,
+}
+
+impl<T> std::fmt::Debug for TaskHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TaskHandle").finish_non_exhaustive()
+    }
+}
+
+impl<T> TaskHandle<T> {
+    /// Block until the job is done and return its result.
+    ///
+    /// Returns [`PoolError::Cancelled`] if the job was discarded before it
+    /// could be executed (see [`Pool::shutdown_now`]) and
+    /// [`PoolError::Panicked`] if the job panicked.
+    #[inline(always)]
+    pub fn join(self) -> Result<T, PoolError> {
+        super::block_on(self)
+    }
+}
+
+impl<T> Future for TaskHandle<T> {
+    type Output = Result<T, PoolError>;
+
+    #[inline]
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx).poll(cx).map(|res| match res {
+            Ok(Ok(v)) => Ok(v),
+            Ok(Err(e)) => Err(PoolError::Panicked(e)),
+            Err(_) => Err(PoolError::Cancelled),
+        })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PoolStats
+////////////////////////////////////////////////////////////////////////////////
+
+/// A snapshot of [`Pool`]'s metrics. See [`Pool::stats`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Number of worker fibers.
+    pub workers: usize,
+    /// Number of worker fibers executing a job at the moment.
+    pub busy_workers: usize,
+    /// Number of jobs waiting in the queue.
+    pub queue_depth: usize,
+    /// Capacity of the job queue.
+    pub queue_capacity: usize,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// PoolError
+////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, thiserror::Error)]
+pub enum PoolError {
+    /// The job queue is full.
+    #[error("fiber pool queue is full")]
+    Full,
+    /// The pool is shutting down or the current fiber was cancelled.
+    #[error("fiber pool is shut down")]
+    ShutDown,
+    /// The job was discarded before it could be executed.
+    #[error("fiber pool job was cancelled")]
+    Cancelled,
+    /// The job panicked. The worker fiber keeps serving other jobs.
+    #[error("fiber pool job panicked: {0}")]
+    Panicked(JoinError),
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::cell::RefCell;
+
+    #[crate::test(tarantool = "crate")]
+    fn submit_and_join() {
+        if !crate::ffi::has_fiber_channel() {
+            return;
+        }
+
+        let pool = Pool::builder()
+            .name("test_pool")
+            .workers(2)
+            .build()
+            .unwrap();
+        assert_eq!(pool.worker_count(), 2);
+
+        let tasks: Vec<_> = (0..10)
+            .map(|i| pool.submit(move || i * i).unwrap())
+            .collect();
+        let results: Vec<_> = tasks.into_iter().map(|t| t.join().unwrap()).collect();
+        assert_eq!(results, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81]);
+
+        let task = pool.submit(fiber::name).unwrap();
+        let name = fiber::block_on(task).unwrap();
+        assert!(name.starts_with("test_pool/"), "{}", name);
+
+        pool.shutdown();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn metrics_and_queue_limit() {
+        if !crate::ffi::has_fiber_channel() {
+            return;
+        }
+
+        let pool = Pool::builder().workers(1).queue_len(1).build().unwrap();
+        let cond = Rc::new(fiber::Cond::new());
+
+        let c = cond.clone();
+        let first = pool.submit(move || c.wait()).unwrap();
+        // Let the worker pick up the job.
+        fiber::reschedule();
+        assert_eq!(pool.busy_workers(), 1);
+
+        let second = pool.try_submit(|| 2).unwrap();
+        assert_eq!(pool.queue_depth(), 1);
+        assert!(matches!(pool.try_submit(|| 3), Err(PoolError::Full)));
+        #[rustfmt::skip]
+        assert_eq!(pool.stats(), PoolStats { workers: 1, busy_workers: 1, queue_depth: 1, queue_capacity: 1 });
+
+        cond.signal();
+        assert!(first.join().unwrap());
+        assert_eq!(second.join().unwrap(), 2);
+        assert_eq!(pool.busy_workers(), 0);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn shutdown_drains_or_cancels() {
+        if !crate::ffi::has_fiber_channel() {
+            return;
+        }
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let pool = Pool::builder().workers(1).build().unwrap();
+        let tasks: Vec<_> = (0..3)
+            .map(|i| {
+                let log = log.clone();
+                pool.submit(move || log.borrow_mut().push(i)).unwrap()
+            })
+            .collect();
+        pool.shutdown();
+        assert_eq!(*log.borrow(), [0, 1, 2]);
+        for task in tasks {
+            task.join().unwrap();
+        }
+
+        let pool = Pool::builder().workers(1).build().unwrap();
+        let cond = Rc::new(fiber::Cond::new());
+        let c = cond.clone();
+        let running = pool.submit(move || c.wait()).unwrap();
+        fiber::reschedule();
+        let queued = pool.submit(|| 1).unwrap();
+
+        cond.signal();
+        pool.shutdown_now();
+        assert!(running.join().unwrap());
+        assert!(matches!(queued.join(), Err(PoolError::Cancelled)));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn panicking_job() {
+        if !crate::ffi::has_fiber_channel() {
+            return;
+        }
+
+        let pool = Pool::builder().workers(2).build().unwrap();
+        let task: TaskHandle<()> = pool.submit(|| panic!("oops")).unwrap();
+        match task.join() {
+            Err(PoolError::Panicked(e)) => assert_eq!(e.message(), Some("oops")),
+            res => panic!("unexpected result: {:?}", res),
+        }
+        assert_eq!(pool.busy_workers(), 0);
+
+        // Both workers are still alive and serve jobs.
+        let ch = fiber::Channel::new(1);
+        let rx = ch.clone();
+        let blocked = pool.submit(move || rx.recv()).unwrap();
+        assert_eq!(pool.submit(|| 42).unwrap().join().unwrap(), 42);
+        assert_eq!(pool.busy_workers(), 1);
+        ch.send(13).unwrap();
+        assert_eq!(blocked.join().unwrap(), Some(13));
+        assert_eq!(pool.worker_count(), 2);
+    }
+}
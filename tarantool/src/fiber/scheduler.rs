@@ -0,0 +1,735 @@
+//! Periodic background tasks.
+//!
+//! A [`Scheduler`] runs tasks in dedicated fibers according to a
+//! [`Schedule`]: at a fixed interval (optionally with a random jitter) or
+//! according to a [cron expression](cron).
+//!
+//! Each task is executed sequentially in its own fiber, so the runs of the
+//! same task never overlap. If a run takes longer than the period, the next
+//! tick is chosen according to the task's [`MissedTickBehavior`].
+//!
+//! All the tasks of a scheduler are stopped and joined when the scheduler is
+//! [stopped](Scheduler::stop), dropped or when tarantool is shutting down
+//! (see [`trigger::on_shutdown`]).
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber::scheduler::{MissedTickBehavior, Scheduler};
+//! use std::time::Duration;
+//!
+//! let scheduler = Scheduler::new();
+//!
+//! scheduler
+//!     .every(Duration::from_secs(10))
+//!     .jitter(Duration::from_secs(1))
+//!     .name("gc")
+//!     .start(|| {
+//!         // collect some garbage
+//!     })
+//!     .unwrap();
+//!
+//! scheduler
+//!     .cron("0 3 * * *")
+//!     .unwrap()
+//!     .name("nightly report")
+//!     .missed_tick_behavior(MissedTickBehavior::Skip)
+//!     .start(|| {
+//!         // build a report
+//!     })
+//!     .unwrap();
+//! ```
+//!
+//! See also `fiber::r#async::interval` for an async version of a periodic
+//! timer.
+//!
+//! [`trigger::on_shutdown`]: crate::trigger::on_shutdown
+
+use super::{Builder, Cond, FiberId};
+use crate::time::Instant;
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use std::rc::{Rc, Weak};
+use std::time::Duration;
+
+pub mod cron;
+pub use cron::{Cron, CronError};
+
+////////////////////////////////////////////////////////////////////////////////
+// Schedule
+////////////////////////////////////////////////////////////////////////////////
+
+/// Defines when a periodic task should be run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schedule {
+    /// Run every `period`. Each tick is delayed by a random duration in
+    /// range `[0, jitter)`. The jitter doesn't accumulate, i.e. the ticks are
+    /// still aligned to `period` on average.
+    Interval { period: Duration, jitter: Duration },
+    /// Run according to the cron expression (in UTC).
+    Cron(Cron),
+}
+
+impl Schedule {
+    /// Run every `period` without jitter.
+    #[inline(always)]
+    pub fn every(period: Duration) -> Self {
+        Self::Interval {
+            period,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    /// Run according to the cron expression `expr`.
+    #[inline(always)]
+    pub fn cron(expr: &str) -> Result<Self, CronError> {
+        Ok(Self::Cron(Cron::parse(expr)?))
+    }
+
+    /// Returns the time of the first tick if the schedule starts at `now`.
+    fn first_tick(&self, now: Instant) -> Option<Instant> {
+        match self {
+            Self::Interval { period, .. } => Some(now + *period),
+            Self::Cron(cron) => cron_next_after(cron, now, now),
+        }
+    }
+
+    /// Returns the time of the tick which follows the `previous` one given
+    /// the current time is `now`.
+    fn next_tick(
+        &self,
+        previous: Instant,
+        now: Instant,
+        behavior: MissedTickBehavior,
+    ) -> Option<Instant> {
+        match self {
+            Self::Interval { period, .. } => Some(behavior.next_tick(previous, now, *period)),
+            Self::Cron(cron) => {
+                let next = cron_next_after(cron, previous, now)?;
+                if next > now || behavior == MissedTickBehavior::Burst {
+                    Some(next)
+                } else {
+                    cron_next_after(cron, now, now)
+                }
+            }
+        }
+    }
+
+    /// Returns a random delay which should be added to a tick.
+    fn jitter(&self) -> Duration {
+        match self {
+            Self::Interval { jitter, .. } if !jitter.is_zero() => jitter.mul_f64(random_f64()),
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+impl From<Cron> for Schedule {
+    #[inline(always)]
+    fn from(cron: Cron) -> Self {
+        Self::Cron(cron)
+    }
+}
+
+/// Converts the monotonic instant `after` to wall clock time, finds the next
+/// matching cron time and converts it back to a monotonic instant.
+fn cron_next_after(cron: &Cron, after: Instant, now: Instant) -> Option<Instant> {
+    let wall_now = time::OffsetDateTime::now_utc();
+    let wall_after = if after <= now {
+        wall_now - now.duration_since(after)
+    } else {
+        wall_now + after.duration_since(now)
+    };
+    let wall_next = cron.next_after(wall_after)?;
+    let next = match Duration::try_from(wall_next - wall_now) {
+        Ok(ahead) => now.saturating_add(ahead),
+        Err(_) => now.saturating_sub(Duration::try_from(wall_now - wall_next).unwrap_or_default()),
+    };
+    Some(next)
+}
+
+/// A cheap non cryptographic pseudo random number in range `[0, 1)`.
+fn random_f64() -> f64 {
+    thread_local! {
+        static STATE: Cell<u64> = const { Cell::new(0) };
+    }
+    STATE.with(|state| {
+        let mut x = state.get();
+        if x == 0 {
+            x = crate::clock::time64() | 1;
+        }
+        // xorshift64
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+        (x >> 11) as f64 / (1_u64 << 53) as f64
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// MissedTickBehavior
+////////////////////////////////////////////////////////////////////////////////
+
+/// Defines what happens if a tick is missed, e.g. because the previous run
+/// of the task took longer than the period.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MissedTickBehavior {
+    /// Run the missed ticks as soon as possible one after another until the
+    /// schedule catches up.
+    Burst,
+    /// Run the missed tick as soon as possible and shift all the following
+    /// ticks so that they are a whole period apart from it.
+    Delay,
+    /// Skip the missed ticks and wait for the next tick aligned with the
+    /// original schedule.
+    #[default]
+    Skip,
+}
+
+impl MissedTickBehavior {
+    /// Returns the tick which follows the `previous` one given the current
+    /// time is `now`.
+    pub(crate) fn next_tick(self, previous: Instant, now: Instant, period: Duration) -> Instant {
+        let next = previous + period;
+        if next > now {
+            return next;
+        }
+        match self {
+            Self::Burst => next,
+            Self::Delay => now + period,
+            Self::Skip if period.is_zero() => now,
+            Self::Skip => {
+                let behind = now.duration_since(next).as_nanos();
+                let periods = behind / period.as_nanos() + 1;
+                let skipped = Duration::from_nanos((periods * period.as_nanos()) as _);
+                next + skipped
+            }
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Scheduler
+////////////////////////////////////////////////////////////////////////////////
+
+/// A set of periodic tasks. See [module level documentation](self) for
+/// details.
+///
+/// When the scheduler is dropped all of its tasks are stopped and joined, so
+/// the drop **yields**.
+pub struct Scheduler {
+    inner: Rc<Inner>,
+}
+
+#[derive(Default)]
+struct Inner {
+    tasks: RefCell<Vec<Rc<TaskState>>>,
+    on_shutdown_registered: Cell<bool>,
+}
+
+impl Inner {
+    fn stop(&self) {
+        let tasks = self.tasks.take();
+        for state in &tasks {
+            state.stop();
+        }
+        let current = super::id();
+        for state in tasks {
+            if state.fiber_id.get() == current {
+                // Can't wait for self, the task will stop when the current
+                // run returns.
+                continue;
+            }
+            state.join();
+        }
+    }
+}
+
+impl std::fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Scheduler")
+            .field("tasks", &self.task_count())
+            .finish()
+    }
+}
+
+impl Default for Scheduler {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    /// Creates a new scheduler without any tasks.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            inner: Default::default(),
+        }
+    }
+
+    /// Starts configuring a task which runs every `period`.
+    #[inline(always)]
+    pub fn every(&self, period: Duration) -> TaskBuilder<'_> {
+        self.schedule(Schedule::every(period))
+    }
+
+    /// Starts configuring a task which runs according to the cron
+    /// expression `expr`. See [`cron`] for the syntax.
+    #[inline(always)]
+    pub fn cron(&self, expr: &str) -> Result<TaskBuilder<'_>, CronError> {
+        Ok(self.schedule(Schedule::cron(expr)?))
+    }
+
+    /// Starts configuring a task which runs according to the `schedule`.
+    #[inline(always)]
+    pub fn schedule(&self, schedule: Schedule) -> TaskBuilder<'_> {
+        TaskBuilder {
+            scheduler: self,
+            schedule,
+            name: None,
+            missed_tick_behavior: Default::default(),
+            run_immediately: false,
+        }
+    }
+
+    /// Returns the number of tasks which are still running.
+    #[inline]
+    pub fn task_count(&self) -> usize {
+        // Finished tasks remove themselves from the list.
+        self.inner.tasks.borrow().len()
+    }
+
+    /// Stops all the tasks and waits for them to finish. A task which is
+    /// running at the moment is not interrupted, but no new runs are started.
+    ///
+    /// This function **yields**.
+    ///
+    /// **NOTE**: if this is called from within a task of this scheduler,
+    /// that task is stopped but not joined.
+    #[inline(always)]
+    pub fn stop(&self) {
+        self.inner.stop()
+    }
+
+    /// Adds the scheduler to the list of schedulers stopped on tarantool
+    /// shutdown. The [`trigger::on_shutdown`] trigger is only set once per
+    /// thread, because it can't be removed.
+    ///
+    /// [`trigger::on_shutdown`]: crate::trigger::on_shutdown
+    fn register_on_shutdown(&self) -> crate::Result<()> {
+        if self.inner.on_shutdown_registered.get() {
+            return Ok(());
+        }
+        let trigger_is_set = ON_SHUTDOWN.with(|list| list.borrow().is_some());
+        if !trigger_is_set {
+            crate::trigger::on_shutdown(|| {
+                let schedulers = ON_SHUTDOWN.with(|list| list.take()).unwrap_or_default();
+                for inner in schedulers.iter().filter_map(Weak::upgrade) {
+                    inner.stop();
+                }
+            })?;
+        }
+        ON_SHUTDOWN.with(|list| {
+            let mut list = list.borrow_mut();
+            let list = list.get_or_insert_with(Vec::new);
+            // Forget the schedulers which have been dropped meanwhile.
+            list.retain(|inner| inner.strong_count() > 0);
+            list.push(Rc::downgrade(&self.inner));
+        });
+        self.inner.on_shutdown_registered.set(true);
+        Ok(())
+    }
+}
+
+type SchedulerList = Vec<Weak<Inner>>;
+
+thread_local! {
+    /// Schedulers to be stopped on tarantool shutdown. Is `None` until the
+    /// shutdown trigger is set.
+    static ON_SHUTDOWN: RefCell<Option<SchedulerList>> = const { RefCell::new(None) };
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.inner.stop();
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TaskBuilder
+////////////////////////////////////////////////////////////////////////////////
+
+/// Configures a periodic task. Created by [`Scheduler::every`],
+/// [`Scheduler::cron`] or [`Scheduler::schedule`].
+#[must_use = "the task isn't started until `start` is called"]
+#[derive(Debug)]
+pub struct TaskBuilder<'a> {
+    scheduler: &'a Scheduler,
+    schedule: Schedule,
+    name: Option<String>,
+    missed_tick_behavior: MissedTickBehavior,
+    run_immediately: bool,
+}
+
+impl<'a> TaskBuilder<'a> {
+    /// Sets the name of the task's fiber.
+    ///
+    /// The name must not contain null bytes (`\0`).
+    #[inline(always)]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the random jitter added to each tick, see
+    /// [`Schedule::Interval`]. Has no effect for cron schedules.
+    #[inline(always)]
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        if let Schedule::Interval { jitter: j, .. } = &mut self.schedule {
+            *j = jitter;
+        }
+        self
+    }
+
+    /// Sets what happens if a tick is missed. Default is
+    /// [`MissedTickBehavior::Skip`].
+    #[inline(always)]
+    pub fn missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.missed_tick_behavior = behavior;
+        self
+    }
+
+    /// If `true` the task is run right after it's started instead of
+    /// waiting for the first tick. Default is `false`.
+    #[inline(always)]
+    pub fn run_immediately(mut self, run_immediately: bool) -> Self {
+        self.run_immediately = run_immediately;
+        self
+    }
+
+    /// Starts the task in a new fiber.
+    ///
+    /// The current fiber **yields** to the new fiber.
+    ///
+    /// Returns an error if
+    /// - spawning a fiber failed,
+    /// - the task name contains a nul byte,
+    /// - the [`trigger::on_shutdown`] trigger couldn't be set.
+    ///
+    /// [`trigger::on_shutdown`]: crate::trigger::on_shutdown
+    pub fn start<F>(self, f: F) -> crate::Result<ScheduledTask>
+    where
+        F: FnMut() + 'static,
+    {
+        let Self {
+            scheduler,
+            schedule,
+            name,
+            missed_tick_behavior,
+            run_immediately,
+        } = self;
+        scheduler.register_on_shutdown()?;
+
+        let state = Rc::new(TaskState::default());
+        let name = name.unwrap_or_else(|| "<rust scheduled task>".into());
+        let task = Task {
+            state: state.clone(),
+            scheduler: Rc::downgrade(&scheduler.inner),
+            schedule,
+            missed_tick_behavior,
+            run_immediately,
+        };
+        // Added before the fiber is started, because the task may finish
+        // before `start_non_joinable` returns.
+        scheduler.inner.tasks.borrow_mut().push(state.clone());
+        let res = Builder::new()
+            .name(name)
+            .func(move || task.run(f))
+            .start_non_joinable();
+        let fiber_id = match res {
+            Ok(id) => id,
+            Err(e) => {
+                let mut tasks = scheduler.inner.tasks.borrow_mut();
+                tasks.retain(|s| !Rc::ptr_eq(s, &state));
+                return Err(e);
+            }
+        };
+        state.fiber_id.set(fiber_id);
+
+        Ok(ScheduledTask { state })
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ScheduledTask
+////////////////////////////////////////////////////////////////////////////////
+
+/// A handle to a task started via [`TaskBuilder::start`].
+///
+/// Dropping the handle doesn't stop the task.
+#[derive(Debug, Clone)]
+pub struct ScheduledTask {
+    state: Rc<TaskState>,
+}
+
+impl ScheduledTask {
+    /// Requests the task to stop. A run which is in progress is not
+    /// interrupted, but no new runs are started.
+    ///
+    /// **Does NOT yield**. Use [`Scheduler::stop`] to wait for the tasks to
+    /// finish.
+    #[inline(always)]
+    pub fn stop(&self) {
+        self.state.stop()
+    }
+
+    /// Returns `true` if the task's fiber has finished.
+    #[inline(always)]
+    pub fn is_finished(&self) -> bool {
+        self.state.is_finished.get()
+    }
+
+    /// Returns the number of completed runs of the task.
+    #[inline(always)]
+    pub fn run_count(&self) -> u64 {
+        self.state.run_count.get()
+    }
+}
+
+#[derive(Debug, Default)]
+struct TaskState {
+    /// Signalled when the task is stopped.
+    cond: Cond,
+    /// Signalled when the task's fiber is finished.
+    finished: Cond,
+    is_stopped: Cell<bool>,
+    is_finished: Cell<bool>,
+    run_count: Cell<u64>,
+    fiber_id: Cell<FiberId>,
+}
+
+impl TaskState {
+    fn stop(&self) {
+        self.is_stopped.set(true);
+        self.cond.signal();
+    }
+
+    /// Waits until the task's fiber is finished.
+    fn join(&self) {
+        while !self.is_finished.get() {
+            self.finished.wait();
+        }
+    }
+
+    /// Waits until `deadline`. Returns `false` if the task was stopped
+    /// meanwhile.
+    fn sleep_until(&self, deadline: Instant) -> bool {
+        loop {
+            if self.is_stopped.get() || super::is_cancelled() {
+                return false;
+            }
+            let now = super::clock();
+            if now >= deadline {
+                // Still yield, so that a task which is behind the schedule
+                // doesn't starve the other fibers.
+                super::reschedule();
+                return !self.is_stopped.get();
+            }
+            self.cond.wait_timeout(deadline.duration_since(now));
+        }
+    }
+}
+
+struct Task {
+    state: Rc<TaskState>,
+    scheduler: Weak<Inner>,
+    schedule: Schedule,
+    missed_tick_behavior: MissedTickBehavior,
+    run_immediately: bool,
+}
+
+impl Task {
+    fn run(self, mut f: impl FnMut()) {
+        /// Marks the task as finished and removes it from the scheduler even
+        /// if `f` panics, otherwise [`TaskState::join`] would wait forever.
+        struct FinishGuard(Rc<TaskState>, Weak<Inner>);
+
+        impl Drop for FinishGuard {
+            fn drop(&mut self) {
+                if let Some(inner) = self.1.upgrade() {
+                    inner.tasks.borrow_mut().retain(|s| !Rc::ptr_eq(s, &self.0));
+                }
+                self.0.is_finished.set(true);
+                self.0.finished.broadcast();
+            }
+        }
+
+        let Self {
+            state,
+            scheduler,
+            schedule,
+            missed_tick_behavior,
+            run_immediately,
+        } = self;
+        let _guard = FinishGuard(state.clone(), scheduler);
+        let start = super::clock();
+        let mut tick = if run_immediately {
+            Some(start)
+        } else {
+            schedule.first_tick(start)
+        };
+        while let Some(t) = tick {
+            if !state.sleep_until(t + schedule.jitter()) {
+                break;
+            }
+            f();
+            state.run_count.set(state.run_count.get() + 1);
+            tick = schedule.next_tick(t, super::clock(), missed_tick_behavior);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+
+    #[crate::test(tarantool = "crate")]
+    fn missed_tick_behavior() {
+        let start = fiber::clock();
+        let period = Duration::from_secs(10);
+        let now = start + Duration::from_secs(25);
+
+        let next = |b: MissedTickBehavior| b.next_tick(start, now, period);
+        assert_eq!(next(MissedTickBehavior::Burst), start + period);
+        assert_eq!(next(MissedTickBehavior::Delay), now + period);
+        assert_eq!(
+            next(MissedTickBehavior::Skip),
+            start + Duration::from_secs(30)
+        );
+
+        // Not late.
+        let now = start + Duration::from_secs(1);
+        for b in [
+            MissedTickBehavior::Burst,
+            MissedTickBehavior::Delay,
+            MissedTickBehavior::Skip,
+        ] {
+            assert_eq!(b.next_tick(start, now, period), start + period);
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn interval_task() {
+        let scheduler = Scheduler::new();
+        let counter = Rc::new(Cell::new(0));
+        let c = counter.clone();
+        let task = scheduler
+            .every(Duration::from_millis(10))
+            .name("test_interval_task")
+            .run_immediately(true)
+            .start(move || c.set(c.get() + 1))
+            .unwrap();
+        assert_eq!(scheduler.task_count(), 1);
+
+        fiber::sleep(Duration::from_millis(55));
+        assert!(counter.get() >= 3);
+        assert_eq!(task.run_count(), counter.get());
+
+        scheduler.stop();
+        assert!(task.is_finished());
+        assert_eq!(scheduler.task_count(), 0);
+        let count = counter.get();
+        fiber::sleep(Duration::from_millis(20));
+        assert_eq!(counter.get(), count);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn no_overlap() {
+        let scheduler = Scheduler::new();
+        let running = Rc::new(Cell::new(false));
+        let overlapped = Rc::new(Cell::new(false));
+        let (r, o) = (running.clone(), overlapped.clone());
+        let task = scheduler
+            .every(Duration::from_millis(1))
+            .missed_tick_behavior(MissedTickBehavior::Burst)
+            .start(move || {
+                o.set(o.get() || r.replace(true));
+                fiber::sleep(Duration::from_millis(5));
+                r.set(false);
+            })
+            .unwrap();
+
+        fiber::sleep(Duration::from_millis(30));
+        task.stop();
+        drop(scheduler);
+        assert!(task.is_finished());
+        assert!(task.run_count() > 0);
+        assert!(!overlapped.get());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn cron_task_is_stopped() {
+        let scheduler = Scheduler::new();
+        let task = scheduler
+            .cron("@yearly")
+            .unwrap()
+            .start(|| unreachable!())
+            .unwrap();
+        assert!(scheduler.cron("not a cron").is_err());
+
+        fiber::reschedule();
+        assert!(!task.is_finished());
+        scheduler.stop();
+        assert!(task.is_finished());
+        assert_eq!(task.run_count(), 0);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn panicking_task() {
+        let scheduler = Scheduler::new();
+        let task = scheduler
+            .every(Duration::from_millis(1))
+            .run_immediately(true)
+            .start(|| panic!("oops"))
+            .unwrap();
+
+        fiber::sleep(Duration::from_millis(10));
+        assert!(task.is_finished());
+        assert_eq!(task.run_count(), 0);
+        assert_eq!(scheduler.task_count(), 0);
+        // Doesn't hang.
+        scheduler.stop();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn finished_tasks_are_forgotten() {
+        let scheduler = Scheduler::new();
+        let start = || scheduler.every(Duration::from_secs(100)).start(|| {});
+        let task = start().unwrap();
+        let _other = start().unwrap();
+        assert_eq!(scheduler.inner.tasks.borrow().len(), 2);
+
+        task.stop();
+        fiber::reschedule();
+        assert!(task.is_finished());
+        assert_eq!(scheduler.inner.tasks.borrow().len(), 1);
+
+        // The shutdown trigger is shared by all the schedulers.
+        let count = || ON_SHUTDOWN.with(|l| l.borrow().as_ref().map(Vec::len));
+        let before = count().unwrap();
+        drop(scheduler);
+        let scheduler = Scheduler::new();
+        scheduler
+            .every(Duration::from_secs(100))
+            .start(|| {})
+            .unwrap();
+        assert_eq!(count().unwrap(), before);
+    }
+}
@@ -0,0 +1,417 @@
+//! Cron expressions.
+//!
+//! A cron expression consists of 5 whitespace separated fields:
+//! ```text
+//! ┌───────────── minute (0-59)
+//! │ ┌─────────── hour (0-23)
+//! │ │ ┌───────── day of month (1-31)
+//! │ │ │ ┌─────── month (1-12 or jan-dec)
+//! │ │ │ │ ┌───── day of week (0-7 or sun-sat, both 0 and 7 mean sunday)
+//! │ │ │ │ │
+//! * * * * *
+//! ```
+//!
+//! Each field is a comma separated list of items, where an item is either
+//! `*`, a single value `a` or a range `a-b`, optionally followed by a step
+//! `/n`. For example `*/15` means every 15th value starting from the
+//! minimum and `10-20/5` means `10,15,20`. A value with a step (`a/n`) means
+//! every `n`th value starting from `a`.
+//!
+//! If both day of month and day of week are restricted (i.e. not `*`), the
+//! expression matches if either of the fields matches, as in the classic
+//! cron.
+//!
+//! The following aliases are also supported: `@yearly` (`@annually`),
+//! `@monthly`, `@weekly`, `@daily` (`@midnight`) and `@hourly`.
+//!
+//! All the times are in UTC.
+
+use std::fmt;
+use std::str::FromStr;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+/// Maximum number of days [`Cron::next_after`] looks ahead before giving up.
+/// Enough to find the next february 29th.
+const MAX_DAYS_AHEAD: usize = 366 * 8;
+
+/// A parsed cron expression. See [module level documentation](self) for the
+/// syntax.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Cron {
+    expr: String,
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+/// Error returned when parsing an invalid [`Cron`] expression.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid cron expression '{expr}': {reason}")]
+pub struct CronError {
+    expr: String,
+    reason: String,
+}
+
+impl CronError {
+    fn new(expr: &str, reason: impl Into<String>) -> Self {
+        Self {
+            expr: expr.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl From<CronError> for crate::error::Error {
+    #[inline(always)]
+    fn from(e: CronError) -> Self {
+        Self::other(e)
+    }
+}
+
+const MONTH_NAMES: &[&str] = &[
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const DAY_NAMES: &[&str] = &["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+struct FieldSpec {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+}
+
+const MINUTE: FieldSpec = FieldSpec {
+    name: "minute",
+    min: 0,
+    max: 59,
+    names: &[],
+};
+const HOUR: FieldSpec = FieldSpec {
+    name: "hour",
+    min: 0,
+    max: 23,
+    names: &[],
+};
+const DAY_OF_MONTH: FieldSpec = FieldSpec {
+    name: "day of month",
+    min: 1,
+    max: 31,
+    names: &[],
+};
+const MONTH: FieldSpec = FieldSpec {
+    name: "month",
+    min: 1,
+    max: 12,
+    names: MONTH_NAMES,
+};
+const DAY_OF_WEEK: FieldSpec = FieldSpec {
+    name: "day of week",
+    min: 0,
+    max: 7,
+    names: DAY_NAMES,
+};
+
+impl FieldSpec {
+    fn parse_value(&self, s: &str) -> Result<u32, String> {
+        let lower = s.to_ascii_lowercase();
+        if let Some(i) = self.names.iter().position(|&n| n == lower) {
+            return Ok(i as u32 + self.min);
+        }
+        let v: u32 = s
+            .parse()
+            .map_err(|_| format!("invalid {} value '{}'", self.name, s))?;
+        if v < self.min || v > self.max {
+            return Err(format!(
+                "{} value {} is out of range {}-{}",
+                self.name, v, self.min, self.max
+            ));
+        }
+        Ok(v)
+    }
+
+    /// Returns a bitmask of the matching values and `true` if the field is
+    /// `*`.
+    fn parse(&self, field: &str) -> Result<(u64, bool), String> {
+        if field == "*" || field == "?" {
+            return Ok((self.range_mask(self.min, self.max, 1), true));
+        }
+
+        let mut mask = 0;
+        for item in field.split(',') {
+            let (range, step) = match item.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .map_err(|_| format!("invalid {} step '{}'", self.name, step))?;
+                    if step == 0 {
+                        return Err(format!("{} step must not be 0", self.name));
+                    }
+                    (range, Some(step))
+                }
+                None => (item, None),
+            };
+
+            let (from, to) = if range == "*" {
+                (self.min, self.max)
+            } else if let Some((from, to)) = range.split_once('-') {
+                let (from, to) = (self.parse_value(from)?, self.parse_value(to)?);
+                if from > to {
+                    return Err(format!("invalid {} range '{}'", self.name, range));
+                }
+                (from, to)
+            } else {
+                let v = self.parse_value(range)?;
+                if step.is_some() {
+                    (v, self.max)
+                } else {
+                    (v, v)
+                }
+            };
+
+            mask |= self.range_mask(from, to, step.unwrap_or(1));
+        }
+        Ok((mask, false))
+    }
+
+    fn range_mask(&self, from: u32, to: u32, step: u32) -> u64 {
+        (from..=to)
+            .step_by(step as _)
+            .fold(0, |mask, v| mask | (1_u64 << v))
+    }
+}
+
+impl Cron {
+    /// Parses a cron expression. See [module level documentation](self) for
+    /// the syntax.
+    pub fn parse(expr: &str) -> Result<Self, CronError> {
+        let normalized = match expr.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+
+        let fields: Vec<_> = normalized.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = match fields[..] {
+            [a, b, c, d, e] => [a, b, c, d, e],
+            _ => {
+                return Err(CronError::new(
+                    expr,
+                    format!("expected 5 fields, got {}", fields.len()),
+                ))
+            }
+        };
+
+        let err = |reason| CronError::new(expr, reason);
+        let (minutes, _) = MINUTE.parse(minute).map_err(err)?;
+        let (hours, _) = HOUR.parse(hour).map_err(err)?;
+        let (days_of_month, any_day_of_month) = DAY_OF_MONTH.parse(dom).map_err(err)?;
+        let (months, _) = MONTH.parse(month).map_err(err)?;
+        let (mut days_of_week, any_day_of_week) = DAY_OF_WEEK.parse(dow).map_err(err)?;
+        // Both 0 and 7 mean sunday.
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+
+        Ok(Self {
+            expr: expr.into(),
+            minutes,
+            hours: hours as _,
+            days_of_month: days_of_month as _,
+            months: months as _,
+            days_of_week: days_of_week as _,
+            any_day_of_month,
+            any_day_of_week,
+        })
+    }
+
+    /// Returns the original expression.
+    #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+
+    /// Returns the earliest time strictly after `after` matching the
+    /// expression, or `None` if there's no such time (e.g. `0 0 30 2 *`).
+    ///
+    /// `after` is converted to UTC, the result is always in UTC.
+    pub fn next_after(&self, after: OffsetDateTime) -> Option<OffsetDateTime> {
+        let after = after.to_offset(time::UtcOffset::UTC);
+        // Start from the beginning of the next minute.
+        let mut date = after.date();
+        let mut hour = after.hour() as u32;
+        let mut minute = after.minute() as u32 + 1;
+        if minute == 60 {
+            minute = 0;
+            hour += 1;
+        }
+        if hour == 24 {
+            hour = 0;
+            date = date.next_day()?;
+        }
+
+        for _ in 0..MAX_DAYS_AHEAD {
+            if !self.matches_month(date.month()) {
+                date = first_day_of_next_month(date)?;
+                hour = 0;
+                minute = 0;
+                continue;
+            }
+
+            if self.matches_day(date) {
+                if let Some((h, m)) = self.next_time_of_day(hour, minute) {
+                    let time = Time::from_hms(h as _, m as _, 0).ok()?;
+                    return Some(PrimitiveDateTime::new(date, time).assume_utc());
+                }
+            }
+
+            date = date.next_day()?;
+            hour = 0;
+            minute = 0;
+        }
+
+        None
+    }
+
+    #[inline(always)]
+    fn matches_month(&self, month: Month) -> bool {
+        self.months & (1 << month as u8) != 0
+    }
+
+    fn matches_day(&self, date: Date) -> bool {
+        let dom = self.days_of_month & (1 << date.day()) != 0;
+        let dow = self.days_of_week & (1 << date.weekday().number_days_from_sunday()) != 0;
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => dom || dow,
+            _ => dom && dow,
+        }
+    }
+
+    /// Returns the earliest matching time of day not earlier than
+    /// `hour:minute`.
+    fn next_time_of_day(&self, hour: u32, minute: u32) -> Option<(u32, u32)> {
+        for h in hour..24 {
+            if self.hours & (1 << h) == 0 {
+                continue;
+            }
+            let from = if h == hour { minute } else { 0 };
+            if let Some(m) = (from..60).find(|&m| self.minutes & (1 << m) != 0) {
+                return Some((h, m));
+            }
+        }
+        None
+    }
+}
+
+fn first_day_of_next_month(date: Date) -> Option<Date> {
+    let (year, month) = match date.month() {
+        Month::December => (date.year() + 1, Month::January),
+        month => (date.year(), month.next()),
+    };
+    Date::from_calendar_date(year, month, 1).ok()
+}
+
+impl FromStr for Cron {
+    type Err = CronError;
+
+    #[inline(always)]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for Cron {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.expr)
+    }
+}
+
+impl fmt::Debug for Cron {
+    #[inline(always)]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Cron").field(&self.expr).finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: Month, day: u8, hour: u8, minute: u8) -> OffsetDateTime {
+        Date::from_calendar_date(year, month, day)
+            .unwrap()
+            .with_hms(hour, minute, 0)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn parse_errors() {
+        assert!(Cron::parse("* * * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("* 24 * * *").is_err());
+        assert!(Cron::parse("* * 0 * *").is_err());
+        assert!(Cron::parse("* * * 13 * ").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("5-1 * * * *").is_err());
+        assert!(Cron::parse("* * * foo *").is_err());
+        assert_eq!(
+            Cron::parse("x * * * *").unwrap_err().to_string(),
+            "invalid cron expression 'x * * * *': invalid minute value 'x'"
+        );
+        assert_eq!(Cron::parse("@daily").unwrap().as_str(), "@daily");
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn next_after() {
+        let every_15 = Cron::parse("*/15 * * * *").unwrap();
+        assert_eq!(
+            every_15.next_after(at(2023, Month::May, 1, 10, 0)),
+            Some(at(2023, Month::May, 1, 10, 15))
+        );
+        assert_eq!(
+            every_15.next_after(at(2023, Month::December, 31, 23, 50)),
+            Some(at(2024, Month::January, 1, 0, 0))
+        );
+
+        let weekdays = Cron::parse("30 9 * * mon-fri").unwrap();
+        // 2023-05-06 is a saturday.
+        assert_eq!(
+            weekdays.next_after(at(2023, Month::May, 6, 12, 0)),
+            Some(at(2023, Month::May, 8, 9, 30))
+        );
+
+        let sunday = Cron::parse("0 0 * * 7").unwrap();
+        assert_eq!(
+            sunday.next_after(at(2023, Month::May, 1, 0, 0)),
+            Some(at(2023, Month::May, 7, 0, 0))
+        );
+
+        // Either day of month or day of week matches.
+        let either = Cron::parse("0 0 13 * fri").unwrap();
+        assert_eq!(
+            either.next_after(at(2023, Month::May, 1, 0, 0)),
+            Some(at(2023, Month::May, 5, 0, 0))
+        );
+
+        let leap = Cron::parse("0 12 29 feb *").unwrap();
+        assert_eq!(
+            leap.next_after(at(2023, Month::March, 1, 0, 0)),
+            Some(at(2024, Month::February, 29, 12, 0))
+        );
+
+        let never = Cron::parse("0 0 30 2 *").unwrap();
+        assert_eq!(never.next_after(at(2023, Month::May, 1, 0, 0)), None);
+    }
+}
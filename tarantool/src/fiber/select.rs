@@ -0,0 +1,617 @@
+//! Waiting on multiple fiber primitives at once.
+//!
+//! [`Select`] allows a fiber to block until one of several operations can
+//! proceed: receiving from or sending to a [`Channel`], a [`Cond`] being
+//! signalled or a timeout expiring. The [`select!`] macro provides a more
+//! convenient syntax on top of it.
+//!
+//! The waiting is implemented without any helper fibers: the selecting fiber
+//! registers itself in each of the sources and is woken up whenever any of
+//! them changes its state (a message is sent or received, a channel is
+//! closed, a cond is signalled).
+//!
+//! If several operations are ready at the same time, the one which is
+//! completed is chosen in a round robin manner, so that none of the sources
+//! can starve the others.
+//!
+//! **NOTE**: a send or receive operation on a channel is considered ready
+//! only if it can be completed without blocking. This means that two fibers
+//! can't exchange messages over an unbuffered channel (`Channel::new(0)`) if
+//! both of them are waiting in a select. Use buffered channels or plain
+//! [`Channel::send`]/[`Channel::recv`] on one of the sides.
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::fiber::{self, Channel};
+//! use std::time::Duration;
+//!
+//! let control = Channel::<&str>::new(1);
+//! let data = Channel::<i32>::new(16);
+//!
+//! loop {
+//!     fiber::select! {
+//!         recv(control) -> cmd => match cmd {
+//!             Some("stop") | None => break,
+//!             Some(_) => continue,
+//!         },
+//!         recv(data) -> msg => {
+//!             if let Some(msg) = msg {
+//!                 println!("got {}", msg);
+//!             }
+//!         },
+//!         timeout(Duration::from_secs(1)) => println!("idle"),
+//!     }
+//! }
+//! ```
+//!
+//! [`select!`]: crate::fiber::select!
+
+use super::{Channel, Cond, TryRecvError, TrySendError};
+use crate::time::Instant;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::Duration;
+
+////////////////////////////////////////////////////////////////////////////////
+// SelectWaiters
+////////////////////////////////////////////////////////////////////////////////
+
+/// A list of fibers waiting in a [`Select`] on some source (e.g. a
+/// [`Channel`] or a [`Cond`]).
+#[derive(Default)]
+pub(crate) struct SelectWaiters(RefCell<Vec<Rc<Registration>>>);
+
+impl std::fmt::Debug for SelectWaiters {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_tuple("SelectWaiters")
+            .field(&self.0.borrow().len())
+            .finish()
+    }
+}
+
+impl SelectWaiters {
+    /// Wakes up all the fibers waiting on this source in a [`Select`].
+    ///
+    /// **Does NOT yield**.
+    #[inline]
+    pub(crate) fn notify(&self) {
+        for registration in self.0.borrow().iter() {
+            registration.notified.set(true);
+            registration.waker.signal();
+        }
+    }
+
+    #[inline(always)]
+    fn register(&self, registration: Rc<Registration>) {
+        self.0.borrow_mut().push(registration);
+    }
+
+    #[inline(always)]
+    fn unregister(&self, registration: &Rc<Registration>) {
+        self.0.borrow_mut().retain(|r| !Rc::ptr_eq(r, registration));
+    }
+}
+
+struct Registration {
+    waker: Rc<Cond>,
+    notified: Cell<bool>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Select
+////////////////////////////////////////////////////////////////////////////////
+
+/// A set of operations to wait on. Each operation (arm) has a handler, which
+/// is called with the result of the operation when it completes. The result
+/// of the handler is returned from [`Select::wait`].
+///
+/// See [module level documentation](self) for details.
+#[must_use = "select does nothing unless `wait` is called"]
+pub struct Select<'a, R> {
+    arms: Vec<Arm<'a, R>>,
+    deadline: Option<(Instant, Handler<'a, R>)>,
+    default: Option<Handler<'a, R>>,
+}
+
+type Handler<'a, R> = Box<dyn FnOnce() -> R + 'a>;
+
+struct Arm<'a, R> {
+    waiters: &'a SelectWaiters,
+    try_complete: Box<dyn FnMut(Attempt) -> Option<R> + 'a>,
+}
+
+#[derive(Clone, Copy)]
+enum Attempt {
+    /// Try completing the operation without blocking. `notified` is `true`
+    /// if the source has been notified since the select started waiting.
+    Poll { notified: bool },
+    /// The current fiber was cancelled, the operation must be completed with
+    /// a failure.
+    Cancelled,
+}
+
+impl<R> std::fmt::Debug for Select<'_, R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Select")
+            .field("arms", &self.arms.len())
+            .field("deadline", &self.deadline.as_ref().map(|(d, _)| d))
+            .field("has_default", &self.default.is_some())
+            .finish()
+    }
+}
+
+impl<R> Default for Select<'_, R> {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, R> Select<'a, R> {
+    /// Creates an empty select.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            arms: Vec::new(),
+            deadline: None,
+            default: None,
+        }
+    }
+
+    /// Adds an arm which receives a message from the `channel`.
+    ///
+    /// `f` is called with `Some(message)` or with `None` if the channel was
+    /// closed or the current fiber was cancelled (same as [`Channel::recv`]).
+    pub fn recv<T>(mut self, channel: &'a Channel<T>, f: impl FnOnce(Option<T>) -> R + 'a) -> Self {
+        let mut f = Some(f);
+        let mut complete = move |v| (f.take().expect("arm completed twice"))(v);
+        self.arms.push(Arm {
+            waiters: channel.select_waiters(),
+            try_complete: Box::new(move |attempt| match attempt {
+                Attempt::Cancelled => Some(complete(None)),
+                Attempt::Poll { .. } => match channel.try_recv() {
+                    Ok(v) => Some(complete(Some(v))),
+                    Err(TryRecvError::Disconnected) => Some(complete(None)),
+                    Err(TryRecvError::Empty) => None,
+                },
+            }),
+        });
+        self
+    }
+
+    /// Adds an arm which sends the `value` to the `channel`.
+    ///
+    /// `f` is called with `Ok(())` if the value was sent or with
+    /// `Err(value)` if the channel was closed or the current fiber was
+    /// cancelled (same as [`Channel::send`]). If another arm completes
+    /// first, the value is dropped.
+    pub fn send<T>(
+        mut self,
+        channel: &'a Channel<T>,
+        value: T,
+        f: impl FnOnce(Result<(), T>) -> R + 'a,
+    ) -> Self
+    where
+        T: 'static,
+    {
+        let mut f = Some(f);
+        let mut value = Some(value);
+        let mut complete = move |v| (f.take().expect("arm completed twice"))(v);
+        self.arms.push(Arm {
+            waiters: channel.select_waiters(),
+            try_complete: Box::new(move |attempt| {
+                let v = value.take().expect("arm completed twice");
+                match attempt {
+                    Attempt::Cancelled => Some(complete(Err(v))),
+                    Attempt::Poll { .. } => match channel.try_send(v) {
+                        Ok(()) => Some(complete(Ok(()))),
+                        Err(TrySendError::Disconnected(v)) => Some(complete(Err(v))),
+                        Err(TrySendError::Full(v)) => {
+                            value = Some(v);
+                            None
+                        }
+                    },
+                }
+            }),
+        });
+        self
+    }
+
+    /// Adds an arm which waits for the `cond` to be [signalled] or
+    /// [broadcast] while the select is waiting.
+    ///
+    /// `f` is called with `true` if the cond was signalled or with `false`
+    /// if the current fiber was cancelled (same as [`Cond::wait`]).
+    ///
+    /// **NOTE**: a signal issued before the select started waiting is not
+    /// remembered, just like with [`Cond::wait`]. Also both [`Cond::signal`]
+    /// and [`Cond::broadcast`] wake up all the selects waiting on the cond.
+    ///
+    /// [signalled]: Cond::signal
+    /// [broadcast]: Cond::broadcast
+    pub fn cond(mut self, cond: &'a Cond, f: impl FnOnce(bool) -> R + 'a) -> Self {
+        let mut f = Some(f);
+        let mut complete = move |v| (f.take().expect("arm completed twice"))(v);
+        self.arms.push(Arm {
+            waiters: cond.select_waiters(),
+            try_complete: Box::new(move |attempt| match attempt {
+                Attempt::Cancelled => Some(complete(false)),
+                Attempt::Poll { notified: true } => Some(complete(true)),
+                Attempt::Poll { notified: false } => None,
+            }),
+        });
+        self
+    }
+
+    /// Adds an arm which completes if none of the other arms complete within
+    /// `timeout`.
+    ///
+    /// If the select already has a timeout or a deadline, the earliest one is
+    /// used.
+    #[inline]
+    pub fn timeout(self, timeout: Duration, f: impl FnOnce() -> R + 'a) -> Self {
+        self.deadline(super::clock().saturating_add(timeout), f)
+    }
+
+    /// Adds an arm which completes if none of the other arms complete before
+    /// `deadline`.
+    ///
+    /// If the select already has a timeout or a deadline, the earliest one is
+    /// used.
+    pub fn deadline(mut self, deadline: Instant, f: impl FnOnce() -> R + 'a) -> Self {
+        match &self.deadline {
+            Some((current, _)) if *current <= deadline => {}
+            _ => self.deadline = Some((deadline, Box::new(f))),
+        }
+        self
+    }
+
+    /// Adds an arm which completes immediately if none of the other arms are
+    /// ready. A select with a default arm never **yields**.
+    #[inline]
+    pub fn default(mut self, f: impl FnOnce() -> R + 'a) -> Self {
+        self.default = Some(Box::new(f));
+        self
+    }
+
+    /// Waits until one of the arms completes and returns the result of its
+    /// handler. The handlers of the other arms are not called.
+    ///
+    /// If the current fiber is cancelled while waiting, one of the arms is
+    /// completed with a failure (see the arm documentation), or the timeout
+    /// arm if there are no others.
+    ///
+    /// This function may **yield**.
+    ///
+    /// # Panicking
+    /// Panics if the select has no arms.
+    pub fn wait(mut self) -> R {
+        assert!(
+            !self.arms.is_empty() || self.deadline.is_some() || self.default.is_some(),
+            "select with no arms would wait forever"
+        );
+        let offset = next_offset(self.arms.len());
+
+        if let Some(res) = self.try_arms(offset, |_| false) {
+            return res;
+        }
+        if let Some(f) = self.default.take() {
+            return f();
+        }
+
+        let waker = Rc::new(Cond::new());
+        let registrations: Vec<_> = self
+            .arms
+            .iter()
+            .map(|arm| {
+                let registration = Rc::new(Registration {
+                    waker: waker.clone(),
+                    notified: Cell::new(false),
+                });
+                arm.waiters.register(registration.clone());
+                (arm.waiters, registration)
+            })
+            .collect();
+        let _guard = UnregisterGuard(&registrations);
+
+        loop {
+            if super::is_cancelled() {
+                return self.cancelled(offset);
+            }
+
+            if let Some(deadline) = self.deadline.as_ref().map(|(d, _)| *d) {
+                let now = super::clock();
+                if now >= deadline {
+                    let (_, f) = self.deadline.take().expect("checked above");
+                    return f();
+                }
+                waker.wait_timeout(deadline.duration_since(now));
+            } else {
+                waker.wait();
+            }
+
+            let notified = |i: usize| registrations[i].1.notified.replace(false);
+            if let Some(res) = self.try_arms(offset, notified) {
+                return res;
+            }
+        }
+    }
+
+    fn try_arms(&mut self, offset: usize, notified: impl Fn(usize) -> bool) -> Option<R> {
+        let n = self.arms.len();
+        for i in (0..n).map(|i| (i + offset) % n) {
+            let attempt = Attempt::Poll {
+                notified: notified(i),
+            };
+            if let Some(res) = (self.arms[i].try_complete)(attempt) {
+                return Some(res);
+            }
+        }
+        None
+    }
+
+    fn cancelled(mut self, offset: usize) -> R {
+        if let Some(arm) = self.arms.get_mut(offset) {
+            if let Some(res) = (arm.try_complete)(Attempt::Cancelled) {
+                return res;
+            }
+        }
+        let (_, f) = self
+            .deadline
+            .take()
+            .expect("there's at least one arm or a deadline");
+        f()
+    }
+}
+
+struct UnregisterGuard<'a, 'b>(&'b [(&'a SelectWaiters, Rc<Registration>)]);
+
+impl Drop for UnregisterGuard<'_, '_> {
+    fn drop(&mut self) {
+        for (waiters, registration) in self.0 {
+            waiters.unregister(registration);
+        }
+    }
+}
+
+/// Returns the index of the arm which should be tried first, so that the
+/// arms are tried in a round robin manner.
+fn next_offset(n: usize) -> usize {
+    thread_local! {
+        static COUNTER: Cell<usize> = const { Cell::new(0) };
+    }
+    if n == 0 {
+        return 0;
+    }
+    COUNTER.with(|counter| {
+        let c = counter.get();
+        counter.set(c.wrapping_add(1));
+        c % n
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// select!
+////////////////////////////////////////////////////////////////////////////////
+
+/// Waits on multiple fiber primitives at once. This is a more convenient
+/// syntax for [`fiber::select::Select`]. Unlike the handlers of `Select`, the
+/// bodies of the arms are not closures, so `return`, `break`, `continue` and
+/// `?` work as expected.
+///
+/// The following arms are supported:
+/// - `recv(channel) -> pattern => body`: `pattern` is matched against
+///   `Option<T>`, see [`Select::recv`],
+/// - `send(channel, value) -> pattern => body`: `pattern` is matched against
+///   `Result<(), T>`, see [`Select::send`],
+/// - `cond(cond) -> pattern => body`: `pattern` is matched against `bool`,
+///   see [`Select::cond`],
+/// - `timeout(duration) => body`, see [`Select::timeout`],
+/// - `deadline(instant) => body`, see [`Select::deadline`],
+/// - `default => body`, see [`Select::default`].
+///
+/// The arms must be separated by commas. The value of the macro is the value
+/// of the body of the completed arm.
+///
+/// ```no_run
+/// use tarantool::fiber::{self, Channel, Cond};
+/// use std::time::Duration;
+///
+/// let ch = Channel::<i32>::new(1);
+/// let cond = Cond::new();
+/// let res = fiber::select! {
+///     recv(ch) -> msg => msg,
+///     cond(cond) -> _ => None,
+///     timeout(Duration::from_millis(100)) => Some(0),
+/// };
+/// ```
+///
+/// [`fiber::select::Select`]: crate::fiber::select::Select
+/// [`Select::recv`]: crate::fiber::select::Select::recv
+/// [`Select::send`]: crate::fiber::select::Select::send
+/// [`Select::cond`]: crate::fiber::select::Select::cond
+/// [`Select::timeout`]: crate::fiber::select::Select::timeout
+/// [`Select::deadline`]: crate::fiber::select::Select::deadline
+/// [`Select::default`]: crate::fiber::select::Select::default
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __fiber_select {
+    (@parse [$($arms:tt)*]) => {
+        $crate::__fiber_select! { @expand $($arms)* }
+    };
+    (@parse [$($arms:tt)*] recv($ch:expr) -> $pat:pat => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__fiber_select! {
+            @parse [$($arms)* (__value value [recv(&$ch,)] $pat => $body)] $($($rest)*)?
+        }
+    };
+    (@parse [$($arms:tt)*] send($ch:expr, $v:expr) -> $pat:pat => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__fiber_select! {
+            @parse [$($arms)* (__value value [send(&$ch, $v,)] $pat => $body)] $($($rest)*)?
+        }
+    };
+    (@parse [$($arms:tt)*] cond($c:expr) -> $pat:pat => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__fiber_select! {
+            @parse [$($arms)* (__value value [cond(&$c,)] $pat => $body)] $($($rest)*)?
+        }
+    };
+    (@parse [$($arms:tt)*] timeout($t:expr) => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__fiber_select! {
+            @parse [$($arms)* (__value unit [timeout($t,)] _ => $body)] $($($rest)*)?
+        }
+    };
+    (@parse [$($arms:tt)*] deadline($d:expr) => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__fiber_select! {
+            @parse [$($arms)* (__value unit [deadline($d,)] _ => $body)] $($($rest)*)?
+        }
+    };
+    (@parse [$($arms:tt)*] default => $body:expr $(, $($rest:tt)*)?) => {
+        $crate::__fiber_select! {
+            @parse [$($arms)* (__value unit [default()] _ => $body)] $($($rest)*)?
+        }
+    };
+    (@parse [$($arms:tt)*] $($rest:tt)+) => {
+        ::std::compile_error!(::std::concat!(
+            "invalid select! arm: `", ::std::stringify!($($rest)+), "`"
+        ))
+    };
+    (@handler value $var:ident) => {
+        |__v| $var = ::std::option::Option::Some(__v)
+    };
+    (@handler unit $var:ident) => {
+        || $var = ::std::option::Option::Some(())
+    };
+    (@expand $(($var:ident $kind:ident [$method:ident($($args:tt)*)] $pat:pat => $body:expr))*) => {{
+        $( let mut $var = ::std::option::Option::None; )*
+        $crate::fiber::select::Select::new()
+            $(
+                .$method($($args)* $crate::__fiber_select!(@handler $kind $var))
+            )*
+            .wait();
+        $(
+            if let ::std::option::Option::Some(__v) = $var {
+                match __v {
+                    $pat => $body,
+                }
+            } else
+        )*
+        {
+            ::std::unreachable!()
+        }
+    }};
+    ($($arms:tt)*) => {
+        $crate::__fiber_select! { @parse [] $($arms)* }
+    };
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+
+    #[crate::test(tarantool = "crate")]
+    fn select_recv() {
+        if !crate::ffi::has_fiber_channel() {
+            return;
+        }
+
+        let control = Channel::<&str>::new(1);
+        let data = Channel::<i32>::new(4);
+
+        let (c, d) = (control.clone(), data.clone());
+        let jh = fiber::start(move || {
+            let mut got = vec![];
+            loop {
+                fiber::select! {
+                    recv(c) -> cmd => match cmd {
+                        Some("stop") | None => break,
+                        Some(other) => panic!("unexpected command {}", other),
+                    },
+                    recv(d) -> msg => got.push(msg.unwrap()),
+                }
+            }
+            got
+        });
+
+        data.send(1).unwrap();
+        data.send(2).unwrap();
+        fiber::reschedule();
+        data.send(3).unwrap();
+        fiber::reschedule();
+        control.send("stop").unwrap();
+        assert_eq!(jh.join().unwrap(), [1, 2, 3]);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn select_cond_and_timeout() {
+        let cond = Rc::new(Cond::new());
+
+        let res = fiber::select! {
+            cond(cond) -> _ => "cond",
+            timeout(Duration::from_millis(10)) => "timeout",
+        };
+        assert_eq!(res, "timeout");
+
+        let c = cond.clone();
+        let jh = fiber::defer(move || c.signal());
+        let res = fiber::select! {
+            cond(cond) -> signalled => signalled,
+            timeout(Duration::from_secs(10)) => false,
+        };
+        assert!(res);
+        jh.join().unwrap();
+
+        let res = fiber::check_yield(|| {
+            fiber::select! {
+                cond(cond) -> _ => 1,
+                default => 2,
+            }
+        });
+        assert_eq!(res, fiber::YieldResult::DidntYield(2));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn select_send_and_fairness() {
+        if !crate::ffi::has_fiber_channel() {
+            return;
+        }
+
+        let a = Channel::<i32>::new(1);
+        let b = Channel::<i32>::new(1);
+        let mut counts = [0; 2];
+        for _ in 0..10 {
+            a.try_send(0).ok();
+            b.try_send(0).ok();
+            let i = Select::new().recv(&a, |_| 0).recv(&b, |_| 1).wait();
+            counts[i] += 1;
+        }
+        assert!(counts[0] >= 4 && counts[1] >= 4, "{:?}", counts);
+
+        // Sending to a full channel succeeds once a message is received.
+        let full = Channel::<i32>::new(1);
+        full.send(1).unwrap();
+        let f = full.clone();
+        let jh = fiber::defer(move || f.recv());
+        let res = fiber::select! {
+            send(full, 2) -> res => res,
+            timeout(Duration::from_secs(10)) => Err(0),
+        };
+        assert_eq!(res, Ok(()));
+        assert_eq!(jh.join().unwrap(), Some(1));
+        assert_eq!(full.try_recv(), Ok(2));
+
+        // Cancelled fiber completes an arm with a failure.
+        let ch = Channel::<i32>::new(1);
+        let c = ch.clone();
+        let jh = fiber::start(move || {
+            fiber::select! {
+                recv(c) -> msg => msg,
+            }
+        });
+        jh.cancel();
+        assert_eq!(jh.join().unwrap(), None);
+    }
+}
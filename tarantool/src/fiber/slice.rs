@@ -0,0 +1,465 @@
+//! Fiber slice limits.
+//!
+//! A fiber slice is the maximum amount of time a fiber is allowed to run
+//! without yielding. When the *warning* slice is exceeded a warning is logged,
+//! when the *error* slice is exceeded [`check_slice`] returns an error. This
+//! is useful for detecting fibers which hog the TX thread.
+//!
+//! **NOTE**: the limits are not enforced automatically, the long running code
+//! must call [`check_slice`] periodically.
+//!
+//! This api is based on the lua `fiber.set_max_slice` & co. functions which
+//! are not available in all versions of tarantool. Use [`has_fiber_slice`] to
+//! check if it is supported in your case.
+//!
+//! See also [`Watchdog`] for detecting long running fibers without modifying
+//! their code.
+//!
+//! See also:
+//! - [Lua reference: fiber.set_max_slice](https://www.tarantool.io/en/doc/latest/reference/reference_lua/fiber/#fiber-set-max-slice)
+//! - [Lua reference: fiber.check_slice](https://www.tarantool.io/en/doc/latest/reference/reference_lua/fiber/#fiber-check-slice)
+
+use super::{Cond, FiberId, JoinHandle};
+use crate::error::BoxError;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+////////////////////////////////////////////////////////////////////////////////
+// FiberSlice
+////////////////////////////////////////////////////////////////////////////////
+
+/// A pair of fiber slice limits. See [module level documentation](self) for
+/// details.
+///
+/// Use [`Duration::MAX`] to disable one of the limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FiberSlice {
+    /// If exceeded, a warning is logged by [`check_slice`].
+    pub warn: Duration,
+    /// If exceeded, [`check_slice`] returns an error.
+    pub err: Duration,
+}
+
+impl FiberSlice {
+    #[inline(always)]
+    pub const fn new(warn: Duration, err: Duration) -> Self {
+        Self { warn, err }
+    }
+
+    #[inline(always)]
+    fn as_secs_f64(&self) -> (f64, f64) {
+        (self.warn.as_secs_f64(), self.err.as_secs_f64())
+    }
+}
+
+/// Only the error limit is set, warning limit is disabled.
+impl From<Duration> for FiberSlice {
+    #[inline(always)]
+    fn from(err: Duration) -> Self {
+        Self::new(Duration::MAX, err)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// slice api
+////////////////////////////////////////////////////////////////////////////////
+
+/// Returns `true` if the current tarantool executable supports fiber slices.
+#[inline]
+pub fn has_fiber_slice() -> bool {
+    crate::global_lua()
+        .eval("return require('fiber').check_slice ~= nil")
+        .unwrap_or(false)
+}
+
+/// Sets the default slice limits for all the fibers.
+///
+/// This is the equivalent of lua's `fiber.set_max_slice(slice)`.
+#[inline]
+pub fn set_default_max_slice(slice: impl Into<FiberSlice>) -> crate::Result<()> {
+    crate::global_lua()
+        .exec_with(
+            "local warn, err = ...
+            require('fiber').set_max_slice({ warn = warn, err = err })",
+            slice.into().as_secs_f64(),
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Sets the slice limits for the fiber with the given `id`. These override
+/// the default limits (see [`set_default_max_slice`]).
+///
+/// Returns an error if the fiber wasn't found.
+///
+/// This is the equivalent of lua's `fiber_object:set_max_slice(slice)`.
+#[inline]
+pub fn set_max_slice(id: FiberId, slice: impl Into<FiberSlice>) -> crate::Result<()> {
+    let (warn, err) = slice.into().as_secs_f64();
+    crate::global_lua()
+        .exec_with(
+            "local id, warn, err = ...
+            local f = require('fiber').find(id)
+            if f == nil then
+                error(('fiber %d not found'):format(id))
+            end
+            f:set_max_slice({ warn = warn, err = err })",
+            (id, warn, err),
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Sets the slice limits for the current fiber's current slice, i.e. until
+/// the fiber yields.
+///
+/// This is the equivalent of lua's `fiber.set_slice(slice)`.
+#[inline]
+pub fn set_slice(slice: impl Into<FiberSlice>) -> crate::Result<()> {
+    crate::global_lua()
+        .exec_with(
+            "local warn, err = ...
+            require('fiber').set_slice({ warn = warn, err = err })",
+            slice.into().as_secs_f64(),
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Extends the current fiber's current slice by the given durations.
+///
+/// This is the equivalent of lua's `fiber.extend_slice(slice)`.
+#[inline]
+pub fn extend_slice(slice: impl Into<FiberSlice>) -> crate::Result<()> {
+    crate::global_lua()
+        .exec_with(
+            "local warn, err = ...
+            require('fiber').extend_slice({ warn = warn, err = err })",
+            slice.into().as_secs_f64(),
+        )
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+/// Checks if the current fiber has exceeded its slice. Returns an error if
+/// the error limit was exceeded. Logs a warning if the warning limit was
+/// exceeded.
+///
+/// Call this periodically in long running loops, and yield (or abort) when
+/// an error is returned.
+///
+/// **NOTE**: this calls into lua, so it's relatively expensive. Consider
+/// calling it once every several iterations in hot loops.
+///
+/// This is the equivalent of lua's `fiber.check_slice()`.
+pub fn check_slice() -> crate::Result<()> {
+    let (code, message): (Option<u32>, Option<String>) = crate::global_lua().eval(
+        "local ok, err = pcall(require('fiber').check_slice)
+        if ok then
+            return nil, nil
+        end
+        if type(err) == 'cdata' then
+            return err.code, err.message
+        end
+        return nil, tostring(err)",
+    )?;
+    match (code, message) {
+        (_, None) => Ok(()),
+        (Some(code), Some(message)) => Err(BoxError::new(code, message).into()),
+        (None, Some(message)) => Err(tlua::LuaError::ExecutionError(message.into()).into()),
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Watchdog
+////////////////////////////////////////////////////////////////////////////////
+
+/// A debugging tool which detects fibers running longer than a threshold
+/// without yielding.
+///
+/// The watchdog is a fiber which wakes up periodically. If it wakes up
+/// later than expected by at least the threshold, the fiber which used the
+/// most cpu time in the meantime is reported via [`say_warn`] along with its
+/// backtrace (if supported by the tarantool executable). Use
+/// [`Watchdog::start_with_handler`] to handle the reports differently.
+///
+/// **NOTE**: the report is logged after the offending fiber yields, so the
+/// backtrace points to the place where the fiber yielded, not where it spent
+/// the time.
+///
+/// The watchdog is based on [`fiber::top`], which is enabled while the
+/// watchdog is running. This has a noticeable performance overhead, so the
+/// watchdog is intended for debugging and testing environments.
+///
+/// The watchdog is stopped when dropped.
+///
+/// [`say_warn`]: crate::say_warn
+/// [`fiber::top`]: crate::fiber::top
+#[derive(Debug)]
+pub struct Watchdog {
+    state: Rc<WatchdogState>,
+    fiber: Option<JoinHandle<'static, ()>>,
+}
+
+struct WatchdogState {
+    threshold: Duration,
+    cond: Cond,
+    is_stopped: Cell<bool>,
+    disable_top: bool,
+    handler: Box<dyn Fn(&WatchdogReport)>,
+}
+
+impl std::fmt::Debug for WatchdogState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("WatchdogState")
+            .field("threshold", &self.threshold)
+            .field("is_stopped", &self.is_stopped)
+            .field("disable_top", &self.disable_top)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Information about a fiber detected by a [`Watchdog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchdogReport {
+    /// Id of the fiber.
+    pub fiber_id: FiberId,
+    /// Name of the fiber.
+    pub fiber_name: String,
+    /// Cpu time the fiber used since the previous check of the watchdog.
+    pub cpu_time: Duration,
+    /// Backtrace of the place where the fiber yielded, if supported by the
+    /// tarantool executable.
+    pub backtrace: Option<Vec<String>>,
+}
+
+impl std::fmt::Display for WatchdogReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "fiber {} '{}' has been running for {:?} without yielding",
+            self.fiber_id, self.fiber_name, self.cpu_time
+        )?;
+        if let Some(backtrace) = &self.backtrace {
+            f.write_str(", yielded at:")?;
+            for frame in backtrace {
+                write!(f, "\n    {frame}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Watchdog {
+    /// Starts the watchdog fiber which reports fibers running longer than
+    /// `threshold` without yielding.
+    ///
+    /// Returns an error if [`fiber::top`] is not supported by the tarantool
+    /// executable or if spawning the fiber failed.
+    ///
+    /// [`fiber::top`]: crate::fiber::top
+    #[inline(always)]
+    pub fn start(threshold: Duration) -> crate::Result<Self> {
+        Self::start_with_handler(threshold, |report| crate::say_warn!("{}", report))
+    }
+
+    /// Like [`Watchdog::start`] but `handler` is called for each detected
+    /// fiber instead of logging it.
+    pub fn start_with_handler(
+        threshold: Duration,
+        handler: impl Fn(&WatchdogReport) + 'static,
+    ) -> crate::Result<Self> {
+        let top_was_enabled = super::top().is_ok();
+        if !top_was_enabled {
+            super::top_enable()?;
+        }
+
+        let state = Rc::new(WatchdogState {
+            threshold,
+            cond: Cond::new(),
+            is_stopped: Cell::new(false),
+            disable_top: !top_was_enabled,
+            handler: Box::new(handler),
+        });
+        let s = state.clone();
+        let fiber = super::Builder::new()
+            .name("rust watchdog")
+            .func(move || watchdog_main(&s))
+            .start();
+        let fiber = match fiber {
+            Ok(fiber) => fiber,
+            Err(e) => {
+                if state.disable_top {
+                    _ = super::top_disable();
+                }
+                return Err(e);
+            }
+        };
+
+        Ok(Self {
+            state,
+            fiber: Some(fiber),
+        })
+    }
+
+    /// Stops the watchdog and waits for its fiber to finish.
+    ///
+    /// This function **yields**.
+    #[inline(always)]
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Watchdog {
+    fn drop(&mut self) {
+        self.state.is_stopped.set(true);
+        self.state.cond.signal();
+        if let Some(fiber) = self.fiber.take() {
+            if let Err(e) = fiber.join() {
+                crate::say_error!("{e}");
+            }
+        }
+        if self.state.disable_top {
+            _ = super::top_disable();
+        }
+    }
+}
+
+fn watchdog_main(state: &WatchdogState) {
+    let interval = (state.threshold / 2).max(Duration::from_millis(1));
+    let mut prev_cpu_time = cpu_times();
+
+    loop {
+        let start = super::clock();
+        state.cond.wait_timeout(interval);
+        if state.is_stopped.get() || super::is_cancelled() {
+            break;
+        }
+        let lag = start.elapsed().saturating_sub(interval);
+        let cpu_time = cpu_times();
+
+        if lag >= state.threshold {
+            let me = super::id();
+            let culprit = cpu_time
+                .iter()
+                .filter(|(&id, _)| id != me)
+                .map(|(&id, (name, time))| {
+                    let prev = prev_cpu_time.get(&id).map(|(_, t)| *t);
+                    (id, name, time.saturating_sub(prev.unwrap_or_default()))
+                })
+                .max_by_key(|(_, _, delta)| *delta);
+            if let Some((id, name, delta)) = culprit {
+                (state.handler)(&report(id, name, delta));
+            }
+        }
+
+        prev_cpu_time = cpu_time;
+    }
+}
+
+fn cpu_times() -> HashMap<FiberId, (String, Duration)> {
+    match super::top() {
+        Ok(top) => top
+            .fibers
+            .into_iter()
+            .map(|f| (f.id, (f.name, f.time)))
+            .collect(),
+        Err(_) => HashMap::new(),
+    }
+}
+
+fn report(id: FiberId, name: &str, cpu_time: Duration) -> WatchdogReport {
+    let backtrace = super::info_with_backtrace()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|f| f.id == id)
+        .and_then(|f| f.backtrace);
+
+    WatchdogReport {
+        fiber_id: id,
+        fiber_name: name.to_owned(),
+        cpu_time,
+        backtrace,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::cell::RefCell;
+
+    #[crate::test(tarantool = "crate")]
+    fn slice_limits() {
+        if !has_fiber_slice() {
+            assert!(check_slice().is_err());
+            return;
+        }
+
+        set_slice(Duration::from_secs(100)).unwrap();
+        check_slice().unwrap();
+
+        set_slice(FiberSlice::new(Duration::MAX, Duration::ZERO)).unwrap();
+        let e = check_slice().unwrap_err();
+        assert!(e.to_string().contains("slice"), "{}", e);
+
+        // The slice is reset after a yield.
+        extend_slice(Duration::from_secs(100)).unwrap();
+        check_slice().unwrap();
+        fiber::reschedule();
+        check_slice().unwrap();
+
+        let jh = fiber::start(fiber::reschedule);
+        set_max_slice(jh.id_checked().unwrap(), Duration::from_secs(100)).unwrap();
+        jh.join().unwrap();
+        assert!(set_max_slice(u64::MAX, Duration::from_secs(1)).is_err());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn watchdog_reports_long_running_fiber() {
+        if fiber::top_enable().is_err() {
+            return;
+        }
+        fiber::top_disable().unwrap();
+
+        let reports = Rc::new(RefCell::new(vec![]));
+        let r = reports.clone();
+        let watchdog = Watchdog::start_with_handler(Duration::from_millis(10), move |report| {
+            r.borrow_mut().push(report.clone());
+        })
+        .unwrap();
+        assert!(fiber::top().is_ok());
+        fiber::reschedule();
+
+        let hog = fiber::Builder::new()
+            .name("test_hog")
+            .func(|| {
+                let start = std::time::Instant::now();
+                while start.elapsed() < Duration::from_millis(50) {
+                    std::hint::spin_loop();
+                }
+                // Stay alive until the watchdog has a look at us.
+                fiber::sleep(Duration::from_millis(20));
+            })
+            .start()
+            .unwrap();
+        hog.join().unwrap();
+
+        watchdog.stop();
+        assert!(fiber::top().is_err());
+
+        let reports = reports.borrow();
+        let report = reports
+            .iter()
+            .find(|r| r.fiber_name == "test_hog")
+            .unwrap_or_else(|| panic!("no report for test_hog: {:?}", reports));
+        assert!(report.cpu_time >= Duration::from_millis(40), "{:?}", report);
+    }
+}
@@ -0,0 +1,229 @@
+//! Catching panics at the fiber boundary.
+//!
+//! A panic inside a fiber function never unwinds into tarantool. Instead it is
+//! caught by the fiber trampoline and either returned from
+//! [`JoinHandle::join`] as a [`JoinError`], or passed to the fiber panic hook
+//! (see [`set_panic_hook`]) if the fiber is not joinable.
+//!
+//! [`JoinHandle::join`]: super::JoinHandle::join
+use super::FiberId;
+use std::any::Any;
+use std::cell::RefCell;
+use std::panic::AssertUnwindSafe;
+use std::rc::Rc;
+
+////////////////////////////////////////////////////////////////////////////////
+// JoinError
+////////////////////////////////////////////////////////////////////////////////
+
+/// An error returned from [`JoinHandle::join`] if the fiber function panicked.
+///
+/// Carries the panic payload and the name of the fiber which panicked.
+///
+/// [`JoinHandle::join`]: super::JoinHandle::join
+pub struct JoinError {
+    fiber_id: FiberId,
+    fiber_name: String,
+    payload: Box<dyn Any + Send + 'static>,
+}
+
+impl JoinError {
+    /// Returns the id of the fiber which panicked.
+    #[inline(always)]
+    pub fn fiber_id(&self) -> FiberId {
+        self.fiber_id
+    }
+
+    /// Returns the name of the fiber which panicked.
+    #[inline(always)]
+    pub fn fiber_name(&self) -> &str {
+        &self.fiber_name
+    }
+
+    /// Returns the panic message if the payload is a string, which is the case
+    /// for panics raised via [`panic!`] and friends.
+    #[inline]
+    pub fn message(&self) -> Option<&str> {
+        panic_message(&*self.payload)
+    }
+
+    /// Returns a reference to the panic payload.
+    #[inline(always)]
+    pub fn payload(&self) -> &(dyn Any + Send + 'static) {
+        &*self.payload
+    }
+
+    /// Consumes the error returning the panic payload.
+    ///
+    /// Can be used with [`std::panic::resume_unwind`] to propagate the panic
+    /// into the current fiber.
+    #[inline(always)]
+    pub fn into_panic(self) -> Box<dyn Any + Send + 'static> {
+        self.payload
+    }
+}
+
+/// Returns the panic message if the `payload` is a string, which is the case
+/// for panics raised via [`panic!`] and friends.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> Option<&str> {
+    if let Some(s) = payload.downcast_ref::<&'static str>() {
+        Some(s)
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        Some(s)
+    } else {
+        None
+    }
+}
+
+impl std::fmt::Display for JoinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "fiber '{}' ({}) panicked",
+            self.fiber_name, self.fiber_id
+        )?;
+        if let Some(msg) = self.message() {
+            write!(f, ": {msg}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for JoinError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("JoinError")
+            .field("fiber_id", &self.fiber_id)
+            .field("fiber_name", &self.fiber_name)
+            .field("message", &self.message())
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::error::Error for JoinError {}
+
+impl From<JoinError> for crate::error::Error {
+    #[inline(always)]
+    fn from(e: JoinError) -> Self {
+        Self::other(e.to_string())
+    }
+}
+
+/// Calls `f` catching a panic if it happens. Must be called from within the
+/// fiber which is running `f`.
+pub(super) fn catch_unwind<T>(f: impl FnOnce() -> T) -> Result<T, JoinError> {
+    std::panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| JoinError {
+        fiber_id: super::id(),
+        fiber_name: super::name(),
+        payload,
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// panic hook
+////////////////////////////////////////////////////////////////////////////////
+
+/// A fiber panic hook, see [`set_panic_hook`].
+pub type PanicHook = Box<dyn Fn(&JoinError)>;
+
+type SharedPanicHook = Rc<dyn Fn(&JoinError)>;
+
+thread_local! {
+    static PANIC_HOOK: RefCell<Option<SharedPanicHook>> = RefCell::new(None);
+}
+
+/// Sets the hook which is called when a non-joinable fiber panics.
+///
+/// Panics in joinable fibers are returned from [`JoinHandle::join`] instead.
+///
+/// The default hook logs the panic message via [`say_error!`]. Only one hook
+/// can be set at a time, the previous one is replaced.
+///
+/// The hook is called from within the fiber which panicked.
+///
+/// [`JoinHandle::join`]: super::JoinHandle::join
+/// [`say_error!`]: crate::say_error
+#[inline]
+pub fn set_panic_hook(hook: impl Fn(&JoinError) + 'static) {
+    PANIC_HOOK.with(|h| *h.borrow_mut() = Some(Rc::new(hook)));
+}
+
+/// Unregisters the current fiber panic hook and returns it. The default hook
+/// is used after this.
+///
+/// Returns `None` if no custom hook was set.
+#[inline]
+pub fn take_panic_hook() -> Option<PanicHook> {
+    let hook = PANIC_HOOK.with(|h| h.borrow_mut().take())?;
+    Some(Box::new(move |e: &JoinError| hook(e)))
+}
+
+fn default_panic_hook(e: &JoinError) {
+    crate::say_error!("{e}");
+}
+
+/// Passes the error to the current panic hook.
+pub(super) fn call_panic_hook(e: &JoinError) {
+    // Clone the hook, so that it can be replaced from within the hook itself.
+    let hook = PANIC_HOOK.with(|h| h.borrow().clone());
+    match hook {
+        Some(hook) => {
+            // A panic in the hook must not escape into tarantool either.
+            if std::panic::catch_unwind(AssertUnwindSafe(|| hook(e))).is_err() {
+                crate::say_error!("fiber panic hook panicked while handling: {e}");
+            }
+        }
+        None => default_panic_hook(e),
+    }
+}
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use std::cell::Cell;
+
+    #[crate::test(tarantool = "crate")]
+    fn join_returns_panic() {
+        let jh = fiber::Builder::new()
+            .name("panicky")
+            .func(|| -> i32 { panic!("oh no") })
+            .start()
+            .unwrap();
+        let e = jh.join().unwrap_err();
+        assert_eq!(e.fiber_name(), "panicky");
+        assert_eq!(e.message(), Some("oh no"));
+        assert_eq!(
+            e.to_string(),
+            format!("fiber 'panicky' ({}) panicked: oh no", e.fiber_id())
+        );
+
+        let jh = fiber::defer(|| panic!("{}", 42));
+        let e = jh.join().unwrap_err();
+        assert_eq!(e.message(), Some("42"));
+
+        let jh = fiber::start(|| std::panic::panic_any(13_u8));
+        let e = jh.join().unwrap_err();
+        assert_eq!(e.message(), None);
+        assert_eq!(e.into_panic().downcast::<u8>().ok(), Some(Box::new(13)));
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn non_joinable_panic_hook() {
+        let panicked = Rc::new(Cell::new(None));
+        let panicked_clone = panicked.clone();
+        set_panic_hook(move |e| panicked_clone.set(Some(e.to_string())));
+
+        let id = fiber::Builder::new()
+            .name("detached")
+            .func(|| panic!("boom"))
+            .start_non_joinable()
+            .unwrap();
+
+        assert!(take_panic_hook().is_some());
+        assert!(take_panic_hook().is_none());
+        assert_eq!(
+            panicked.take(),
+            Some(format!("fiber 'detached' ({id}) panicked: boom"))
+        );
+    }
+}
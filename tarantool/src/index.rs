@@ -26,6 +26,9 @@ use crate::unwrap_or;
 use crate::util::NumOrStr;
 use crate::util::Value;
 
+mod scan;
+pub use scan::{Scan, ScanIter};
+
 pub type IndexId = u32;
 
 /// An index is a group of key values and pointers.
@@ -202,6 +205,81 @@ impl<'a> Builder<'a> {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// AlterBuilder
+////////////////////////////////////////////////////////////////////////////////
+
+/// A builder for altering an existing index, see [`Index::alter`].
+pub struct AlterBuilder {
+    space_id: SpaceId,
+    index_id: IndexId,
+    opts: IndexAlterOptions,
+}
+
+impl AlterBuilder {
+    #[inline(always)]
+    pub fn new(space_id: SpaceId, index_id: IndexId) -> Self {
+        Self {
+            space_id,
+            index_id,
+            opts: IndexAlterOptions::default(),
+        }
+    }
+
+    define_setters! {
+        index_type(r#type: IndexType)
+        unique(unique: bool)
+    }
+
+    /// Rename the index.
+    #[inline(always)]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.opts.name = Some(name.into());
+        self
+    }
+
+    /// Add a part to the index's new parts list.
+    ///
+    /// Note that the new parts replace the old ones completely. See
+    /// [`Builder::part`] for details.
+    #[inline(always)]
+    pub fn part(mut self, part: impl Into<Part>) -> Self {
+        self.opts
+            .parts
+            .get_or_insert_with(|| Vec::with_capacity(8))
+            .push(part.into());
+        self
+    }
+
+    /// Add parts to the index's new parts list.
+    ///
+    /// Note that the new parts replace the old ones completely. See
+    /// [`Builder::parts`] for details.
+    #[inline(always)]
+    pub fn parts(mut self, parts: impl IntoIterator<Item = impl Into<Part>>) -> Self {
+        let iter = parts.into_iter();
+        let (size, _) = iter.size_hint();
+        self.opts
+            .parts
+            .get_or_insert_with(|| Vec::with_capacity(size))
+            .extend(iter.map(Into::into));
+        self
+    }
+
+    /// Alter the index with the current configuration.
+    #[inline(always)]
+    pub fn apply(self) -> crate::Result<()> {
+        crate::schema::index::alter_index(self.space_id, self.index_id, &self.opts)
+    }
+
+    /// Destructure the builder struct into a tuple of space_id, index_id and
+    /// alter options.
+    #[inline(always)]
+    pub fn into_parts(self) -> (SpaceId, IndexId, IndexAlterOptions) {
+        (self.space_id, self.index_id, self.opts)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // IndexOptions
 ////////////////////////////////////////////////////////////////////////////////
@@ -229,6 +307,18 @@ pub struct IndexOptions {
     // pub hint: Option<bool>,
 }
 
+/// List of options for altering an existing index, used by [`Index::alter_with`].
+/// Only the options which are set are changed.
+///
+/// For details see [index_object:alter](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_index/alter/).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexAlterOptions {
+    pub name: Option<String>,
+    pub r#type: Option<IndexType>,
+    pub unique: Option<bool>,
+    pub parts: Option<Vec<Part>>,
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // SequenceOpt
 ////////////////////////////////////////////////////////////////////////////////
@@ -532,6 +622,34 @@ impl Index {
         tuple.decode::<Metadata>()
     }
 
+    /// Return a builder for altering the index.
+    ///
+    /// ```no_run
+    /// use tarantool::{space::Space, index::FieldType as FT};
+    ///
+    /// let space = Space::find("users").unwrap();
+    /// space.index("name").unwrap()
+    ///     .alter()
+    ///     .unique(false)
+    ///     .parts([("name", FT::String), ("age", FT::Unsigned)])
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    #[inline(always)]
+    pub fn alter(&self) -> AlterBuilder {
+        AlterBuilder::new(self.space_id, self.index_id)
+    }
+
+    /// Alter the index.
+    ///
+    /// - `opts` - see [`IndexAlterOptions`] struct.
+    ///
+    /// For details see [index_object:alter](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_index/alter/).
+    #[inline(always)]
+    pub fn alter_with(&self, opts: &IndexAlterOptions) -> Result<(), Error> {
+        crate::schema::index::alter_index(self.space_id, self.index_id, opts)
+    }
+
     // Drops index.
     #[inline(always)]
     pub fn drop(&self) -> Result<(), Error> {
@@ -568,6 +686,25 @@ impl Index {
         )
     }
 
+    /// Return a builder for a fiber-friendly scan over the index in key
+    /// order, which periodically yields so that other fibers are not blocked
+    /// while a large space is being processed. See [`Scan`] for details.
+    ///
+    /// ```no_run
+    /// use tarantool::space::Space;
+    ///
+    /// let space = Space::find("users").unwrap();
+    /// let index = space.index("age").unwrap();
+    /// for tuple in index.scan().batch_size(100).iter().unwrap() {
+    ///     let tuple = tuple.unwrap();
+    ///     // ...
+    /// }
+    /// ```
+    #[inline(always)]
+    pub fn scan(&self) -> Scan {
+        Scan::new(self.clone())
+    }
+
     /// Allocate and initialize iterator for index.
     ///
     /// This is an alternative to [space.select()](../space/struct.Space.html#method.select) which goes via a particular
@@ -796,6 +933,36 @@ impl Index {
         }
     }
 
+    /// Return the statistics of the index: number of tuples, memory usage and
+    /// for vinyl indexes the statistics of the LSM tree. See [`IndexStat`]
+    /// for details.
+    ///
+    /// This function uses lua internally, so it's relatively expensive and is
+    /// intended for monitoring.
+    ///
+    /// [`IndexStat`]: crate::stat::IndexStat
+    #[inline(always)]
+    pub fn stat(&self) -> Result<crate::stat::IndexStat, Error> {
+        crate::stat::index_stat(self.space_id, self.index_id)
+    }
+
+    /// Schedule a compaction of the vinyl index, see [`vinyl::compact`].
+    ///
+    /// [`vinyl::compact`]: crate::vinyl::compact
+    #[inline(always)]
+    pub fn compact(&self) -> Result<(), Error> {
+        crate::vinyl::compact(self.space_id, self.index_id)
+    }
+
+    /// Return the state of the LSM tree of the vinyl index, see
+    /// [`vinyl::lsm_stat`].
+    ///
+    /// [`vinyl::lsm_stat`]: crate::vinyl::lsm_stat
+    #[inline(always)]
+    pub fn lsm_stat(&self) -> Result<crate::vinyl::LsmStat, Error> {
+        crate::vinyl::lsm_stat(self.space_id, self.index_id)
+    }
+
     /// Return a random tuple from the index (useful for statistical analysis).
     ///
     /// - `rnd` - random seed
@@ -1174,4 +1341,76 @@ mod tests {
             let _meta: Metadata = tuple.decode().unwrap();
         }
     }
+
+    #[crate::test(tarantool = "crate")]
+    fn alter() {
+        let space = Space::builder(&crate::temp_space_name!())
+            .field(("id", space::FieldType::Unsigned))
+            .field(("name", space::FieldType::String))
+            .field((
+                "nick",
+                space::FieldType::String,
+                space::IsNullable::Nullable,
+            ))
+            .create()
+            .unwrap();
+        space.index_builder("pk").create().unwrap();
+        let index = space.index_builder("i").part("name").create().unwrap();
+        space.insert(&(1, "foo", "x")).unwrap();
+        space.insert(&(2, "bar", ())).unwrap();
+
+        // Duplicate names are not allowed.
+        let e = index.alter().name("pk").apply().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: IndexExists: Index 'pk' already exists"
+        );
+
+        // Hash index must be unique.
+        index
+            .alter()
+            .unique(false)
+            .index_type(IndexType::Hash)
+            .apply()
+            .unwrap_err();
+
+        index
+            .alter()
+            .name("by_name")
+            .unique(false)
+            .parts(["name", "nick"])
+            .part(1u32)
+            .apply()
+            .unwrap();
+        assert_eq!(space.index("by_name"), Some(index.clone()));
+        assert_eq!(space.index("i"), None);
+        let meta = index.meta().unwrap();
+        assert_eq!(meta.r#type, IndexType::Tree);
+        assert_eq!(
+            meta.opts,
+            BTreeMap::from([("unique".into(), Value::from(false))])
+        );
+        assert_eq!(
+            meta.parts,
+            vec![
+                Part::field(1).field_type(FieldType::String),
+                Part::field(2)
+                    .field_type(FieldType::String)
+                    .is_nullable(true),
+                Part::field(0).field_type(FieldType::Unsigned),
+            ]
+        );
+
+        // Now non unique.
+        space.insert(&(3, "foo", ())).unwrap();
+        assert_eq!(index.count(IteratorType::Eq, &("foo",)).unwrap(), 2);
+
+        let e = index.alter().part("unknown").apply().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: NoSuchFieldNameInSpace: Field 'unknown' was not found in the space format"
+        );
+
+        space.drop().unwrap();
+    }
 }
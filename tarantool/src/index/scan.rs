@@ -0,0 +1,405 @@
+use super::{Index, IndexIterator, IndexType, IteratorType};
+use crate::error::{Error, TarantoolError, TarantoolErrorCode};
+use crate::fiber;
+use crate::set_error;
+use crate::time::Instant;
+use crate::tuple::{KeyDef, KeyDefPart, ToTupleBuffer, Tuple, TupleBuffer};
+use std::cmp::Ordering;
+use std::time::Duration;
+
+////////////////////////////////////////////////////////////////////////////////
+// Scan
+////////////////////////////////////////////////////////////////////////////////
+
+/// A builder for a fiber-friendly scan over an index, see [`Index::scan`].
+///
+/// The scan walks the index in key order and yields the current fiber every
+/// [`batch_size`] tuples or every [`time_slice`], whichever comes first, so
+/// that other fibers are not blocked while a large space is being processed.
+/// After each yield the scan is re-positioned right after the last returned
+/// tuple, so concurrent modifications of the space don't break it:
+/// - tuples inserted after the current position will be returned,
+/// - tuples deleted after the current position will not be returned,
+/// - tuples before the current position are not visited again.
+///
+/// Because the scan yields it must not be used inside a transaction.
+///
+/// Only TREE indexes are supported.
+///
+/// [`batch_size`]: Self::batch_size
+/// [`time_slice`]: Self::time_slice
+#[derive(Debug, Clone)]
+pub struct Scan {
+    index: Index,
+    batch_size: usize,
+    time_slice: Duration,
+    reverse: bool,
+    start: Option<TupleBuffer>,
+}
+
+impl Scan {
+    /// Default value for [`Self::batch_size`].
+    pub const DEFAULT_BATCH_SIZE: usize = 1000;
+    /// Default value for [`Self::time_slice`].
+    pub const DEFAULT_TIME_SLICE: Duration = Duration::from_millis(10);
+
+    #[inline(always)]
+    pub fn new(index: Index) -> Self {
+        Self {
+            index,
+            batch_size: Self::DEFAULT_BATCH_SIZE,
+            time_slice: Self::DEFAULT_TIME_SLICE,
+            reverse: false,
+            start: None,
+        }
+    }
+
+    /// Yield after every `batch_size` tuples. A value of `0` is treated as `1`.
+    #[inline(always)]
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Yield if at least `time_slice` has passed since the previous yield.
+    #[inline(always)]
+    pub fn time_slice(mut self, time_slice: Duration) -> Self {
+        self.time_slice = time_slice;
+        self
+    }
+
+    /// Walk the index in descending key order.
+    #[inline(always)]
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Start the scan at `key` instead of the beginning (or the end if
+    /// [`Self::reverse`] is set) of the index. Tuples with keys equal to `key`
+    /// are included.
+    #[inline(always)]
+    pub fn start<K>(mut self, key: &K) -> Result<Self, Error>
+    where
+        K: ToTupleBuffer + ?Sized,
+    {
+        self.start = Some(key.to_tuple_buffer()?);
+        Ok(self)
+    }
+
+    /// Returns an iterator over the tuples of the index. The iterator yields
+    /// the fiber in it's [`Iterator::next`] method, see [`Scan`] for details.
+    pub fn iter(self) -> Result<ScanIter, Error> {
+        let meta = self.index.meta()?;
+        if meta.r#type != IndexType::Tree {
+            set_error!(
+                TarantoolErrorCode::Unsupported,
+                "Scan is only supported for TREE indexes, index '{}' is {}",
+                meta.name,
+                meta.r#type.as_str()
+            );
+            return Err(TarantoolError::last().into());
+        }
+
+        // Same as tarantool, tuples with equal keys in a secondary index are
+        // ordered by the primary key, so the primary key parts are appended to
+        // get a strict order.
+        let pk = Index::new(meta.space_id, 0);
+        let pk_meta;
+        let mut parts = meta.parts.iter().collect::<Vec<_>>();
+        if meta.index_id != 0 {
+            pk_meta = pk.meta()?;
+            parts.extend(&pk_meta.parts);
+        }
+        let parts = parts
+            .into_iter()
+            .map(|p| KeyDefPart::try_from_index_part(p).expect("_index always has field numbers"))
+            .collect::<Vec<_>>();
+        let cmp_def = KeyDef::new(&parts)?;
+        let key_def = meta.to_key_def();
+
+        let start = match self.start {
+            Some(start) => start,
+            None => ().to_tuple_buffer()?,
+        };
+        Ok(ScanIter {
+            index: self.index,
+            key_def,
+            cmp_def,
+            reverse: self.reverse,
+            batch_size: self.batch_size,
+            time_slice: self.time_slice,
+            start,
+            iter: None,
+            last: None,
+            repositioned: false,
+            batch_count: 0,
+            batch_start: Instant::now_accurate(),
+            done: false,
+        })
+    }
+
+    /// Calls `f` for each tuple of the index. Returns the number of processed
+    /// tuples or the first error returned by `f`.
+    ///
+    /// ```no_run
+    /// use tarantool::space::Space;
+    /// use std::time::Duration;
+    ///
+    /// let space = Space::find("users").unwrap();
+    /// let mut total_age = 0;
+    /// space
+    ///     .scan()
+    ///     .batch_size(500)
+    ///     .time_slice(Duration::from_millis(5))
+    ///     .for_each(|tuple| {
+    ///         total_age += tuple.get::<_, u64>("age").unwrap_or(0);
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn for_each<F>(self, mut f: F) -> Result<usize, Error>
+    where
+        F: FnMut(Tuple) -> Result<(), Error>,
+    {
+        let mut count = 0;
+        for tuple in self.iter()? {
+            f(tuple?)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ScanIter
+////////////////////////////////////////////////////////////////////////////////
+
+/// An iterator over the tuples of an index which periodically yields the
+/// fiber, see [`Scan`].
+///
+/// Returns an error if the fiber is cancelled during a yield, after which the
+/// iteration stops.
+pub struct ScanIter {
+    index: Index,
+    /// Extracts the index key from a tuple.
+    key_def: KeyDef,
+    /// Defines the strict order of tuples in the index.
+    cmp_def: KeyDef,
+    reverse: bool,
+    batch_size: usize,
+    time_slice: Duration,
+    start: TupleBuffer,
+    iter: Option<IndexIterator>,
+    last: Option<Tuple>,
+    /// Set after the iterator is re-opened, until we skip the tuples which
+    /// have already been returned.
+    repositioned: bool,
+    batch_count: usize,
+    batch_start: Instant,
+    done: bool,
+}
+
+impl ScanIter {
+    fn open(&mut self) -> Result<IndexIterator, Error> {
+        let (iterator_type, key) = match &self.last {
+            None if self.reverse => (IteratorType::LE, self.start.clone()),
+            None => (IteratorType::GE, self.start.clone()),
+            Some(last) => {
+                // There may be several tuples with the same key in a non-unique
+                // index, so we position at the first of them and skip the
+                // ones which were already returned.
+                self.repositioned = true;
+                let key = self.key_def.extract_key(last)?;
+                let iterator_type = if self.reverse {
+                    IteratorType::LE
+                } else {
+                    IteratorType::GE
+                };
+                (iterator_type, key)
+            }
+        };
+        self.index.select(iterator_type, &key)
+    }
+
+    fn yield_if_needed(&mut self) -> Result<(), Error> {
+        if self.batch_count < self.batch_size && self.batch_start.elapsed() < self.time_slice {
+            return Ok(());
+        }
+        // The iterator must not be used after a yield.
+        self.iter = None;
+        fiber::r#yield()?;
+        self.batch_count = 0;
+        self.batch_start = Instant::now_accurate();
+        Ok(())
+    }
+
+    fn next_impl(&mut self) -> Result<Option<Tuple>, Error> {
+        self.yield_if_needed()?;
+        let mut iter = match self.iter.take() {
+            Some(iter) => iter,
+            None => self.open()?,
+        };
+
+        let already_returned = if self.reverse {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        };
+        let tuple = loop {
+            let Some(tuple) = iter.next() else {
+                return Ok(None);
+            };
+            if self.repositioned {
+                let last = self.last.as_ref().expect("set when repositioned");
+                if self.cmp_def.compare(&tuple, last) != already_returned {
+                    continue;
+                }
+                self.repositioned = false;
+            }
+            break tuple;
+        };
+
+        self.iter = Some(iter);
+        self.batch_count += 1;
+        self.last = Some(tuple.clone());
+        Ok(Some(tuple))
+    }
+}
+
+impl Iterator for ScanIter {
+    type Item = Result<Tuple, Error>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let res = self.next_impl().transpose();
+        if !matches!(res, Some(Ok(_))) {
+            self.done = true;
+            self.iter = None;
+        }
+        res
+    }
+}
+
+impl std::fmt::Debug for ScanIter {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ScanIter")
+            .field("index", &self.index)
+            .field("reverse", &self.reverse)
+            .field("batch_size", &self.batch_size)
+            .field("time_slice", &self.time_slice)
+            .field("last", &self.last)
+            .field("done", &self.done)
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::index::Part;
+    use crate::space::Space;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn keys(iter: ScanIter) -> Vec<u32> {
+        iter.map(|t| t.unwrap().get(0).unwrap()).collect()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn scan_yields_in_batches() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+        for i in 0..10 {
+            space.insert(&(i,)).unwrap();
+        }
+
+        let csw_before = fiber::csw();
+        let count = space
+            .scan()
+            .batch_size(3)
+            .time_slice(Duration::from_secs(100))
+            .for_each(|_| Ok(()))
+            .unwrap();
+        assert_eq!(count, 10);
+        assert_eq!(fiber::csw() - csw_before, 3);
+
+        let iter = space.scan().reverse(true).iter().unwrap();
+        assert_eq!(keys(iter), [9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+
+        let iter = space.scan().start(&(5,)).unwrap().iter().unwrap();
+        assert_eq!(keys(iter), [5, 6, 7, 8, 9]);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn scan_concurrent_modification() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+        let value = space
+            .index_builder("value")
+            .unique(false)
+            .part(Part::field(2).field_type(crate::index::FieldType::Unsigned))
+            .create()
+            .unwrap();
+        // Many tuples with the same secondary key.
+        for i in 0..10 {
+            space.insert(&(i, i / 4)).unwrap();
+        }
+
+        let modified = Rc::new(Cell::new(false));
+        let jh = fiber::Builder::new()
+            .func({
+                let space = space.clone();
+                let modified = modified.clone();
+                move || {
+                    // Runs during the first yield of the scan.
+                    space.delete(&(5,)).unwrap();
+                    space.insert(&(10, 1)).unwrap();
+                    space.insert(&(11, 0)).unwrap();
+                    modified.set(true);
+                }
+            })
+            .defer()
+            .unwrap();
+
+        let mut seen = vec![];
+        value
+            .scan()
+            .batch_size(4)
+            .for_each(|t| {
+                seen.push(t.field::<u32>(0).unwrap().unwrap());
+                Ok(())
+            })
+            .unwrap();
+        jh.join().unwrap();
+        assert!(modified.get());
+        // Tuples with equal secondary keys are ordered by primary key, so 11 is
+        // after the position of the first yield and is returned. 5 was deleted
+        // before the scan reached it.
+        assert_eq!(seen, [0, 1, 2, 3, 11, 4, 6, 7, 10, 8, 9]);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn scan_hash_index_unsupported() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        let pk = space
+            .index_builder("pk")
+            .index_type(IndexType::Hash)
+            .create()
+            .unwrap();
+        let e = pk.scan().iter().unwrap_err();
+        assert!(e.to_string().contains("only supported for TREE"), "{}", e);
+        space.drop().unwrap();
+    }
+}
@@ -57,6 +57,7 @@
 //! [stored procedure]: macro@crate::proc
 pub mod access_control;
 pub mod auth;
+pub mod broadcast;
 #[cfg(feature = "picodata")]
 pub mod cbus;
 pub mod clock;
@@ -82,6 +83,7 @@ pub mod sequence;
 pub mod session;
 pub mod space;
 pub mod sql;
+pub mod stat;
 #[cfg(feature = "test")]
 pub mod test;
 pub mod time;
@@ -91,6 +93,7 @@ pub mod tuple;
 pub mod util;
 pub mod uuid;
 pub mod vclock;
+pub mod vinyl;
 
 /// `#[tarantool::proc]` is a macro attribute for creating stored procedure
 /// functions.
@@ -204,6 +204,14 @@ impl DecodeError {
         }
     }
 
+    /// Message of the wrapped error. Used by `#[derive(Decode)]` to detect
+    /// missing optional fields.
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn source_message(&self) -> &str {
+        &self.source
+    }
+
     #[inline(always)]
     pub fn with_part(mut self, part: impl ToString) -> Self {
         self.part = Some(part.to_string());
@@ -902,6 +910,20 @@ impl Encode for serde_json::Map<String, serde_json::Value> {
     }
 }
 
+impl Encode for rmpv::Value {
+    #[inline]
+    fn encode(&self, w: &mut impl Write, _context: &Context) -> Result<(), EncodeError> {
+        rmpv::encode::write_value(w, self).map_err(|e| EncodeError(e.to_string()))
+    }
+}
+
+impl<'de> Decode<'de> for rmpv::Value {
+    #[inline]
+    fn decode(r: &mut &'de [u8], _context: &Context) -> Result<Self, DecodeError> {
+        rmpv::decode::read_value(r).map_err(DecodeError::new::<Self>)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // tests
 ////////////////////////////////////////////////////////////////////////////////
@@ -40,7 +40,6 @@
 #![cfg(feature = "net_box")]
 
 use core::time::Duration;
-use std::net::ToSocketAddrs;
 use std::rc::Rc;
 
 pub use index::{RemoteIndex, RemoteIndexIterator};
@@ -49,6 +48,7 @@ pub use options::{ConnOptions, ConnTriggers, Options};
 use promise::Promise;
 pub use space::RemoteSpace;
 
+use crate::coio::ResolveAddrs;
 use crate::error::Error;
 use crate::network::protocol;
 use crate::tuple::{Decode, ToTupleBuffer, Tuple};
@@ -79,15 +79,18 @@ impl Conn {
     /// automatically after a disconnect (see [reconnect_after](struct.ConnOptions.html#structfield.reconnect_after) option).
     /// The returned conn object supports methods for making remote requests, such as select, update or delete.
     ///
+    /// Domain names in `addr` are resolved in the coio thread pool, see
+    /// [`ResolveAddrs`].
+    ///
     /// See also: [ConnOptions](struct.ConnOptions.html)
     #[inline(always)]
     pub fn new(
-        addr: impl ToSocketAddrs,
+        addr: impl ResolveAddrs,
         options: ConnOptions,
         triggers: Option<Rc<dyn ConnTriggers>>,
     ) -> Result<Self, Error> {
         Ok(Conn {
-            inner: ConnInner::new(addr.to_socket_addrs()?.collect(), options, triggers)?,
+            inner: ConnInner::new(addr.resolve_addrs()?, options, triggers)?,
             is_master: true,
         })
     }
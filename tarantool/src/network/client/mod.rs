@@ -35,6 +35,7 @@
 
 pub mod reconnect;
 pub mod tcp;
+pub mod udp;
 
 use std::collections::HashMap;
 use std::io::Cursor;
@@ -535,8 +536,8 @@ mod tests {
         let fiber_b = fiber::start_async(async {
             client.ping().timeout(Duration::from_secs(3)).await.unwrap()
         });
-        fiber_a.join();
-        fiber_b.join();
+        fiber_a.join().unwrap();
+        fiber_b.join().unwrap();
     }
 
     #[crate::test(tarantool = "crate")]
@@ -284,7 +284,7 @@ mod tests {
             // Globally the client has 1 reconnection
             assert_eq!(client.reconnect_count(), 1);
         });
-        jh.join();
+        jh.join().unwrap();
     }
 
     #[crate::test(tarantool = "crate")]
@@ -19,8 +19,9 @@
 use std::cell::Cell;
 use std::ffi::{CString, NulError};
 use std::io;
-use std::mem::{self, MaybeUninit};
-use std::os::unix::io::RawFd;
+use std::mem;
+use std::net::SocketAddr;
+use std::os::unix::io::{IntoRawFd, RawFd};
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
@@ -99,22 +100,14 @@ impl TcpStream {
     pub fn connect_timeout(url: &str, port: u16, timeout: Duration) -> Result<Self, Error> {
         let deadline = fiber::clock().saturating_add(timeout);
 
-        // SAFETY: it is just simple sys call
-        let (v4_addrs, v6_addrs) = unsafe { resolve_addr(url, port, timeout.as_secs_f64())? };
+        let mut addrs = resolve_addr(url, port, timeout)?;
+        // Prefer ipv4 addresses, the order is otherwise preserved.
+        addrs.sort_by_key(SocketAddr::is_ipv6);
 
         let mut last_error = None;
 
-        for v4_addr in v4_addrs {
-            match Self::connect_single(LibcSocketAddr::V4(v4_addr), deadline) {
-                Ok(stream) => {
-                    return Ok(stream);
-                }
-                Err(e) => last_error = Some(e),
-            }
-        }
-
-        for v6_addr in v6_addrs {
-            match Self::connect_single(LibcSocketAddr::V6(v6_addr), deadline) {
+        for addr in addrs {
+            match Self::connect_single(addr, deadline) {
                 Ok(stream) => {
                     return Ok(stream);
                 }
@@ -136,7 +129,20 @@ impl TcpStream {
         }
     }
 
-    fn connect_single(socket_addr: LibcSocketAddr, deadline: Instant) -> io::Result<Self> {
+    /// Wraps a connected [`std::net::TcpStream`] switching it to the
+    /// nonblocking mode.
+    pub fn from_std(stream: std::net::TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        let result = Self {
+            fd: Rc::new(Cell::new(None)),
+        };
+        // Now TcpStream owns the fd and takes responsibility of closing it.
+        result.fd.set(Some(stream.into_raw_fd()));
+        Ok(result)
+    }
+
+    fn connect_single(socket_addr: SocketAddr, deadline: Instant) -> io::Result<Self> {
+        let socket_addr = LibcSocketAddr::from(socket_addr);
         let (kind, addr, addr_len);
         match &socket_addr {
             LibcSocketAddr::V4(v4) => {
@@ -302,68 +308,19 @@ unsafe fn check_socket_error(fd: RawFd) -> io::Result<()> {
     }
 }
 
-unsafe fn resolve_addr(
-    url: &str,
-    port: u16,
-    timeout: f64,
-) -> Result<(Vec<libc::sockaddr_in>, Vec<libc::sockaddr_in6>), Error> {
-    let mut hints = MaybeUninit::<libc::addrinfo>::zeroed().assume_init();
-    hints.ai_family = libc::AF_UNSPEC;
-    hints.ai_socktype = libc::SOCK_STREAM;
-
-    let host = CString::new(url).map_err(Error::ConstructCString)?;
-    let addrinfo = match crate::coio::getaddrinfo(&host, None, &hints, timeout) {
-        Ok(v) => v,
+fn resolve_addr(url: &str, port: u16, timeout: Duration) -> Result<Vec<SocketAddr>, Error> {
+    if let Err(e) = CString::new(url) {
+        return Err(Error::ConstructCString(e));
+    }
+
+    match crate::coio::resolve(url, port, timeout) {
+        Ok(addrs) => Ok(addrs),
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => Err(Error::Timeout),
         Err(e) => {
-            match e {
-                crate::error::Error::IO(ref ee) => {
-                    if let io::ErrorKind::TimedOut = ee.kind() {
-                        return Err(Error::Timeout);
-                    }
-                }
-                crate::error::Error::Tarantool(ref ee) => {
-                    if let Some(ref kind) = ee.error_type {
-                        let kind: &str = kind;
-                        if kind == "TimedOut" {
-                            return Err(Error::Timeout);
-                        }
-                    }
-                }
-                _ => (),
-            }
             crate::say_error!("coio_getaddrinfo failed: {e}");
-            return Err(Error::ResolveAddress(url.into()));
-        }
-    };
-
-    let mut ipv4_addresses = Vec::with_capacity(2);
-    let mut ipv6_addresses = Vec::with_capacity(2);
-    let mut current = addrinfo;
-
-    while !current.is_null() {
-        let ai = *current;
-        match ai.ai_family {
-            libc::AF_INET => {
-                let mut sockaddr = *(ai.ai_addr as *mut libc::sockaddr_in);
-                sockaddr.sin_port = port.to_be();
-                ipv4_addresses.push(sockaddr);
-            }
-            libc::AF_INET6 => {
-                let mut sockaddr = *(ai.ai_addr as *mut libc::sockaddr_in6);
-                sockaddr.sin6_port = port.to_be();
-                ipv6_addresses.push(sockaddr);
-            }
-            af => {
-                libc::freeaddrinfo(addrinfo);
-                return Err(Error::UnknownAddressFamily(af as u16));
-            }
+            Err(Error::ResolveAddress(url.into()))
         }
-        current = ai.ai_next;
     }
-
-    libc::freeaddrinfo(addrinfo);
-
-    Ok((ipv4_addresses, ipv6_addresses))
 }
 
 enum LibcSocketAddr {
@@ -371,6 +328,31 @@ enum LibcSocketAddr {
     V6(libc::sockaddr_in6),
 }
 
+impl From<SocketAddr> for LibcSocketAddr {
+    fn from(addr: SocketAddr) -> Self {
+        match addr {
+            SocketAddr::V4(v4) => {
+                // SAFETY: all zeroes is a valid value for sockaddr_in.
+                let mut sockaddr: libc::sockaddr_in = unsafe { mem::zeroed() };
+                sockaddr.sin_family = libc::AF_INET as _;
+                sockaddr.sin_port = v4.port().to_be();
+                sockaddr.sin_addr.s_addr = u32::from(*v4.ip()).to_be();
+                Self::V4(sockaddr)
+            }
+            SocketAddr::V6(v6) => {
+                // SAFETY: all zeroes is a valid value for sockaddr_in6.
+                let mut sockaddr: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+                sockaddr.sin6_family = libc::AF_INET6 as _;
+                sockaddr.sin6_port = v6.port().to_be();
+                sockaddr.sin6_addr.s6_addr = v6.ip().octets();
+                sockaddr.sin6_flowinfo = v6.flowinfo();
+                sockaddr.sin6_scope_id = v6.scope_id();
+                Self::V6(sockaddr)
+            }
+        }
+    }
+}
+
 impl AsyncWrite for TcpStream {
     fn poll_write(
         self: Pin<&mut Self>,
@@ -582,16 +564,10 @@ mod tests {
 
     #[crate::test(tarantool = "crate")]
     async fn get_libc_addrs() {
-        let (addrs_v4, addrs_v6) =
-            unsafe { resolve_addr("example.org", 80, _10_SEC.as_secs_f64()).unwrap() };
-
-        let mut our_addrs = HashSet::<net::SocketAddr>::new();
-        for v4 in addrs_v4 {
-            our_addrs.insert(to_socket_addr_v4(v4).into());
-        }
-        for v6 in addrs_v6 {
-            our_addrs.insert(to_socket_addr_v6(v6).into());
-        }
+        let our_addrs: HashSet<_> = resolve_addr("example.org", 80, _10_SEC)
+            .unwrap()
+            .into_iter()
+            .collect();
 
         let addrs_from_std: HashSet<_> = net::ToSocketAddrs::to_socket_addrs(&("example.org", 80))
             .unwrap()
@@ -602,16 +578,10 @@ mod tests {
         //
         // check what happens with "localhost"
         //
-        let (addrs_v4, addrs_v6) =
-            unsafe { resolve_addr("localhost", 1337, _10_SEC.as_secs_f64()).unwrap() };
-
-        let mut our_addrs = HashSet::<net::SocketAddr>::new();
-        for v4 in addrs_v4 {
-            our_addrs.insert(to_socket_addr_v4(v4).into());
-        }
-        for v6 in addrs_v6 {
-            our_addrs.insert(to_socket_addr_v6(v6).into());
-        }
+        let our_addrs: HashSet<_> = resolve_addr("localhost", 1337, _10_SEC)
+            .unwrap()
+            .into_iter()
+            .collect();
 
         let addrs_from_std: HashSet<_> = net::ToSocketAddrs::to_socket_addrs(&("localhost", 1337))
             .unwrap()
@@ -621,12 +591,27 @@ mod tests {
     }
 
     #[crate::test(tarantool = "crate")]
-    async fn get_libc_addrs_error() {
-        let err = unsafe {
-            resolve_addr("invalid domain name", 80, _10_SEC.as_secs_f64())
-                .unwrap_err()
-                .to_string()
+    fn libc_socket_addr_from_socket_addr() {
+        let v4: net::SocketAddrV4 = "127.0.0.1:3301".parse().unwrap();
+        let LibcSocketAddr::V4(sockaddr) = LibcSocketAddr::from(net::SocketAddr::from(v4)) else {
+            panic!("expected ipv4 address");
         };
+        assert_eq!(sockaddr.sin_family, libc::AF_INET as libc::sa_family_t);
+        assert_eq!(to_socket_addr_v4(sockaddr), v4);
+
+        let v6 = net::SocketAddrV6::new("fe80::1".parse().unwrap(), 3301, 7, 2);
+        let LibcSocketAddr::V6(sockaddr) = LibcSocketAddr::from(net::SocketAddr::from(v6)) else {
+            panic!("expected ipv6 address");
+        };
+        assert_eq!(sockaddr.sin6_family, libc::AF_INET6 as libc::sa_family_t);
+        assert_eq!(to_socket_addr_v6(sockaddr), v6);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    async fn get_libc_addrs_error() {
+        let err = resolve_addr("invalid domain name", 80, _10_SEC)
+            .unwrap_err()
+            .to_string();
 
         assert_eq!(err, "failed to resolve domain name 'invalid domain name'");
     }
@@ -738,8 +723,8 @@ mod tests {
                     .await
                     .unwrap();
             });
-            writer_handle.join();
-            reader_handle.join();
+            writer_handle.join().unwrap();
+            reader_handle.join().unwrap();
         }
         let buf = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
         assert_eq!(buf, vec![1, 2, 3, 4, 5])
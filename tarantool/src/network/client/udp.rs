@@ -0,0 +1,204 @@
+//! Contains an implementation of a custom async coio based [`UdpSocket`].
+//!
+//! ## Example
+//! ```no_run
+//! # async {
+//! use tarantool::network::client::udp::UdpSocket;
+//!
+//! let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+//! socket.send_to(b"requests:1|c", "127.0.0.1:8125").await.unwrap();
+//!
+//! let mut buf = [0; 1500];
+//! let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+//! # };
+//! ```
+
+use std::future::poll_fn;
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::task::{Context, Poll};
+
+use crate::coio::ResolveAddrs;
+use crate::ffi::tarantool as ffi;
+use crate::fiber;
+use crate::fiber::r#async::context::ContextExt;
+
+/// Async UdpSocket based on fibers and coio.
+///
+/// Use [timeout][t] on top of send or receive operations to set the max time
+/// to wait for an operation.
+///
+/// Same as [`super::tcp::TcpStream`] the socket is not [`futures::select`]
+/// friendly when awaiting multiple sockets, but it can be used with
+/// [`futures::join`] without problems.
+///
+/// See module level [documentation](super::udp) for examples.
+///
+/// [t]: crate::fiber::async::timeout::timeout
+#[derive(Debug)]
+pub struct UdpSocket {
+    inner: std::net::UdpSocket,
+}
+
+impl UdpSocket {
+    /// Creates a UDP socket bound to the given address.
+    /// See [`std::net::UdpSocket::bind`].
+    ///
+    /// `addr` is resolved in the coio thread pool, see [`ResolveAddrs`], this
+    /// makes the fiber **yield**.
+    pub fn bind<A: ResolveAddrs>(addr: A) -> io::Result<Self> {
+        Self::from_std(std::net::UdpSocket::bind(&*addr.resolve_addrs()?)?)
+    }
+
+    /// Wraps a [`std::net::UdpSocket`] switching it to the nonblocking mode.
+    pub fn from_std(socket: std::net::UdpSocket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        Ok(Self { inner: socket })
+    }
+
+    /// Sets the default destination of the socket, after which [`Self::send`]
+    /// and [`Self::recv`] can be used.
+    /// See [`std::net::UdpSocket::connect`].
+    ///
+    /// `addr` is resolved in the coio thread pool, see [`ResolveAddrs`], this
+    /// makes the fiber **yield**.
+    pub fn connect<A: ResolveAddrs>(&self, addr: A) -> io::Result<()> {
+        self.inner.connect(&*addr.resolve_addrs()?)
+    }
+
+    /// Sends a datagram to the given address. Returns the number of bytes
+    /// sent.
+    ///
+    /// `addr` is resolved in the coio thread pool, see [`ResolveAddrs`], this
+    /// makes the fiber **yield**.
+    pub async fn send_to<A: ResolveAddrs>(&self, buf: &[u8], addr: A) -> io::Result<usize> {
+        let addr = addr.resolve_addrs()?.into_iter().next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no addresses to send to")
+        })?;
+        poll_fn(|cx| self.poll_io(cx, ffi::CoIOFlags::WRITE, || self.inner.send_to(buf, addr)))
+            .await
+    }
+
+    /// Receives a datagram. Returns the number of bytes read and the address
+    /// of the sender.
+    ///
+    /// If `buf` is too small to hold the datagram, the excess bytes are
+    /// discarded.
+    pub async fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        poll_fn(|cx| self.poll_io(cx, ffi::CoIOFlags::READ, || self.inner.recv_from(buf))).await
+    }
+
+    /// Sends a datagram to the address the socket is connected to.
+    pub async fn send(&self, buf: &[u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_io(cx, ffi::CoIOFlags::WRITE, || self.inner.send(buf))).await
+    }
+
+    /// Receives a datagram from the address the socket is connected to.
+    pub async fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        poll_fn(|cx| self.poll_io(cx, ffi::CoIOFlags::READ, || self.inner.recv(buf))).await
+    }
+
+    /// Returns the address the socket is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+
+    /// Returns the address the socket is connected to.
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.peer_addr()
+    }
+
+    fn poll_io<T>(
+        &self,
+        cx: &mut Context<'_>,
+        flags: ffi::CoIOFlags,
+        f: impl FnOnce() -> io::Result<T>,
+    ) -> Poll<io::Result<T>> {
+        match f() {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                // SAFETY: Safe as long as this future is executed by
+                // `fiber::block_on` async executor.
+                unsafe { ContextExt::set_coio_wait(cx, self.inner.as_raw_fd(), flags) }
+                Poll::Pending
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                // Return poll pending without setting coio wait
+                // so that the operation can be retried immediately.
+                //
+                // SAFETY: Safe as long as this future is executed by
+                // `fiber::block_on` async executor.
+                unsafe { ContextExt::set_deadline(cx, fiber::clock()) }
+                Poll::Pending
+            }
+            res => Poll::Ready(res),
+        }
+    }
+}
+
+impl AsRawFd for UdpSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+
+    use crate::fiber;
+    use crate::fiber::r#async::timeout::{self, IntoTimeout as _};
+
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    async fn send_recv() {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b_addr = b.local_addr().unwrap();
+
+        let sent = a.send_to(b"ping", b_addr).await.unwrap();
+        assert_eq!(sent, 4);
+
+        let mut buf = [0; 16];
+        let (len, from) = b.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        assert_eq!(from, a.local_addr().unwrap());
+
+        a.connect(b_addr).unwrap();
+        a.send(b"pong").await.unwrap();
+        let len = b.recv(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"pong");
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn recv_in_another_fiber() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let jh = fiber::start(move || {
+            let mut buf = [0; 16];
+            let (len, _) = fiber::block_on(socket.recv_from(&mut buf)).unwrap();
+            buf[..len].to_vec()
+        });
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        fiber::block_on(sender.send_to(b"hello", addr)).unwrap();
+        assert_eq!(jh.join().unwrap(), b"hello");
+    }
+
+    #[crate::test(tarantool = "crate")]
+    async fn recv_timeout() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut buf = [0; 16];
+        let err = socket
+            .recv_from(&mut buf)
+            .timeout(Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, timeout::Error::Expired));
+    }
+}
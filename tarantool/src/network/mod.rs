@@ -3,6 +3,7 @@
 //! Consists of:
 //! - Runtime and transport agnostic [`protocol`] layer
 //! - Async and coio based [`client`] layer
+//! - Async and coio based tcp [`server`] helpers
 //!
 //! More on Sans-I/O pattern can be found on the respective [wiki](https://sans-io.readthedocs.io/how-to-sans-io.html).
 //!
@@ -12,6 +13,8 @@
 #[cfg(feature = "network_client")]
 pub mod client;
 pub mod protocol;
+#[cfg(feature = "network_client")]
+pub mod server;
 
 pub use protocol::ProtocolError;
 
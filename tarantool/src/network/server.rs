@@ -0,0 +1,713 @@
+//! Async coio based tcp server.
+//!
+//! Consists of:
+//! - [`TcpListener`] for accepting connections with `accept().await`,
+//! - [`Server`] a helper which accepts connections in a separate fiber and
+//!   handles each of them in a fiber of its own.
+//!
+//! The [`Server`] limits the number of concurrent connections, closes the
+//! connections which are idle for too long and is gracefully shut down when
+//! tarantool shuts down (see [`trigger::on_shutdown`]).
+//!
+//! ## Example
+//! ```no_run
+//! use futures::{AsyncReadExt, AsyncWriteExt};
+//! use tarantool::network::server::{Server, TcpListener};
+//! use std::time::Duration;
+//!
+//! let listener = TcpListener::bind("127.0.0.1:3302").unwrap();
+//! let server = Server::builder()
+//!     .name("echo")
+//!     .max_connections(128)
+//!     .idle_timeout(Duration::from_secs(60))
+//!     .start(listener, |mut conn| async move {
+//!         let mut buf = [0; 1024];
+//!         while !conn.is_shutting_down() {
+//!             let n = match conn.read(&mut buf).await {
+//!                 Ok(0) | Err(_) => break,
+//!                 Ok(n) => n,
+//!             };
+//!             if conn.write_all(&buf[..n]).await.is_err() {
+//!                 break;
+//!             }
+//!         }
+//!     })
+//!     .unwrap();
+//!
+//! // Stop accepting connections and wait for the active ones to finish.
+//! server.shutdown();
+//! ```
+//!
+//! [`trigger::on_shutdown`]: crate::trigger::on_shutdown
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+#[cfg(feature = "async-std")]
+use async_std::io::{Read as AsyncRead, Write as AsyncWrite};
+#[cfg(not(feature = "async-std"))]
+use futures::{AsyncRead, AsyncWrite};
+
+use super::client::tcp::TcpStream;
+use crate::coio::ResolveAddrs;
+use crate::ffi::tarantool as ffi;
+use crate::fiber::r#async::context::ContextExt;
+use crate::fiber::{self, FiberId};
+use crate::time::Instant;
+
+/// Default maximum number of concurrent connections of a [`Server`].
+pub const DEFAULT_MAX_CONNECTIONS: usize = 1024;
+
+/// How long a [`Server`] waits for the active connections to finish when it's
+/// shut down. See also [`ServerBuilder::shutdown_timeout`].
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(3);
+
+////////////////////////////////////////////////////////////////////////////////
+// TcpListener
+////////////////////////////////////////////////////////////////////////////////
+
+/// Async tcp listener based on fibers and coio.
+///
+/// Same as [`TcpStream`] it can only be used with the [`fiber::block_on`]
+/// async executor.
+#[derive(Debug)]
+pub struct TcpListener {
+    inner: std::net::TcpListener,
+}
+
+impl TcpListener {
+    /// Creates a listener bound to the given address.
+    ///
+    /// Domain names in `addr` are resolved in the coio thread pool, see
+    /// [`ResolveAddrs`].
+    pub fn bind<A: ResolveAddrs>(addr: A) -> io::Result<Self> {
+        Self::from_std(std::net::TcpListener::bind(&*addr.resolve_addrs()?)?)
+    }
+
+    /// Wraps a [`std::net::TcpListener`] switching it to the nonblocking mode.
+    pub fn from_std(listener: std::net::TcpListener) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        Ok(Self { inner: listener })
+    }
+
+    /// Accepts a new incoming connection. Returns the connected stream and the
+    /// address of the peer.
+    pub async fn accept(&self) -> io::Result<(TcpStream, SocketAddr)> {
+        poll_fn(|cx| self.poll_accept(cx)).await
+    }
+
+    /// Polls for a new incoming connection.
+    pub fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<(TcpStream, SocketAddr)>> {
+        match self.inner.accept() {
+            Ok((stream, addr)) => Poll::Ready(TcpStream::from_std(stream).map(|s| (s, addr))),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                // SAFETY: Safe as long as this future is executed by
+                // `fiber::block_on` async executor.
+                unsafe { ContextExt::set_coio_wait(cx, self.as_raw_fd(), ffi::CoIOFlags::READ) }
+                Poll::Pending
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                // SAFETY: Safe as long as this future is executed by
+                // `fiber::block_on` async executor.
+                unsafe { ContextExt::set_deadline(cx, fiber::clock()) }
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+
+    /// Returns the address the listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.inner.local_addr()
+    }
+}
+
+impl AsRawFd for TcpListener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Shared
+////////////////////////////////////////////////////////////////////////////////
+
+/// The state shared between the [`Server`], the accepting fiber and the
+/// connections.
+struct Shared {
+    name: String,
+    max_connections: usize,
+    idle_timeout: Option<Duration>,
+    shutdown_timeout: Duration,
+    is_shutdown: Cell<bool>,
+    accept_fiber: Cell<Option<FiberId>>,
+    /// Waker of the accepting fiber waiting for a free connection slot.
+    accept_waker: RefCell<Option<Waker>>,
+    next_connection_id: Cell<u64>,
+    /// Fibers handling the active connections.
+    connections: RefCell<HashMap<u64, FiberId>>,
+    /// Signalled when a connection is closed or the accepting fiber exits.
+    cond: fiber::Cond,
+}
+
+impl Shared {
+    fn poll_accept(
+        &self,
+        listener: &TcpListener,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<io::Result<(TcpStream, SocketAddr)>>> {
+        if self.is_shutdown.get() {
+            return Poll::Ready(None);
+        }
+        if self.connections.borrow().len() >= self.max_connections {
+            *self.accept_waker.borrow_mut() = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        listener.poll_accept(cx).map(Some)
+    }
+
+    fn connection_closed(&self, id: u64) {
+        self.connections.borrow_mut().remove(&id);
+        if let Some(waker) = self.accept_waker.take() {
+            waker.wake();
+        }
+        self.cond.broadcast();
+    }
+}
+
+/// Stops the server. **Yields**.
+fn shutdown(shared: &Shared, timeout: Duration) {
+    if shared.is_shutdown.replace(true) {
+        return;
+    }
+
+    // Stop accepting new connections.
+    if let Some(waker) = shared.accept_waker.take() {
+        waker.wake();
+    }
+    if let Some(id) = shared.accept_fiber.get() {
+        fiber::wakeup(id);
+    }
+    // Waiting in a cancelled fiber doesn't yield, hence the checks.
+    while shared.accept_fiber.get().is_some() && !fiber::is_cancelled() {
+        shared.cond.wait();
+    }
+
+    // Let the active connections finish.
+    let deadline = fiber::clock().saturating_add(timeout);
+    while !shared.connections.borrow().is_empty()
+        && fiber::clock() < deadline
+        && !fiber::is_cancelled()
+    {
+        shared.cond.wait_deadline(deadline);
+    }
+
+    // Cancelled connections fail with an error on the next io operation.
+    for &id in shared.connections.borrow().values() {
+        fiber::cancel(id);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Server
+////////////////////////////////////////////////////////////////////////////////
+
+/// A tcp server which handles each connection in a separate fiber.
+///
+/// Use [`Server::builder`] to configure and start a server.
+///
+/// The server is gracefully [shut down](Server::shutdown) when it's dropped
+/// or when tarantool shuts down.
+pub struct Server {
+    shared: Rc<Shared>,
+    local_addr: SocketAddr,
+}
+
+impl Server {
+    /// Returns a [`ServerBuilder`] with the default configuration.
+    #[inline(always)]
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::new()
+    }
+
+    /// Returns the name of the server. Connection fibers are named after it.
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.shared.name
+    }
+
+    /// Returns the address the server is listening on.
+    #[inline(always)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the number of currently active connections.
+    #[inline(always)]
+    pub fn connection_count(&self) -> usize {
+        self.shared.connections.borrow().len()
+    }
+
+    /// Returns `true` if the server is shut down or is being shut down.
+    #[inline(always)]
+    pub fn is_shutdown(&self) -> bool {
+        self.shared.is_shutdown.get()
+    }
+
+    /// Gracefully shut down the server.
+    ///
+    /// Stops accepting new connections and closes the listener, then waits
+    /// for at most [`ServerBuilder::shutdown_timeout`] for the active
+    /// connections to finish. The connections which are still active after
+    /// that are cancelled, which means any io operation on them returns an
+    /// error.
+    ///
+    /// Connection handlers can check [`Connection::is_shutting_down`] to
+    /// finish early.
+    ///
+    /// This function **yields**.
+    ///
+    /// **NOTE**: calling this from one of the connection fibers results in a
+    /// wait for the full shutdown timeout.
+    #[inline(always)]
+    pub fn shutdown(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        shutdown(&self.shared, self.shared.shutdown_timeout);
+    }
+}
+
+impl std::fmt::Debug for Server {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Server")
+            .field("name", &self.shared.name)
+            .field("local_addr", &self.local_addr)
+            .field("connections", &self.connection_count())
+            .field("is_shutdown", &self.is_shutdown())
+            .finish_non_exhaustive()
+    }
+}
+
+fn accept_loop<F, Fut>(listener: TcpListener, shared: Rc<Shared>, handler: Rc<F>)
+where
+    F: Fn(Connection) -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    fiber::block_on(async {
+        while let Some(res) = poll_fn(|cx| shared.poll_accept(&listener, cx)).await {
+            let (stream, peer_addr) = match res {
+                Ok(v) => v,
+                Err(e) => {
+                    crate::say_error!("{}: accept failed: {e}", shared.name);
+                    // Most likely out of file descriptors, let some of them
+                    // be closed.
+                    fiber::r#async::sleep(Duration::from_millis(10)).await;
+                    continue;
+                }
+            };
+            spawn_connection(&shared, &handler, stream, peer_addr);
+        }
+    });
+    // Close the listener before notifying the waiters.
+    drop(listener);
+    shared.accept_fiber.set(None);
+    shared.cond.broadcast();
+}
+
+fn spawn_connection<F, Fut>(
+    shared: &Rc<Shared>,
+    handler: &Rc<F>,
+    stream: TcpStream,
+    peer_addr: SocketAddr,
+) where
+    F: Fn(Connection) -> Fut + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    struct Guard(Rc<Shared>, u64);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.connection_closed(self.1);
+        }
+    }
+
+    let id = shared.next_connection_id.get();
+    shared.next_connection_id.set(id + 1);
+
+    let shared_clone = shared.clone();
+    let handler = handler.clone();
+    let res = fiber::Builder::new()
+        .name(format!("{}/{peer_addr}", shared.name))
+        .func(move || {
+            // Registering from within the fiber, so that the connection is
+            // removed even if the handler finishes without yielding.
+            let shared = shared_clone;
+            shared.connections.borrow_mut().insert(id, fiber::id());
+            let guard = Guard(shared.clone(), id);
+
+            let conn = Connection {
+                stream,
+                peer_addr,
+                idle_timeout: shared.idle_timeout,
+                last_activity: fiber::clock(),
+                shared: Rc::downgrade(&shared),
+            };
+            fiber::block_on(handler(conn));
+            drop(guard);
+        })
+        .start_non_joinable();
+    if let Err(e) = res {
+        crate::say_error!("{}: failed to start a connection fiber: {e}", shared.name);
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// ServerBuilder
+////////////////////////////////////////////////////////////////////////////////
+
+/// Factory for [`Server`] which can be used to configure its properties.
+#[derive(Debug, Clone)]
+pub struct ServerBuilder {
+    name: Option<String>,
+    max_connections: usize,
+    idle_timeout: Option<Duration>,
+    shutdown_timeout: Duration,
+}
+
+impl Default for ServerBuilder {
+    #[inline(always)]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerBuilder {
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            name: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            idle_timeout: None,
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+        }
+    }
+
+    /// Sets the name of the server. It's used as a prefix for the names of
+    /// the server's fibers and in log messages. Default is `"server"`.
+    #[inline(always)]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Sets the maximum number of concurrent connections. When the limit is
+    /// reached, new connections are not accepted until some of the active
+    /// ones are closed. Default is [`DEFAULT_MAX_CONNECTIONS`].
+    #[inline(always)]
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections;
+        self
+    }
+
+    /// Sets the maximum time a connection can wait for a read or write to
+    /// complete. After it expires the io operation returns an error of kind
+    /// [`io::ErrorKind::TimedOut`]. By default there's no idle timeout.
+    #[inline(always)]
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how long the server waits for the active connections to finish
+    /// when it's shut down. Default is [`DEFAULT_SHUTDOWN_TIMEOUT`].
+    #[inline(always)]
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Starts the server accepting connections from `listener`. Every
+    /// connection is handled by the future returned from `handler` in a
+    /// separate fiber.
+    ///
+    /// Returns an error if the accepting fiber could not be started.
+    pub fn start<F, Fut>(self, listener: TcpListener, handler: F) -> crate::Result<Server>
+    where
+        F: Fn(Connection) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let local_addr = listener.local_addr()?;
+        let name = self.name.unwrap_or_else(|| "server".into());
+        let shared = Rc::new(Shared {
+            name,
+            max_connections: self.max_connections,
+            idle_timeout: self.idle_timeout,
+            shutdown_timeout: self.shutdown_timeout,
+            is_shutdown: Cell::new(false),
+            accept_fiber: Cell::new(None),
+            accept_waker: RefCell::new(None),
+            next_connection_id: Cell::new(0),
+            connections: RefCell::new(HashMap::new()),
+            cond: fiber::Cond::new(),
+        });
+
+        let shared_clone = shared.clone();
+        let handler = Rc::new(handler);
+        let fiber_id = fiber::Builder::new()
+            .name(format!("{}/accept", shared.name))
+            .func(move || accept_loop(listener, shared_clone, handler))
+            .start_non_joinable()?;
+        shared.accept_fiber.set(Some(fiber_id));
+
+        let weak = Rc::downgrade(&shared);
+        let res = crate::trigger::on_shutdown(move || {
+            if let Some(shared) = Weak::upgrade(&weak) {
+                shutdown(&shared, shared.shutdown_timeout);
+            }
+        });
+        let server = Server { shared, local_addr };
+        // If the trigger could not be set, the server is shut down on drop.
+        res?;
+        Ok(server)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Connection
+////////////////////////////////////////////////////////////////////////////////
+
+/// A connection accepted by the [`Server`].
+///
+/// Implements [`AsyncRead`] and [`AsyncWrite`] on top of [`TcpStream`]
+/// applying the server's idle timeout. Once the server is shut down and the
+/// shutdown timeout expires, all the io operations return an error of kind
+/// [`io::ErrorKind::ConnectionAborted`].
+///
+/// Same as [`TcpStream`] it can only be used with the [`fiber::block_on`]
+/// async executor, which is what the [`Server`] uses for the handlers.
+pub struct Connection {
+    stream: TcpStream,
+    peer_addr: SocketAddr,
+    idle_timeout: Option<Duration>,
+    last_activity: Instant,
+    shared: Weak<Shared>,
+}
+
+impl Connection {
+    /// Returns the address of the peer.
+    #[inline(always)]
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Returns `true` if the server is being shut down. The handler should
+    /// finish what it's doing and return as soon as possible.
+    #[inline]
+    pub fn is_shutting_down(&self) -> bool {
+        self.shared
+            .upgrade()
+            .map_or(true, |shared| shared.is_shutdown.get())
+    }
+
+    /// Returns a reference to the underlying stream. Note that the idle
+    /// timeout is not applied to the operations on it.
+    #[inline(always)]
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+
+    /// Consumes the connection returning the underlying stream.
+    #[inline(always)]
+    pub fn into_stream(self) -> TcpStream {
+        self.stream
+    }
+
+    fn poll_io<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        f: impl FnOnce(Pin<&mut TcpStream>, &mut Context<'_>) -> Poll<io::Result<T>>,
+    ) -> Poll<io::Result<T>> {
+        if fiber::is_cancelled() {
+            let e = io::Error::new(io::ErrorKind::ConnectionAborted, "server is shut down");
+            return Poll::Ready(Err(e));
+        }
+
+        if let Poll::Ready(res) = f(Pin::new(&mut self.stream), cx) {
+            self.last_activity = fiber::clock();
+            return Poll::Ready(res);
+        }
+
+        if let Some(idle_timeout) = self.idle_timeout {
+            let deadline = self.last_activity.saturating_add(idle_timeout);
+            if fiber::clock() >= deadline {
+                let e = io::Error::new(io::ErrorKind::TimedOut, "connection idle timeout expired");
+                return Poll::Ready(Err(e));
+            }
+            // SAFETY: Safe as long as this future is executed by
+            // `fiber::block_on` async executor.
+            unsafe { ContextExt::set_deadline(cx, deadline) }
+        }
+        Poll::Pending
+    }
+}
+
+impl AsyncRead for Connection {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_io(cx, |stream, cx| stream.poll_read(cx, buf))
+    }
+}
+
+impl AsyncWrite for Connection {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_io(cx, |stream, cx| stream.poll_write(cx, buf))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_io(cx, |stream, cx| stream.poll_flush(cx))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_close(Pin::new(&mut self.stream), cx)
+    }
+}
+
+impl std::fmt::Debug for Connection {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Connection")
+            .field("peer_addr", &self.peer_addr)
+            .field("idle_timeout", &self.idle_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use futures::{AsyncReadExt, AsyncWriteExt};
+    use std::io::{Read, Write};
+
+    fn echo_server(builder: ServerBuilder) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        builder
+            .start(listener, |mut conn| async move {
+                let mut buf = [0; 64];
+                loop {
+                    match conn.read(&mut buf).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(n) => {
+                            if conn.write_all(&buf[..n]).await.is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            })
+            .unwrap()
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn accept() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            stream.write_all(b"hi").unwrap();
+        });
+        let (mut stream, _) = fiber::block_on(listener.accept()).unwrap();
+        let mut buf = [0; 2];
+        fiber::block_on(stream.read_exact(&mut buf)).unwrap();
+        assert_eq!(&buf, b"hi");
+        client.join().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn echo() {
+        let server = echo_server(Server::builder().name("echo"));
+        let addr = server.local_addr();
+
+        let client = std::thread::spawn(move || {
+            let mut stream = std::net::TcpStream::connect(addr).unwrap();
+            stream.write_all(b"ping").unwrap();
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).unwrap();
+            buf
+        });
+        while !client.is_finished() {
+            fiber::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(&client.join().unwrap(), b"ping");
+
+        server.shutdown();
+        assert!(std::net::TcpStream::connect(addr).is_err());
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn max_connections_and_idle_timeout() {
+        let server = echo_server(
+            Server::builder()
+                .max_connections(1)
+                .idle_timeout(Duration::from_millis(50)),
+        );
+        let addr = server.local_addr();
+
+        let first = std::net::TcpStream::connect(addr).unwrap();
+        fiber::sleep(Duration::from_millis(10));
+        assert_eq!(server.connection_count(), 1);
+
+        // The second connection isn't accepted until the first one is closed
+        // due to the idle timeout.
+        let _second = std::net::TcpStream::connect(addr).unwrap();
+        fiber::sleep(Duration::from_millis(10));
+        assert_eq!(server.connection_count(), 1);
+
+        let deadline = fiber::clock().saturating_add(Duration::from_secs(1));
+        while fiber::clock() < deadline {
+            fiber::sleep(Duration::from_millis(10));
+            // The first connection was closed by the server.
+            let mut buf = [0; 1];
+            first.set_nonblocking(true).unwrap();
+            if matches!((&first).read(&mut buf), Ok(0)) {
+                break;
+            }
+        }
+        assert!(matches!((&first).read(&mut [0; 1]), Ok(0)));
+        assert_eq!(server.connection_count(), 1);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn shutdown_cancels_connections() {
+        let server = echo_server(Server::builder().shutdown_timeout(Duration::from_millis(10)));
+        let _client = std::net::TcpStream::connect(server.local_addr()).unwrap();
+        fiber::sleep(Duration::from_millis(10));
+        assert_eq!(server.connection_count(), 1);
+
+        let shared = server.shared.clone();
+        server.shutdown();
+        fiber::sleep(Duration::from_millis(10));
+        assert!(shared.connections.borrow().is_empty());
+    }
+}
@@ -1,8 +1,13 @@
 use crate::c_ptr;
-use crate::error::{Error, TarantoolError};
+use crate::error::{Error, TarantoolError, TarantoolErrorCode};
 use crate::ffi::lua;
 use crate::ffi::tarantool::luaT_call;
-use crate::index::{Index, IndexOptions};
+use crate::index::{FieldType, Index, IndexAlterOptions, IndexOptions, Part};
+use crate::schema;
+use crate::set_error;
+use crate::space::{SystemSpace, UpdateOps};
+use crate::util::{NumOrStr, Value};
+use std::collections::BTreeMap;
 use tlua::AsLua as _;
 use tlua::{
     LuaError::{self, ExecutionError},
@@ -39,6 +44,146 @@ pub fn create_index(space_id: u32, index_name: &str, opts: &IndexOptions) -> Res
     Ok(Index::new(space_id, index_id))
 }
 
+/// Alter existing index.
+///
+/// - `space_id` - ID of existing space.
+/// - `index_id` - ID of existing index.
+/// - `opts`     - see IndexAlterOptions struct, only the options which are set
+///   are changed.
+///
+/// The index is altered by updating its tuple in `_index`, so tarantool
+/// validates the new definition the same way as in `index_object:alter()`.
+///
+/// For details see [index_object:alter](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_index/alter/)
+pub fn alter_index(space_id: u32, index_id: u32, opts: &IndexAlterOptions) -> Result<(), Error> {
+    let sys_space = SystemSpace::Space.as_space();
+    let Some(tuple) = sys_space.get(&[space_id])? else {
+        set_error!(TarantoolErrorCode::NoSuchSpace, "#{}", space_id);
+        return Err(TarantoolError::last().into());
+    };
+    let space_meta: crate::space::RawMetadata = tuple.decode()?;
+
+    let sys_index = SystemSpace::Index.as_space();
+    if sys_index.get(&[space_id, index_id])?.is_none() {
+        set_error!(
+            TarantoolErrorCode::NoSuchIndexID,
+            "No index #{} is defined in space '{}'",
+            index_id,
+            space_meta.name
+        );
+        return Err(TarantoolError::last().into());
+    }
+    schema::check_space_alter_access(&space_meta)?;
+
+    let mut ops = UpdateOps::new();
+    if let Some(name) = &opts.name {
+        let name_index = sys_index
+            .index("name")
+            .expect("_index always has index 'name'");
+        if let Some(t) = name_index.get(&(space_id, name))? {
+            if t.field::<u32>(1)? != Some(index_id) {
+                set_error!(
+                    TarantoolErrorCode::IndexExists,
+                    "Index '{}' already exists",
+                    name
+                );
+                return Err(TarantoolError::last().into());
+            }
+        }
+        ops.assign("name", name)?;
+    }
+    if let Some(r#type) = opts.r#type {
+        ops.assign("type", r#type)?;
+    }
+    if let Some(unique) = opts.unique {
+        ops.assign("opts.unique", unique)?;
+    }
+    if let Some(parts) = &opts.parts {
+        let parts = parts
+            .iter()
+            .map(|part| resolve_part(part, &space_meta.format))
+            .collect::<Result<Vec<_>, _>>()?;
+        ops.assign("parts", parts)?;
+    }
+
+    if !ops.as_slice().is_empty() {
+        sys_index.update(&[space_id, index_id], ops)?;
+    }
+    Ok(())
+}
+
+/// Converts an index part as accepted by `box.schema.index.create` (1-based
+/// field numbers or field names with optional json paths) to the
+/// representation stored in `_index` (0-based field numbers).
+fn resolve_part(
+    part: &Part,
+    format: &[BTreeMap<String, rmpv::Value>],
+) -> Result<BTreeMap<&'static str, Value<'static>>, Error> {
+    if part.collation.is_some() {
+        set_error!(
+            TarantoolErrorCode::Unsupported,
+            "collations are not supported when altering an index"
+        );
+        return Err(TarantoolError::last().into());
+    }
+
+    let (field_no, path) = match &part.field {
+        NumOrStr::Num(0) => {
+            set_error!(
+                TarantoolErrorCode::IllegalParams,
+                "field numbers in index parts are 1-based"
+            );
+            return Err(TarantoolError::last().into());
+        }
+        NumOrStr::Num(n) => (n - 1, part.path.clone()),
+        NumOrStr::Str(name) => {
+            let position = |name: &str| {
+                format
+                    .iter()
+                    .position(|f| f.get("name").and_then(rmpv::Value::as_str) == Some(name))
+            };
+            if let Some(i) = position(name) {
+                (i as u32, part.path.clone())
+            } else {
+                let split_at = name.find(['.', '[']).unwrap_or(name.len());
+                let (head, path) = name.split_at(split_at);
+                let Some(i) = position(head).filter(|_| !path.is_empty()) else {
+                    set_error!(
+                        TarantoolErrorCode::NoSuchFieldNameInSpace,
+                        "Field '{}' was not found in the space format",
+                        name
+                    );
+                    return Err(TarantoolError::last().into());
+                };
+                (i as u32, Some(path.into()))
+            }
+        }
+    };
+
+    // Same as in lua, type and nullability default to those of the field in
+    // the space format.
+    let field_format = format.get(field_no as usize).filter(|_| path.is_none());
+    let r#type = part.r#type.or_else(|| {
+        let t = field_format?.get("type")?.as_str()?;
+        t.parse::<FieldType>().ok()
+    });
+    let is_nullable = part
+        .is_nullable
+        .or_else(|| field_format?.get("is_nullable")?.as_bool());
+
+    let r#type = r#type.unwrap_or(FieldType::Unsigned);
+    let mut res = BTreeMap::new();
+    res.insert("field", Value::Num(field_no));
+    res.insert("type", Value::Str(r#type.as_str().into()));
+    if is_nullable == Some(true) {
+        res.insert("is_nullable", Value::Bool(true));
+    }
+    if let Some(path) = path {
+        res.insert("path", Value::Str(path.into()));
+    }
+    Ok(res)
+}
+
 /// Drop existing index.
 ///
 /// - `space_id` - ID of existing space.
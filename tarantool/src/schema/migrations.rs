@@ -0,0 +1,442 @@
+//! Versioned schema migrations.
+//!
+//! A module registers an ordered list of migrations in a [`Migrations`] set.
+//! Each migration is identified by a version number, which must be strictly
+//! increasing, and is either a rust closure or a chunk of lua code (e.g. DDL
+//! calls like `box.schema.space.create`).
+//!
+//! When [`Migrations::apply`] is called, every migration with a version
+//! greater than the currently applied one is executed in a separate
+//! transaction, and the version is recorded in the `_schema` system space in
+//! the same transaction. So each migration is either applied completely
+//! exactly once or not applied at all.
+//!
+//! The version is stored under the `migrations.<name>` key of `_schema`, so
+//! several independent sets of migrations (e.g. of different modules) can be
+//! used on the same instance as long as their names are different.
+//!
+//! Migrations modify the schema and therefore are refused on a read-only
+//! instance. Use [`Migrations::wait_rw_and_apply`] to wait until the instance
+//! becomes writable (see `box.ctl.wait_rw`).
+//!
+//! # Example
+//! ```no_run
+//! use tarantool::schema::migrations::Migrations;
+//! use tarantool::space::Space;
+//! use std::time::Duration;
+//!
+//! let migrations = Migrations::new("my_module")
+//!     .add(1, "create users", || {
+//!         let space = Space::builder("users").create()?;
+//!         space.index_builder("pk").create()?;
+//!         Ok(())
+//!     })
+//!     .add_lua(2, "create users.name index", r#"
+//!         box.space.users:create_index('name', {parts = {{2, 'string'}}})
+//!     "#);
+//!
+//! for m in migrations.pending().unwrap() {
+//!     println!("will apply {}: {}", m.version(), m.name());
+//! }
+//!
+//! migrations.wait_rw_and_apply(Duration::from_secs(10)).unwrap();
+//! ```
+
+use crate::error::Error as TarantoolError;
+use crate::space::{Space, SystemSpace};
+use crate::transaction::{self, TransactionError};
+use std::time::Duration;
+
+/// Migration related errors.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("cannot apply migrations '{0}': the instance is read-only")]
+    ReadOnly(String),
+
+    #[error("cannot apply migrations '{0}': a transaction is already active")]
+    InTransaction(String),
+
+    #[error("migration '{set}' {version} ({name}) failed: {error}")]
+    Failed {
+        set: String,
+        version: u64,
+        name: String,
+        error: TarantoolError,
+    },
+
+    #[error("{0}")]
+    Tarantool(#[from] TarantoolError),
+}
+
+impl From<Error> for TarantoolError {
+    #[inline(always)]
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Tarantool(e) => e,
+            e => Self::other(e),
+        }
+    }
+}
+
+enum Kind {
+    Func(Box<dyn Fn() -> Result<(), TarantoolError>>),
+    Lua(String),
+}
+
+/// A single registered migration. See [module level documentation](self).
+pub struct Migration {
+    version: u64,
+    name: String,
+    kind: Kind,
+}
+
+impl Migration {
+    /// Returns the version of the migration.
+    #[inline(always)]
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the human readable name of the migration.
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the lua code of the migration if it's defined via lua.
+    #[inline(always)]
+    pub fn lua_code(&self) -> Option<&str> {
+        match &self.kind {
+            Kind::Lua(code) => Some(code),
+            Kind::Func(_) => None,
+        }
+    }
+
+    fn run(&self) -> Result<(), TarantoolError> {
+        match &self.kind {
+            Kind::Func(f) => f(),
+            Kind::Lua(code) => {
+                let lua = crate::lua_state();
+                lua.exec(code)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Migration {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Migration")
+            .field("version", &self.version)
+            .field("name", &self.name)
+            .field("lua_code", &self.lua_code())
+            .finish_non_exhaustive()
+    }
+}
+
+/// An ordered set of schema migrations. See [module level documentation](self).
+#[derive(Debug)]
+pub struct Migrations {
+    name: String,
+    migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    /// Creates an empty set of migrations. `name` is used to store the
+    /// applied version in `_schema` and must be unique per instance.
+    #[inline(always)]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            migrations: Vec::new(),
+        }
+    }
+
+    /// Registers a migration implemented as a rust closure.
+    ///
+    /// # Panics
+    /// If `version` is 0 or is not greater than the version of the previously
+    /// added migration.
+    #[inline]
+    pub fn add<F>(self, version: u64, name: impl Into<String>, f: F) -> Self
+    where
+        F: Fn() -> Result<(), TarantoolError> + 'static,
+    {
+        self.push(version, name.into(), Kind::Func(Box::new(f)))
+    }
+
+    /// Registers a migration implemented as a chunk of lua code.
+    ///
+    /// # Panics
+    /// If `version` is 0 or is not greater than the version of the previously
+    /// added migration.
+    #[inline]
+    pub fn add_lua(self, version: u64, name: impl Into<String>, code: impl Into<String>) -> Self {
+        self.push(version, name.into(), Kind::Lua(code.into()))
+    }
+
+    fn push(mut self, version: u64, name: String, kind: Kind) -> Self {
+        let previous = self.migrations.last().map_or(0, |m| m.version);
+        if version <= previous {
+            panic!(
+                "migration '{}' {version} ({name}) must have a version greater than {previous}",
+                self.name
+            );
+        }
+        self.migrations.push(Migration {
+            version,
+            name,
+            kind,
+        });
+        self
+    }
+
+    /// Returns the name of the set of migrations.
+    #[inline(always)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns all the registered migrations.
+    #[inline(always)]
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Returns the version of the last applied migration or 0 if none were
+    /// applied yet.
+    pub fn current_version(&self) -> Result<u64, Error> {
+        let sys_schema = Space::from(SystemSpace::Schema);
+        let Some(tuple) = sys_schema.get(&(self.schema_key(),))? else {
+            return Ok(0);
+        };
+        Ok(tuple.field(1)?.unwrap_or(0))
+    }
+
+    /// Returns the migrations which haven't been applied yet in the order
+    /// they would be applied. Doesn't modify anything, so can be used for a
+    /// dry run.
+    pub fn pending(&self) -> Result<Vec<&Migration>, Error> {
+        let current = self.current_version()?;
+        Ok(self
+            .migrations
+            .iter()
+            .filter(|m| m.version > current)
+            .collect())
+    }
+
+    /// Applies all the pending migrations. Returns versions of the applied
+    /// migrations.
+    ///
+    /// Each migration is applied in a separate transaction, so if one of them
+    /// fails the previous ones stay applied. Returns an error without applying
+    /// anything if the instance is read-only or if there's an active
+    /// transaction.
+    pub fn apply(&self) -> Result<Vec<u64>, Error> {
+        if transaction::is_in_transaction() {
+            return Err(Error::InTransaction(self.name.clone()));
+        }
+        if is_read_only()? {
+            return Err(Error::ReadOnly(self.name.clone()));
+        }
+
+        let mut applied = vec![];
+        for migration in self.pending()? {
+            let res = transaction::transaction(|| -> Result<bool, TarantoolError> {
+                // Somebody might have applied it concurrently.
+                if self.current_version()? >= migration.version {
+                    return Ok(false);
+                }
+                migration.run()?;
+                let sys_schema = Space::from(SystemSpace::Schema);
+                sys_schema.replace(&(self.schema_key(), migration.version))?;
+                Ok(true)
+            });
+            let is_applied = match res {
+                Ok(is_applied) => is_applied,
+                Err(TransactionError::AlreadyStarted) => {
+                    return Err(Error::InTransaction(self.name.clone()));
+                }
+                Err(TransactionError::FailedToCommit(e))
+                | Err(TransactionError::FailedToRollback(e)) => {
+                    return Err(self.failed(migration, e.into()));
+                }
+                Err(TransactionError::RolledBack(e)) => {
+                    return Err(self.failed(migration, e));
+                }
+            };
+            if is_applied {
+                crate::say_info!(
+                    "applied migration '{}' {} ({})",
+                    self.name,
+                    migration.version,
+                    migration.name
+                );
+                applied.push(migration.version);
+            }
+        }
+
+        Ok(applied)
+    }
+
+    /// Waits until the instance becomes writable (see `box.ctl.wait_rw`) and
+    /// applies all the pending migrations. See [`Self::apply`].
+    pub fn wait_rw_and_apply(&self, timeout: Duration) -> Result<Vec<u64>, Error> {
+        wait_rw(timeout)?;
+        self.apply()
+    }
+
+    #[inline(always)]
+    fn schema_key(&self) -> String {
+        format!("migrations.{}", self.name)
+    }
+
+    #[inline(always)]
+    fn failed(&self, migration: &Migration, error: TarantoolError) -> Error {
+        Error::Failed {
+            set: self.name.clone(),
+            version: migration.version,
+            name: migration.name.clone(),
+            error,
+        }
+    }
+}
+
+/// Returns `true` if the instance is read-only (see `box.info.ro`).
+#[inline]
+fn is_read_only() -> Result<bool, TarantoolError> {
+    let lua = crate::lua_state();
+    let is_ro = lua.eval("return box.info.ro")?;
+    Ok(is_ro)
+}
+
+/// Waits until the instance becomes writable, see `box.ctl.wait_rw`.
+#[inline]
+fn wait_rw(timeout: Duration) -> Result<(), TarantoolError> {
+    let lua = crate::lua_state();
+    lua.exec_with("box.ctl.wait_rw(...)", timeout.as_secs_f64())
+        .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn cleanup(migrations: &Migrations) {
+        let sys_schema = Space::from(SystemSpace::Schema);
+        sys_schema.delete(&(migrations.schema_key(),)).unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn apply_once() {
+        let space_name = crate::temp_space_name!();
+        let calls = Rc::new(Cell::new(0));
+        let migrations = Migrations::new(&space_name)
+            .add(1, "create space", {
+                let space_name = space_name.clone();
+                let calls = calls.clone();
+                move || {
+                    calls.set(calls.get() + 1);
+                    let space = Space::builder(&space_name).create()?;
+                    space.index_builder("pk").create()?;
+                    Ok(())
+                }
+            })
+            .add_lua(
+                2,
+                "insert data",
+                format!("box.space['{space_name}']:insert{{1, 'foo'}}"),
+            );
+
+        assert_eq!(migrations.current_version().unwrap(), 0);
+        let pending: Vec<_> = migrations
+            .pending()
+            .unwrap()
+            .iter()
+            .map(|m| (m.version(), m.name()))
+            .collect();
+        assert_eq!(pending, [(1, "create space"), (2, "insert data")]);
+        // Dry run doesn't change anything.
+        assert!(Space::find(&space_name).is_none());
+
+        assert_eq!(migrations.apply().unwrap(), [1, 2]);
+        assert_eq!(migrations.current_version().unwrap(), 2);
+        assert!(migrations.pending().unwrap().is_empty());
+        let space = Space::find(&space_name).unwrap();
+        assert_eq!(space.len().unwrap(), 1);
+
+        // Applying again is a noop.
+        assert!(migrations.apply().unwrap().is_empty());
+        assert_eq!(calls.get(), 1);
+
+        // New migrations are applied on top of the old ones.
+        let migrations = migrations.add(3, "delete data", {
+            let space = space.clone();
+            move || {
+                space.delete(&(1,))?;
+                Ok(())
+            }
+        });
+        assert_eq!(migrations.apply().unwrap(), [3]);
+        assert_eq!(space.len().unwrap(), 0);
+
+        space.drop().unwrap();
+        cleanup(&migrations);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn failed_migration_is_rolled_back() {
+        let space_name = crate::temp_space_name!();
+        let migrations = Migrations::new(&space_name)
+            .add(1, "create space", {
+                let space_name = space_name.clone();
+                move || {
+                    let space = Space::builder(&space_name).create()?;
+                    space.index_builder("pk").create()?;
+                    Ok(())
+                }
+            })
+            .add(2, "insert and fail", {
+                let space_name = space_name.clone();
+                move || {
+                    let space = Space::find(&space_name).unwrap();
+                    space.insert(&(1,))?;
+                    space.insert(&(1,))?;
+                    Ok(())
+                }
+            });
+
+        let err = migrations.apply().unwrap_err();
+        assert!(matches!(err, Error::Failed { version: 2, .. }), "{}", err);
+        assert_eq!(migrations.current_version().unwrap(), 1);
+        let space = Space::find(&space_name).unwrap();
+        assert_eq!(space.len().unwrap(), 0);
+
+        space.drop().unwrap();
+        cleanup(&migrations);
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn refuse_in_transaction() {
+        let migrations = Migrations::new(crate::temp_space_name!()).add(1, "noop", || Ok(()));
+        transaction::begin().unwrap();
+        let err = migrations.apply().unwrap_err();
+        transaction::rollback().unwrap();
+        assert!(matches!(err, Error::InTransaction(_)), "{}", err);
+        assert_eq!(migrations.current_version().unwrap(), 0);
+    }
+
+    #[crate::test(tarantool = "crate", should_panic)]
+    fn versions_must_increase() {
+        let _ = Migrations::new("bad")
+            .add(2, "second", || Ok(()))
+            .add(1, "first", || Ok(()));
+    }
+}
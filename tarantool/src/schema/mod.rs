@@ -1,6 +1,7 @@
 #[cfg(feature = "picodata")]
 pub mod function;
 pub mod index;
+pub mod migrations;
 pub mod sequence;
 pub mod space;
 
@@ -18,6 +19,32 @@ fn resolve_user_or_role(user: &str) -> Result<Option<u32>, Error> {
     })
 }
 
+fn resolve_func(name: &str) -> Result<Option<u32>, Error> {
+    let space_vfunc: Space = SystemSpace::VFunc.into();
+    let name_idx = space_vfunc.index("name").unwrap();
+    Ok(match name_idx.get(&(name,))? {
+        None => None,
+        Some(func_tuple) => Some(func_tuple.field::<u32>(0)?.unwrap()),
+    })
+}
+
+/// Check if the current user is allowed to alter the space or its indexes.
+///
+/// Tarantool also performs this check when `_space` or `_index` is modified,
+/// but with `picodata` feature it is done beforehand via the access control api.
+#[cfg_attr(not(feature = "picodata"), allow(unused_variables))]
+fn check_space_alter_access(space: &crate::space::RawMetadata) -> Result<(), Error> {
+    #[cfg(feature = "picodata")]
+    crate::access_control::box_access_check_ddl(
+        &space.name,
+        space.id,
+        space.user_id,
+        crate::access_control::SchemaObjectType::Space,
+        crate::access_control::PrivType::Alter,
+    )?;
+    Ok(())
+}
+
 /// Revoke all privileges associated with the given object.
 ///
 /// - `obj_type` - string representation of object's type. Can be one of the following: "space", "sequence" or "function".
@@ -6,12 +6,12 @@ use crate::session;
 use crate::set_error;
 use crate::space;
 use crate::space::space_id_temporary_min;
-use crate::space::{Metadata, SpaceCreateOptions};
-use crate::space::{Space, SpaceId, SpaceType, SystemSpace};
+use crate::space::{Field, Metadata, RawMetadata, SpaceAlterOptions, SpaceCreateOptions};
+use crate::space::{Space, SpaceId, SpaceType, SystemSpace, TupleForeignKey, UpdateOps};
 use crate::transaction;
 use crate::tuple::Tuple;
 use crate::unwrap_or;
-use crate::util::Value;
+use rmpv::Value;
 use std::collections::BTreeMap;
 
 /// Create a space.
@@ -77,19 +77,24 @@ pub fn create_space(name: &str, opts: &SpaceCreateOptions) -> Result<Space, Erro
         SpaceType::Normal => {}
     }
 
-    let format = opts
-        .format
-        .iter()
-        .flat_map(|f| f.iter())
-        .map(|f| {
-            IntoIterator::into_iter([
-                ("name".into(), Value::Str(f.name.as_str().into())),
-                ("type".into(), Value::Str(f.field_type.as_str().into())),
-                ("is_nullable".into(), Value::Bool(f.is_nullable)),
-            ])
-            .collect()
-        })
-        .collect();
+    if let Some(defer_deletes) = opts.defer_deletes {
+        flags.insert("defer_deletes".into(), defer_deletes.into());
+    }
+
+    if !opts.constraint.is_empty() {
+        flags.insert(
+            "constraint".into(),
+            constraints_to_metadata(&opts.constraint)?,
+        );
+    }
+    if !opts.foreign_key.is_empty() {
+        flags.insert(
+            "foreign_key".into(),
+            foreign_keys_to_metadata(&opts.foreign_key, name)?,
+        );
+    }
+
+    let format = format_to_metadata(opts.format.as_deref().unwrap_or_default(), name)?;
 
     let nested_transaction = transaction::is_in_transaction();
     if !nested_transaction {
@@ -98,7 +103,7 @@ pub fn create_space(name: &str, opts: &SpaceCreateOptions) -> Result<Space, Erro
 
     let res = (|| -> Result<_, Error> {
         let sys_space = SystemSpace::Space.as_space();
-        sys_space.insert(&Metadata {
+        sys_space.insert(&RawMetadata {
             id,
             user_id,
             name: name.into(),
@@ -144,6 +149,150 @@ pub fn create_space(name: &str, opts: &SpaceCreateOptions) -> Result<Space, Erro
     Ok(space)
 }
 
+/// Converts the space format to the representation stored in `_space`.
+///
+/// `space_name` is the name of the space the format belongs to, it's used to
+/// detect foreign keys referencing the same space.
+fn format_to_metadata(
+    format: &[Field],
+    space_name: &str,
+) -> Result<Vec<BTreeMap<String, Value>>, Error> {
+    let mut res = Vec::with_capacity(format.len());
+    for f in format {
+        let mut field = BTreeMap::new();
+        field.insert("name".into(), f.name.as_str().into());
+        field.insert("type".into(), f.field_type.as_str().into());
+        field.insert("is_nullable".into(), f.is_nullable.into());
+        if let Some(foreign_key) = &f.foreign_key {
+            let mut fkeys = vec![];
+            for (name, fkey) in foreign_key {
+                let mut def = vec![];
+                if let Some(id) = resolve_foreign_space(fkey.space.as_deref(), space_name)? {
+                    def.push(("space".into(), id.into()));
+                }
+                def.push(("field".into(), fkey.field.as_str().into()));
+                fkeys.push((name.as_str().into(), Value::Map(def)));
+            }
+            field.insert("foreign_key".into(), Value::Map(fkeys));
+        }
+        if let Some(constraint) = &f.constraint {
+            field.insert("constraint".into(), constraints_to_metadata(constraint)?);
+        }
+        if let Some(default) = &f.default {
+            field.insert("default".into(), default.clone());
+        }
+        res.push(field);
+    }
+    Ok(res)
+}
+
+/// Converts the check constraints to the representation stored in `_space`,
+/// i.e. a map from constraint name to the function id.
+fn constraints_to_metadata(constraint: &BTreeMap<String, String>) -> Result<Value, Error> {
+    let mut res = vec![];
+    for (name, func) in constraint {
+        let Some(func_id) = schema::resolve_func(func)? else {
+            set_error!(
+                TarantoolErrorCode::NoSuchFunction,
+                "Function '{}' does not exist",
+                func
+            );
+            return Err(TarantoolError::last().into());
+        };
+        res.push((name.as_str().into(), func_id.into()));
+    }
+    Ok(Value::Map(res))
+}
+
+/// Converts the tuple foreign keys to the representation stored in `_space`.
+fn foreign_keys_to_metadata(
+    foreign_key: &BTreeMap<String, TupleForeignKey>,
+    space_name: &str,
+) -> Result<Value, Error> {
+    let mut res = vec![];
+    for (name, fkey) in foreign_key {
+        let mut def = vec![];
+        if let Some(id) = resolve_foreign_space(fkey.space.as_deref(), space_name)? {
+            def.push(("space".into(), id.into()));
+        }
+        let fields = fkey
+            .fields
+            .iter()
+            .map(|(local, foreign)| (local.as_str().into(), foreign.as_str().into()))
+            .collect();
+        def.push(("field".into(), Value::Map(fields)));
+        res.push((name.as_str().into(), Value::Map(def)));
+    }
+    Ok(Value::Map(res))
+}
+
+/// Returns the id of the space referenced by a foreign key or `None` if the
+/// foreign key references the space `space_name` itself, in which case the
+/// space id is omitted in `_space`.
+fn resolve_foreign_space(space: Option<&str>, space_name: &str) -> Result<Option<SpaceId>, Error> {
+    let Some(space) = space.filter(|&space| space != space_name) else {
+        return Ok(None);
+    };
+    let Some(space) = Space::find(space) else {
+        set_error!(
+            TarantoolErrorCode::NoSuchSpace,
+            "Space '{}' does not exist",
+            space
+        );
+        return Err(TarantoolError::last().into());
+    };
+    Ok(Some(space.id()))
+}
+
+/// Alter a space.
+/// (for details see [space_object:alter()](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/alter/)).
+///
+/// - `space_id` - ID of existing space.
+/// - `opts` - see SpaceAlterOptions struct, only the options which are set
+///   are changed.
+///
+/// The space is altered by updating its tuple in `_space`, so tarantool
+/// validates the new definition the same way as in `box.space.x:alter()`.
+pub fn alter_space(space_id: SpaceId, opts: &SpaceAlterOptions) -> Result<(), Error> {
+    let sys_space = SystemSpace::Space.as_space();
+    let Some(tuple) = sys_space.get(&[space_id])? else {
+        set_error!(TarantoolErrorCode::NoSuchSpace, "#{}", space_id);
+        return Err(TarantoolError::last().into());
+    };
+    // `Metadata` can't represent all the flags and formats, e.g. constraints.
+    let meta: RawMetadata = tuple.decode()?;
+    schema::check_space_alter_access(&meta)?;
+
+    let mut ops = UpdateOps::new();
+    if let Some(name) = &opts.name {
+        if let Some(space) = Space::find(name) {
+            if space.id() != space_id {
+                set_error!(TarantoolErrorCode::SpaceExists, "{}", name);
+                return Err(TarantoolError::last().into());
+            }
+        }
+        ops.assign("name", name)?;
+    }
+    if let Some(field_count) = opts.field_count {
+        ops.assign("field_count", field_count)?;
+    }
+    if let Some(is_sync) = opts.is_sync {
+        ops.assign("flags.is_sync", is_sync)?;
+    }
+    if let Some(defer_deletes) = opts.defer_deletes {
+        ops.assign("flags.defer_deletes", defer_deletes)?;
+    }
+    if let Some(format) = &opts.format {
+        let space_name = opts.name.as_deref().unwrap_or(&meta.name);
+        ops.assign("format", format_to_metadata(format, space_name)?)?;
+    }
+
+    if !ops.as_slice().is_empty() {
+        sys_space.update(&[space_id], ops)?;
+    }
+    Ok(())
+}
+
 #[deprecated = "use `tarantool::space::Metadata` instead"]
 pub type SpaceMetadata<'a> = Metadata<'a>;
 
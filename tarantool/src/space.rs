@@ -8,7 +8,8 @@
 //! - [C API reference: Module box](https://www.tarantool.io/en/doc/latest/dev_guide/reference_capi/box/)
 use crate::error::{Error, TarantoolError};
 use crate::ffi::tarantool as ffi;
-use crate::index::{Index, IndexIterator, IteratorType};
+use crate::index::{Index, IndexIterator, IndexOptions, IteratorType, Scan};
+use crate::trigger::SpaceTrigger;
 use crate::tuple::{Encode, ToTupleBuffer, Tuple, TupleBuffer};
 use crate::unwrap_or;
 use crate::util::Value;
@@ -22,6 +23,10 @@ use std::collections::HashMap;
 use std::ops::Range;
 use std::os::raw::c_char;
 
+mod dump;
+mod typed;
+pub use typed::{TypedIndex, TypedIter, TypedSpace};
+
 /// End of the reserved range of system spaces.
 pub const SYSTEM_ID_MAX: SpaceId = 511;
 
@@ -181,6 +186,27 @@ pub struct SpaceCreateOptions {
     pub user: Option<String>,
     pub space_type: SpaceType,
     pub format: Option<Vec<Field>>,
+    /// Tuple check constraints: constraint name to the name of the function
+    /// from `_func`.
+    pub constraint: BTreeMap<String, String>,
+    /// Tuple foreign keys by name.
+    pub foreign_key: BTreeMap<String, TupleForeignKey>,
+    /// Defer generation of DELETE statements for secondary indexes until the
+    /// primary index compaction. Only supported by vinyl engine.
+    pub defer_deletes: Option<bool>,
+}
+
+/// Options for altering an existing space, used by [`Space::alter_with`].
+/// Only the options which are set are changed.
+/// (for details see [space_object:alter()](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/alter/)).
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct SpaceAlterOptions {
+    pub name: Option<String>,
+    pub format: Option<Vec<Field>>,
+    pub field_count: Option<u32>,
+    pub is_sync: Option<bool>,
+    /// Only supported by vinyl engine.
+    pub defer_deletes: Option<bool>,
 }
 
 /// Possible values for the [`SpaceCreateOptions::space_type`] field.
@@ -226,15 +252,29 @@ pub enum SpaceType {
 #[deprecated = "Use `space::Field` instead"]
 pub type SpaceFieldFormat = Field;
 
-#[derive(Clone, Debug, Serialize, Deserialize, msgpack::Encode, msgpack::Decode, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, msgpack::Encode, msgpack::Decode, PartialEq)]
 #[encode(tarantool = "crate", as_map)]
 pub struct Field {
     pub name: String, // TODO(gmoshkin): &str
     #[serde(alias = "type")]
     pub field_type: FieldType,
     pub is_nullable: bool,
+    /// Foreign keys of the field by name, see [`Field::foreign_key`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreign_key: Option<BTreeMap<String, ForeignKey>>,
+    /// Check constraints of the field: constraint name to the name of the
+    /// function from `_func`, see [`Field::constraint`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constraint: Option<BTreeMap<String, String>>,
+    /// Default value of the field, see [`Field::default`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<rmpv::Value>,
 }
 
+// `rmpv::Value` isn't `Eq` because of floats, but a default value is never a
+// NaN in practice.
+impl Eq for Field {}
+
 impl<S> From<(S, FieldType, IsNullable)> for Field
 where
     String: From<S>,
@@ -248,6 +288,9 @@ where
             name,
             field_type,
             is_nullable,
+            foreign_key: None,
+            constraint: None,
+            default: None,
         }
     }
 }
@@ -265,6 +308,9 @@ where
             name,
             field_type,
             is_nullable,
+            foreign_key: None,
+            constraint: None,
+            default: None,
         }
     }
 }
@@ -282,6 +328,9 @@ macro_rules! define_constructors {
                     name: name.into(),
                     field_type: $type,
                     is_nullable: false,
+                    foreign_key: None,
+                    constraint: None,
+                    default: None,
                 }
             }
         )+
@@ -299,6 +348,9 @@ impl Field {
             name: name.to_string(),
             field_type: ft,
             is_nullable: false,
+            foreign_key: None,
+            constraint: None,
+            default: None,
         }
     }
 
@@ -315,6 +367,46 @@ impl Field {
         self
     }
 
+    /// Add a foreign key named `name` to the field. A tuple can only be
+    /// inserted if the referenced space contains a tuple with the same value
+    /// in the referenced field.
+    /// ```no_run
+    /// use tarantool::space::{Field, ForeignKey};
+    /// let f = Field::unsigned("author_id").foreign_key("author", ForeignKey::new("authors", "id"));
+    /// ```
+    #[inline]
+    pub fn foreign_key(mut self, name: impl Into<String>, foreign_key: ForeignKey) -> Self {
+        self.foreign_key
+            .get_or_insert_with(Default::default)
+            .insert(name.into(), foreign_key);
+        self
+    }
+
+    /// Add a check constraint named `name` to the field. `func` is the name of
+    /// a persistent function from `_func` which is called with the field's
+    /// value and the constraint name whenever a tuple is inserted or replaced.
+    /// The function must return `true` for the value to be accepted.
+    #[inline]
+    pub fn constraint(mut self, name: impl Into<String>, func: impl Into<String>) -> Self {
+        self.constraint
+            .get_or_insert_with(Default::default)
+            .insert(name.into(), func.into());
+        self
+    }
+
+    /// Set the default value of the field, which is used if the field is
+    /// absent or is `null` in the inserted tuple. Requires tarantool 3.0 or
+    /// newer.
+    /// ```no_run
+    /// use tarantool::space::Field;
+    /// let f = Field::string("status").default("active");
+    /// ```
+    #[inline]
+    pub fn default(mut self, value: impl Into<rmpv::Value>) -> Self {
+        self.default = Some(value.into());
+        self
+    }
+
     define_constructors! {
         any(FieldType::Any)
         unsigned(FieldType::Unsigned)
@@ -334,6 +426,82 @@ impl Field {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// ForeignKey
+////////////////////////////////////////////////////////////////////////////////
+
+/// Foreign key of a single field, see [`Field::foreign_key`].
+/// (for details see [Constraints](https://www.tarantool.io/en/doc/latest/concepts/data_model/value_store/#constraints)).
+#[derive(Clone, Debug, Serialize, Deserialize, msgpack::Encode, msgpack::Decode, PartialEq, Eq)]
+#[encode(tarantool = "crate", as_map)]
+pub struct ForeignKey {
+    /// Name of the referenced space. `None` means the field references
+    /// another field of the same space.
+    pub space: Option<String>,
+    /// Name of the referenced field.
+    pub field: String,
+}
+
+impl ForeignKey {
+    /// Reference the field `field` of the space `space`.
+    #[inline(always)]
+    pub fn new(space: impl Into<String>, field: impl Into<String>) -> Self {
+        Self {
+            space: Some(space.into()),
+            field: field.into(),
+        }
+    }
+
+    /// Reference the field `field` of the same space.
+    #[inline(always)]
+    pub fn local(field: impl Into<String>) -> Self {
+        Self {
+            space: None,
+            field: field.into(),
+        }
+    }
+}
+
+/// Foreign key of a whole tuple, which may consist of several fields, see
+/// [`Builder::foreign_key`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TupleForeignKey {
+    /// Name of the referenced space. `None` means the tuple references
+    /// another tuple of the same space.
+    pub space: Option<String>,
+    /// Pairs of names of the local and the referenced fields.
+    pub fields: Vec<(String, String)>,
+}
+
+impl TupleForeignKey {
+    /// Reference the space `space`, use [`TupleForeignKey::field`] to add
+    /// the referenced fields.
+    #[inline(always)]
+    pub fn new(space: impl Into<String>) -> Self {
+        Self {
+            space: Some(space.into()),
+            fields: vec![],
+        }
+    }
+
+    /// Reference the same space, use [`TupleForeignKey::field`] to add
+    /// the referenced fields.
+    #[inline(always)]
+    pub fn local() -> Self {
+        Self {
+            space: None,
+            fields: vec![],
+        }
+    }
+
+    /// Add a pair of the local field `local` referencing the field `foreign`.
+    #[inline(always)]
+    pub fn field(mut self, local: impl Into<String>, foreign: impl Into<String>) -> Self {
+        self.fields.push((local.into(), foreign.into()));
+        self
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // FieldType
 ////////////////////////////////////////////////////////////////////////////////
@@ -381,6 +549,77 @@ impl IsNullable {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// SpaceFormat
+////////////////////////////////////////////////////////////////////////////////
+
+/// Derive macro for [`SpaceFormat`] trait.
+///
+/// The space format is generated from the struct's named fields in the order
+/// of declaration. Field types are inferred from the rust types:
+/// - integers, `f32`/`f64`, `bool` and strings map to the corresponding
+///   numeric, boolean and string field types,
+/// - `Decimal`, `Uuid` and `Datetime` map to [`FieldType::Decimal`],
+///   [`FieldType::Uuid`] and [`FieldType::Datetime`],
+/// - sequences, tuples and maps map to [`FieldType::Array`] and [`FieldType::Map`],
+/// - `Option<T>` makes the field nullable with the type inferred from `T`,
+/// - anything else maps to [`FieldType::Any`].
+///
+/// Supported field attributes:
+/// - `#[space_format(rename = "name")]` sets the name of the field in the format,
+/// - `#[space_format(field_type = "unsigned")]` overrides the inferred type,
+/// - `#[space_format(is_nullable = true)]` overrides the inferred nullability.
+///
+/// Indexes are declared with struct attributes
+/// `#[space_format(index(name = "...", parts(field, ...)))]`, the first one
+/// being the primary key. An index also accepts `unique = bool` and
+/// `index_type = "hash"` options. Parts refer to the rust field names, so a
+/// typo is a compile error.
+///
+/// Use `#[space_format(tarantool = "path")]` to specify the path to the
+/// tarantool crate if it's renamed.
+///
+/// # Example
+/// ```no_run
+/// use tarantool::space::{Space, SpaceFormat};
+///
+/// #[derive(SpaceFormat)]
+/// #[space_format(index(name = "pk", parts(id)))]
+/// #[space_format(index(name = "by_name", parts(name, age), unique = false))]
+/// struct User {
+///     id: u64,
+///     name: String,
+///     age: Option<u32>,
+/// }
+///
+/// let space = Space::builder("users")
+///     .format(User::format())
+///     .create()
+///     .unwrap();
+/// User::create_indexes(&space).unwrap();
+/// ```
+pub use tarantool_proc::SpaceFormat;
+
+/// A type which describes the format and the indexes of a space it's stored in.
+///
+/// Usually this trait is derived, see [`SpaceFormat`](derive@SpaceFormat).
+pub trait SpaceFormat {
+    /// Returns the format of the space to pass to [`Builder::format`].
+    fn format() -> Vec<Field>;
+
+    /// Returns the index definitions as pairs of index name and options. The
+    /// first one is the primary key.
+    fn indexes() -> Vec<(&'static str, IndexOptions)>;
+
+    /// Creates all the indexes returned by [`Self::indexes`] in the `space`.
+    fn create_indexes(space: &Space) -> Result<Vec<Index>, Error> {
+        Self::indexes()
+            .into_iter()
+            .map(|(name, opts)| crate::schema::index::create_index(space.id(), name, &opts))
+            .collect()
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // ...
 ////////////////////////////////////////////////////////////////////////////////
@@ -516,6 +755,67 @@ impl Space {
         crate::schema::space::drop_space(self.id)
     }
 
+    /// Return a builder for altering the space.
+    ///
+    /// ```no_run
+    /// use tarantool::space::{Space, Field};
+    ///
+    /// let space = Space::find("users").unwrap();
+    /// space.alter()
+    ///     .name("people")
+    ///     .format([Field::unsigned("id"), Field::string("name")])
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    #[inline(always)]
+    pub fn alter(&self) -> AlterBuilder {
+        AlterBuilder::new(self.id)
+    }
+
+    /// Alter the space.
+    /// (for details see [space_object:alter()](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/alter/)).
+    ///
+    /// - `opts` - see [`SpaceAlterOptions`] struct.
+    #[inline(always)]
+    pub fn alter_with(&self, opts: &SpaceAlterOptions) -> Result<(), Error> {
+        crate::schema::space::alter_space(self.id, opts)
+    }
+
+    /// Set a trigger which is called after a tuple is inserted, replaced,
+    /// updated or deleted in the space. The callback receives the old and the
+    /// new tuples.
+    ///
+    /// The trigger is unregistered when the returned handle is dropped.
+    ///
+    /// See [`trigger::on_replace`] for details.
+    ///
+    /// [`trigger::on_replace`]: crate::trigger::on_replace
+    #[inline(always)]
+    pub fn on_replace<F>(&self, f: F) -> Result<SpaceTrigger, Error>
+    where
+        F: FnMut(Option<Tuple>, Option<Tuple>) -> Result<(), Error> + 'static,
+    {
+        crate::trigger::on_replace(self.id, f)
+    }
+
+    /// Set a trigger which is called before a tuple is inserted, replaced,
+    /// updated or deleted in the space. The callback receives the old and the
+    /// new tuples and returns the tuple which will actually be stored, or an
+    /// error to reject the operation.
+    ///
+    /// The trigger is unregistered when the returned handle is dropped.
+    ///
+    /// See [`trigger::before_replace`] for details.
+    ///
+    /// [`trigger::before_replace`]: crate::trigger::before_replace
+    #[inline(always)]
+    pub fn before_replace<F>(&self, f: F) -> Result<SpaceTrigger, Error>
+    where
+        F: FnMut(Option<Tuple>, Option<Tuple>) -> Result<Option<Tuple>, Error> + 'static,
+    {
+        crate::trigger::before_replace(self.id, f)
+    }
+
     /// Find space by name.
     ///
     /// This function performs SELECT request to `_vspace` system space.
@@ -642,6 +942,13 @@ impl Space {
         Index::new(self.id, 0)
     }
 
+    /// Return a builder for a fiber-friendly scan over the space in primary
+    /// key order, see [`Index::scan`].
+    #[inline(always)]
+    pub fn scan(&self) -> Scan {
+        self.primary_key().scan()
+    }
+
     /// Insert a `value` into a space.
     ///
     /// Returns a new tuple.
@@ -750,6 +1057,56 @@ impl Space {
         self.primary_key().bsize()
     }
 
+    /// Return the statistics of the space: number of tuples, memory usage,
+    /// etc. See [`SpaceStat`] for details.
+    ///
+    /// This function uses lua internally, so it's relatively expensive and is
+    /// intended for monitoring. Use [`Index::stat`] for the statistics of the
+    /// space's indexes.
+    ///
+    /// [`SpaceStat`]: crate::stat::SpaceStat
+    #[inline(always)]
+    pub fn stat(&self) -> Result<crate::stat::SpaceStat, Error> {
+        crate::stat::space_stat(self.id)
+    }
+
+    /// Write the format, the indexes and all of the tuples of the space into
+    /// `writer` as a self-describing msgpack stream, which can be loaded back
+    /// with [`Space::restore_from`]. Returns the underlying writer.
+    ///
+    /// The data is written as a single msgpack array using
+    /// [`msgpack::ArrayWriter`]: the first element is a header map with the
+    /// space and index definitions from `_space` and `_index`, followed by
+    /// the tuples in primary key order.
+    ///
+    /// For memtx spaces a read view is used when it's available (the
+    /// `picodata` feature), so the dump is consistent even if the fiber yields
+    /// while writing. For vinyl spaces the tuples are read within a
+    /// transaction, unless there's already an active one.
+    #[inline(always)]
+    pub fn dump_to<W>(&self, writer: W) -> Result<W, Error>
+    where
+        W: std::io::Write + std::io::Seek,
+    {
+        dump::dump_to(self, writer)
+    }
+
+    /// Load a space from a msgpack stream written by [`Space::dump_to`].
+    ///
+    /// If a space with the dumped name doesn't exist, it is created using
+    /// [`Space::builder`] with the dumped engine, type and format (without
+    /// foreign keys and constraints) and the dumped indexes (type, uniqueness
+    /// and parts only). Otherwise the tuples are loaded into the existing
+    /// space, replacing the ones with the same primary key.
+    ///
+    /// Tuples are inserted in batches of 1000, each in a separate transaction,
+    /// so this function must not be called within a transaction. If an error
+    /// happens, the batches which were already committed are not rolled back.
+    #[inline(always)]
+    pub fn restore_from(reader: impl std::io::Read) -> Result<Self, Error> {
+        dump::restore_from(reader)
+    }
+
     /// Search for a tuple in the given space.
     #[inline(always)]
     pub fn get<K>(&self, key: &K) -> Result<Option<Tuple>, Error>
@@ -947,6 +1304,21 @@ pub struct Metadata<'a> {
 }
 impl Encode for Metadata<'_> {}
 
+/// Same as [`Metadata`] but the flags and the format are kept as raw msgpack
+/// values, so that any space definition can be represented, e.g. one with
+/// constraints, foreign keys or field defaults.
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct RawMetadata {
+    pub id: u32,
+    pub user_id: u32,
+    pub name: String,
+    pub engine: SpaceEngineType,
+    pub field_count: u32,
+    pub flags: BTreeMap<String, rmpv::Value>,
+    pub format: Vec<BTreeMap<String, rmpv::Value>>,
+}
+impl Encode for RawMetadata {}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Builder
 ////////////////////////////////////////////////////////////////////////////////
@@ -985,6 +1357,7 @@ impl<'a> Builder<'a> {
         field_count(field_count: u32)
         user(user: String)
         space_type(space_type: SpaceType)
+        defer_deletes(defer_deletes: bool)
     }
 
     #[deprecated = "use Builder::space_type instead"]
@@ -1058,6 +1431,43 @@ impl<'a> Builder<'a> {
         self
     }
 
+    /// Add a check constraint named `name` on the whole tuple. `func` is the
+    /// name of a persistent function from `_func` which is called with the
+    /// tuple and the constraint name whenever a tuple is inserted or replaced.
+    /// The function must return `true` for the tuple to be accepted.
+    ///
+    /// Use [`Field::constraint`] to add a constraint on a single field.
+    #[inline(always)]
+    pub fn constraint(mut self, name: impl Into<String>, func: impl Into<String>) -> Self {
+        self.opts.constraint.insert(name.into(), func.into());
+        self
+    }
+
+    /// Add a foreign key named `name` on the whole tuple.
+    ///
+    /// ```no_run
+    /// use tarantool::space::{Space, Field, TupleForeignKey};
+    ///
+    /// let space = Space::builder("books")
+    ///     .field(Field::unsigned("id"))
+    ///     .field(Field::string("author_name"))
+    ///     .field(Field::string("author_surname"))
+    ///     .foreign_key(
+    ///         "author",
+    ///         TupleForeignKey::new("authors")
+    ///             .field("author_name", "name")
+    ///             .field("author_surname", "surname"),
+    ///     )
+    ///     .create();
+    /// ```
+    ///
+    /// Use [`Field::foreign_key`] to add a foreign key on a single field.
+    #[inline(always)]
+    pub fn foreign_key(mut self, name: impl Into<String>, foreign_key: TupleForeignKey) -> Self {
+        self.opts.foreign_key.insert(name.into(), foreign_key);
+        self
+    }
+
     /// Create a space with the current configuration.
     ///
     /// **NOTE:** This function will initiate a transaction if there's isn't an
@@ -1077,6 +1487,79 @@ impl<'a> Builder<'a> {
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////
+// AlterBuilder
+////////////////////////////////////////////////////////////////////////////////
+
+/// A builder for altering an existing space, see [`Space::alter`].
+pub struct AlterBuilder {
+    space_id: SpaceId,
+    opts: SpaceAlterOptions,
+}
+
+impl AlterBuilder {
+    #[inline(always)]
+    pub fn new(space_id: SpaceId) -> Self {
+        Self {
+            space_id,
+            opts: Default::default(),
+        }
+    }
+
+    define_setters! {
+        field_count(field_count: u32)
+        is_sync(is_sync: bool)
+        defer_deletes(defer_deletes: bool)
+    }
+
+    /// Rename the space.
+    #[inline(always)]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.opts.name = Some(name.into());
+        self
+    }
+
+    /// Add a field to the space's new format.
+    ///
+    /// Note that the new format replaces the old one completely, so all the
+    /// fields must be specified, not only the added ones.
+    #[inline(always)]
+    pub fn field(mut self, field: impl Into<Field>) -> Self {
+        self.opts
+            .format
+            .get_or_insert_with(|| Vec::with_capacity(16))
+            .push(field.into());
+        self
+    }
+
+    /// Add fields to the space's new format.
+    ///
+    /// Note that the new format replaces the old one completely, so all the
+    /// fields must be specified, not only the added ones.
+    #[inline]
+    pub fn format(mut self, format: impl IntoIterator<Item = impl Into<Field>>) -> Self {
+        let iter = format.into_iter();
+        let (size, _) = iter.size_hint();
+        self.opts
+            .format
+            .get_or_insert_with(|| Vec::with_capacity(size))
+            .extend(iter.map(Into::into));
+        self
+    }
+
+    /// Alter the space with the current configuration.
+    #[inline(always)]
+    pub fn apply(self) -> crate::Result<()> {
+        crate::schema::space::alter_space(self.space_id, &self.opts)
+    }
+
+    /// Destructure the builder struct into a tuple of space id and alter options.
+    #[inline(always)]
+    pub fn into_parts(self) -> (SpaceId, SpaceAlterOptions) {
+        (self.space_id, self.opts)
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // UpdateOps
 ////////////////////////////////////////////////////////////////////////////////
@@ -1519,4 +2002,342 @@ mod test {
             space.drop().unwrap();
         }
     }
+
+    #[crate::test(tarantool = "crate")]
+    fn derive_space_format() {
+        use crate::datetime::Datetime;
+        #[cfg(any(feature = "picodata", feature = "standalone_decimal"))]
+        use crate::decimal::Decimal;
+        use crate::index::{IndexType, Part};
+        use crate::uuid::Uuid;
+
+        #[allow(dead_code)]
+        #[derive(SpaceFormat)]
+        #[space_format(tarantool = "crate")]
+        #[space_format(index(name = "pk", parts(id)))]
+        #[space_format(index(name = "by_name", parts(name, nick), unique = false))]
+        #[space_format(index(name = "by_uuid", parts(uuid), index_type = "hash"))]
+        struct User {
+            id: u64,
+            name: String,
+            nick: Option<String>,
+            #[cfg(any(feature = "picodata", feature = "standalone_decimal"))]
+            balance: Decimal,
+            uuid: Uuid,
+            created_at: Datetime,
+            tags: Vec<String>,
+            #[space_format(rename = "type")]
+            r#type: i32,
+            #[space_format(field_type = "scalar", is_nullable = true)]
+            extra: crate::util::Value<'static>,
+        }
+
+        assert_eq!(
+            User::format(),
+            [
+                Field::unsigned("id"),
+                Field::string("name"),
+                Field::string("nick").is_nullable(true),
+                #[cfg(any(feature = "picodata", feature = "standalone_decimal"))]
+                Field::decimal("balance"),
+                Field::uuid("uuid"),
+                Field::datetime("created_at"),
+                Field::array("tags"),
+                Field::integer("type"),
+                Field::scalar("extra").is_nullable(true),
+            ]
+        );
+
+        let indexes = User::indexes();
+        assert_eq!(indexes.len(), 3);
+        assert_eq!(indexes[0].0, "pk");
+        assert_eq!(indexes[0].1.parts, Some(vec![Part::field("id")]));
+        assert_eq!(indexes[1].0, "by_name");
+        assert_eq!(indexes[1].1.unique, Some(false));
+        assert_eq!(
+            indexes[1].1.parts,
+            Some(vec![
+                Part::field("name"),
+                Part::field("nick").is_nullable(true)
+            ])
+        );
+        assert_eq!(indexes[2].1.r#type, Some(IndexType::Hash));
+
+        let space = Space::builder(&crate::temp_space_name!())
+            .format(User::format())
+            .create()
+            .unwrap();
+        let created = User::create_indexes(&space).unwrap();
+        assert_eq!(created.len(), 3);
+        assert_eq!(space.index("by_name").unwrap(), created[1]);
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn alter() {
+        let space = Space::builder(&crate::temp_space_name!())
+            .field(("id", FieldType::Unsigned))
+            .create()
+            .unwrap();
+        space.index_builder("pk").create().unwrap();
+        space.insert(&(1, "foo")).unwrap();
+
+        let other = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        let e = space
+            .alter()
+            .name(other.meta().unwrap().name)
+            .apply()
+            .unwrap_err();
+        assert!(e.to_string().contains("SpaceExists"), "{}", e);
+
+        // Existing tuples must match the new format.
+        let e = space
+            .alter()
+            .field(Field::unsigned("id"))
+            .field(Field::unsigned("value"))
+            .apply()
+            .unwrap_err();
+        assert!(e.to_string().contains("FieldType"), "{}", e);
+
+        let new_name = crate::temp_space_name!();
+        space
+            .alter()
+            .name(&new_name)
+            .format([Field::unsigned("id"), Field::string("value")])
+            .field(Field::string("extra").is_nullable(true))
+            .is_sync(true)
+            .apply()
+            .unwrap();
+        assert_eq!(Space::find(&new_name), Some(space.clone()));
+        let meta = space.meta().unwrap();
+        assert_eq!(meta.name, new_name);
+        assert_eq!(meta.flags.get("is_sync"), Some(&Value::Bool(true)));
+        assert_eq!(meta.format.len(), 3);
+        assert_eq!(meta.format[1].get("name"), Some(&Value::from("value")));
+
+        space.alter().is_sync(false).field_count(2).apply().unwrap();
+        let meta = space.meta().unwrap();
+        assert_eq!(meta.flags.get("is_sync"), Some(&Value::Bool(false)));
+        assert_eq!(meta.field_count, 2);
+        assert!(space.insert(&(2, "bar", "baz")).is_err());
+
+        other.drop().unwrap();
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn on_replace() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let log = Rc::new(RefCell::new(vec![]));
+        let trigger = space
+            .on_replace({
+                let log = log.clone();
+                move |old, new| {
+                    let old: Option<(u32, String)> = old.map(|t| t.decode().unwrap());
+                    let new: Option<(u32, String)> = new.map(|t| t.decode().unwrap());
+                    log.borrow_mut().push((old, new));
+                    Ok(())
+                }
+            })
+            .unwrap();
+        assert_eq!(trigger.space_id(), space.id());
+
+        space.insert(&(1, "foo")).unwrap();
+        space.replace(&(1, "bar")).unwrap();
+        space.delete(&(1,)).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [
+                (None, Some((1, "foo".into()))),
+                (Some((1, "foo".into())), Some((1, "bar".into()))),
+                (Some((1, "bar".into())), None),
+            ]
+        );
+
+        // An error from the trigger fails the operation.
+        let failing = space
+            .on_replace(|_, _| Err(Error::other("not today")))
+            .unwrap();
+        let e = space.insert(&(2, "foo")).unwrap_err();
+        assert!(e.to_string().contains("not today"), "{}", e);
+        assert_eq!(space.len().unwrap(), 0);
+        drop(failing);
+
+        // A panic is reported as an error as well.
+        let panicking = space.on_replace(|_, _| panic!("oh no")).unwrap();
+        let e = space.insert(&(2, "foo")).unwrap_err();
+        assert!(
+            e.to_string().contains("on_replace trigger panicked: oh no"),
+            "{}",
+            e
+        );
+        drop(panicking);
+
+        // Dropping the handle unregisters the trigger.
+        drop(trigger);
+        log.borrow_mut().clear();
+        space.insert(&(3, "foo")).unwrap();
+        assert!(log.borrow().is_empty());
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn before_replace() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+        space.insert(&(1, "foo")).unwrap();
+
+        let trigger = space
+            .before_replace(|old, new| {
+                let Some(new) = new else {
+                    // Deletes are forbidden.
+                    return Err(Error::other("can't delete"));
+                };
+                let (id, value): (u32, String) = new.decode()?;
+                match value.as_str() {
+                    // Silently ignore the operation.
+                    "skip" => Ok(old),
+                    "delete" => Ok(None),
+                    _ => Ok(Some(Tuple::new(&(id, value.to_uppercase()))?)),
+                }
+            })
+            .unwrap();
+
+        space.insert(&(2, "bar")).unwrap();
+        let t: (u32, String) = space.get(&(2,)).unwrap().unwrap().decode().unwrap();
+        assert_eq!(t, (2, "BAR".into()));
+
+        space.replace(&(1, "skip")).unwrap();
+        let t: (u32, String) = space.get(&(1,)).unwrap().unwrap().decode().unwrap();
+        assert_eq!(t, (1, "foo".into()));
+
+        let e = space.delete(&(1,)).unwrap_err();
+        assert!(e.to_string().contains("can't delete"), "{}", e);
+        assert!(space.get(&(1,)).unwrap().is_some());
+
+        space.replace(&(1, "delete")).unwrap();
+        assert!(space.get(&(1,)).unwrap().is_none());
+
+        drop(trigger);
+        space.insert(&(3, "baz")).unwrap();
+        let t: (u32, String) = space.get(&(3,)).unwrap().unwrap().decode().unwrap();
+        assert_eq!(t, (3, "baz".into()));
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn constraints_and_foreign_keys() {
+        let lua = crate::lua_state();
+        let func_name = crate::temp_space_name!();
+        lua.exec_with(
+            "box.schema.func.create(..., {
+                language = 'LUA',
+                is_deterministic = true,
+                body = 'function(v) return type(v) ~= \"number\" or v > 0 end',
+            })",
+            func_name.as_str(),
+        )
+        .unwrap();
+
+        let authors = Space::builder(&crate::temp_space_name!())
+            .field(Field::unsigned("id"))
+            .field(Field::string("name"))
+            .create()
+            .unwrap();
+        authors.index_builder("pk").create().unwrap();
+        authors.insert(&(1, "Pushkin")).unwrap();
+
+        let books_name = crate::temp_space_name!();
+        let books = Space::builder(&books_name)
+            .field(Field::unsigned("id").constraint("positive", &func_name))
+            .field(Field::unsigned("author_id").foreign_key(
+                "author",
+                ForeignKey::new(authors.meta().unwrap().name, "id"),
+            ))
+            .field(
+                Field::unsigned("prequel_id")
+                    .is_nullable(true)
+                    .foreign_key("prequel", ForeignKey::local("id")),
+            )
+            .constraint("also_positive", &func_name)
+            .create()
+            .unwrap();
+        books.index_builder("pk").create().unwrap();
+
+        let meta: RawMetadata = SystemSpace::Space
+            .as_space()
+            .get(&[books.id()])
+            .unwrap()
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert!(meta.flags.contains_key("constraint"));
+        assert!(meta.format[0]["constraint"].is_map());
+        assert!(meta.format[1]["foreign_key"].is_map());
+
+        books.insert(&(1, 1, ())).unwrap();
+        books.insert(&(2, 1, 1)).unwrap();
+
+        // No such author.
+        let e = books.insert(&(3, 2, ())).unwrap_err();
+        assert!(e.to_string().contains("author"), "{}", e);
+        // No such prequel.
+        let e = books.insert(&(3, 1, 42)).unwrap_err();
+        assert!(e.to_string().contains("prequel"), "{}", e);
+        // Constraint violated.
+        let e = books.insert(&(0, 1, ())).unwrap_err();
+        assert!(e.to_string().contains("positive"), "{}", e);
+
+        let e = Space::builder(&crate::temp_space_name!())
+            .field(Field::unsigned("id").constraint("check", "no_such_function"))
+            .create()
+            .unwrap_err();
+        assert!(e.to_string().contains("no_such_function"), "{}", e);
+
+        books.drop().unwrap();
+        authors.drop().unwrap();
+        lua.exec_with("box.schema.func.drop(...)", func_name.as_str())
+            .unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn field_default() {
+        let lua = crate::lua_state();
+        let supported: bool = lua
+            .eval("return tonumber(_TARANTOOL:match('^(%d+)')) >= 3")
+            .unwrap();
+        if !supported {
+            return;
+        }
+
+        let space = Space::builder(&crate::temp_space_name!())
+            .field(Field::unsigned("id"))
+            .field(Field::string("status").default("new"))
+            .field(Field::integer("delta").default(-1i64))
+            .create()
+            .unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        space.insert(&(1, (), ())).unwrap();
+        let t: (u32, String, i64) = space.get(&(1,)).unwrap().unwrap().decode().unwrap();
+        assert_eq!(t, (1, "new".into(), -1));
+
+        let meta: RawMetadata = SystemSpace::Space
+            .as_space()
+            .get(&[space.id()])
+            .unwrap()
+            .unwrap()
+            .decode()
+            .unwrap();
+        assert_eq!(meta.format[2].get("default"), Some(&rmpv::Value::from(-1)));
+
+        space.drop().unwrap();
+    }
 }
@@ -0,0 +1,303 @@
+//! Dumping a space into a msgpack stream and restoring it back, see
+//! [`Space::dump_to`] and [`Space::restore_from`].
+//!
+//! The dump is a single msgpack array. The first element is a header map
+//! describing the space, the rest of the elements are the tuples of the space
+//! in primary key order:
+//! ```text
+//! [
+//!     {
+//!         "version": 1,
+//!         "space": { <tuple from _space encoded as a map> },
+//!         "indexes": [ { <tuple from _index encoded as a map> }, ... ],
+//!     },
+//!     [ <tuple 1> ],
+//!     [ <tuple 2> ],
+//!     ...
+//! ]
+//! ```
+//!
+//! The space and index definitions are stored exactly as they are in `_space`
+//! and `_index`, so in particular index part field numbers are 0-based.
+
+use super::{Field, FieldType, RawMetadata, Space, SpaceEngineType, SpaceType, SystemSpace};
+use crate::error::{BoxError, Error, TarantoolErrorCode};
+use crate::index::{self, IteratorType};
+use crate::msgpack::ArrayWriter;
+use crate::transaction;
+use crate::tuple::TupleBuffer;
+use crate::util::{NumOrStr, Value};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, Write};
+
+/// Version of the dump format, stored in the header.
+const DUMP_VERSION: u32 = 1;
+
+/// Max number of tuples inserted in a single transaction by
+/// [`Space::restore_from`].
+const RESTORE_BATCH_SIZE: u32 = 1000;
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Header {
+    version: u32,
+    space: RawMetadata,
+    indexes: Vec<index::Metadata<'static>>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// dump
+////////////////////////////////////////////////////////////////////////////////
+
+pub(super) fn dump_to<W>(space: &Space, writer: W) -> Result<W, Error>
+where
+    W: Write + Seek,
+{
+    let sys_space = SystemSpace::Space.as_space();
+    let tuple = sys_space.get(&(space.id,))?.ok_or(Error::MetaNotFound)?;
+    let meta: RawMetadata = tuple.decode()?;
+
+    let sys_index = SystemSpace::Index.as_space();
+    let mut indexes = Vec::new();
+    for tuple in sys_index.select(IteratorType::Eq, &(space.id,))? {
+        indexes.push(tuple.decode::<index::Metadata<'static>>()?);
+    }
+
+    let engine = meta.engine;
+    let header = Header {
+        version: DUMP_VERSION,
+        space: meta,
+        indexes,
+    };
+
+    let mut writer = ArrayWriter::new(writer)?;
+    // Encode the header as a map, so that the dump is self-describing.
+    writer.push_raw(&rmp_serde::to_vec_named(&header)?)?;
+
+    #[cfg(feature = "picodata")]
+    if engine == SpaceEngineType::Memtx {
+        let rv = crate::read_view::ReadView::for_space_indexes(vec![(space.id, 0)])?;
+        if let Some(iter) = rv.iter_all(space.id, 0)? {
+            for data in iter {
+                writer.push_raw(data)?;
+            }
+        }
+        return writer.finish();
+    }
+
+    let mut dump_tuples = || -> Result<(), Error> {
+        for tuple in space.select(IteratorType::All, &())? {
+            writer.push_tuple(&tuple)?;
+        }
+        Ok(())
+    };
+    // Vinyl may yield during the iteration, so a transaction is needed for a
+    // consistent snapshot of the data.
+    if engine == SpaceEngineType::Vinyl && !transaction::is_in_transaction() {
+        transaction::transaction(dump_tuples)?;
+    } else {
+        dump_tuples()?;
+    }
+
+    writer.finish()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// restore
+////////////////////////////////////////////////////////////////////////////////
+
+pub(super) fn restore_from<R>(mut reader: R) -> Result<Space, Error>
+where
+    R: Read,
+{
+    let len = rmp::decode::read_array_len(&mut reader)?;
+    if len == 0 {
+        return Err(invalid_dump("header is missing"));
+    }
+    let header: Header = rmp_serde::from_read(&mut reader)?;
+    if header.version != DUMP_VERSION {
+        return Err(invalid_dump(format!(
+            "unsupported version {}, expected {DUMP_VERSION}",
+            header.version
+        )));
+    }
+
+    let space = match Space::find(&header.space.name) {
+        Some(space) => space,
+        None => create_space(header.space, &header.indexes)?,
+    };
+
+    let mut remaining = len - 1;
+    while remaining > 0 {
+        let batch = remaining.min(RESTORE_BATCH_SIZE);
+        transaction::transaction(|| -> Result<(), Error> {
+            for _ in 0..batch {
+                let value = rmpv::decode::read_value(&mut reader).map_err(Error::other)?;
+                let mut data = Vec::new();
+                rmpv::encode::write_value(&mut data, &value).map_err(Error::other)?;
+                space.replace(&TupleBuffer::try_from_vec(data)?)?;
+            }
+            Ok(())
+        })?;
+        remaining -= batch;
+    }
+
+    Ok(space)
+}
+
+fn create_space(meta: RawMetadata, indexes: &[index::Metadata]) -> Result<Space, Error> {
+    let mut format = Vec::with_capacity(meta.format.len());
+    for field in meta.format {
+        format.push(field_from_format(field)?);
+    }
+
+    let mut builder = Space::builder(&meta.name)
+        .engine(meta.engine)
+        .field_count(meta.field_count)
+        .space_type(space_type_from_flags(&meta.flags))
+        .format(format);
+    let defer_deletes = meta.flags.get("defer_deletes");
+    if let Some(defer_deletes) = defer_deletes.and_then(rmpv::Value::as_bool) {
+        builder = builder.defer_deletes(defer_deletes);
+    }
+    let space = builder.create()?;
+
+    for index in indexes {
+        let unique = !matches!(index.opts.get("unique"), Some(Value::Bool(false)));
+        // Field numbers in `_index` are 0-based, but the index builder
+        // expects 1-based ones.
+        let parts = index.parts.iter().cloned().map(|mut part| {
+            if let NumOrStr::Num(n) = &mut part.field {
+                *n += 1;
+            }
+            part
+        });
+        space
+            .index_builder(&index.name)
+            .index_type(index.r#type)
+            .unique(unique)
+            .parts(parts)
+            .create()?;
+    }
+
+    Ok(space)
+}
+
+/// Convert a field definition from `_space` format into a [`Field`]. Foreign
+/// keys and constraints refer to other objects by id, so they are not
+/// restored.
+fn field_from_format(mut field: BTreeMap<String, rmpv::Value>) -> Result<Field, Error> {
+    let Some(rmpv::Value::String(name)) = field.remove("name") else {
+        return Err(invalid_dump("field name is missing in space format"));
+    };
+    let Some(name) = name.into_str() else {
+        return Err(invalid_dump("field name is not a valid utf-8 string"));
+    };
+    let field_type = match field.get("type").and_then(rmpv::Value::as_str) {
+        Some(t) => t.parse::<FieldType>().map_err(Error::other)?,
+        None => FieldType::Any,
+    };
+    let is_nullable = field.get("is_nullable").and_then(rmpv::Value::as_bool) == Some(true);
+
+    let mut res = Field::from((name, field_type)).is_nullable(is_nullable);
+    if let Some(default) = field.remove("default") {
+        res = res.default(default);
+    }
+    Ok(res)
+}
+
+fn space_type_from_flags(flags: &BTreeMap<String, rmpv::Value>) -> SpaceType {
+    let flag = |name: &str| flags.get(name);
+    match flag("type").and_then(rmpv::Value::as_str) {
+        Some("temporary") => return SpaceType::Temporary,
+        Some("data-temporary") => return SpaceType::DataTemporary,
+        _ => {}
+    }
+    if flag("temporary").and_then(rmpv::Value::as_bool) == Some(true) {
+        SpaceType::DataTemporary
+    } else if flag("group_id").and_then(rmpv::Value::as_u64) == Some(1) {
+        SpaceType::DataLocal
+    } else if flag("is_sync").and_then(rmpv::Value::as_bool) == Some(true) {
+        SpaceType::Synchronous
+    } else {
+        SpaceType::Normal
+    }
+}
+
+#[inline]
+fn invalid_dump(message: impl Into<String>) -> Error {
+    BoxError::new(
+        TarantoolErrorCode::InvalidMsgpack,
+        format!("invalid space dump: {}", message.into()),
+    )
+    .into()
+}
+
+#[cfg(feature = "internal_test")]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[crate::test(tarantool = "crate")]
+    fn dump_and_restore() {
+        let name = crate::temp_space_name!();
+        let space = Space::builder(&name)
+            .field(Field::unsigned("id"))
+            .field(Field::string("name"))
+            .field(Field::unsigned("score").is_nullable(true))
+            .create()
+            .unwrap();
+        space.index_builder("pk").create().unwrap();
+        space
+            .index_builder("name")
+            .unique(false)
+            .part("name")
+            .create()
+            .unwrap();
+        let n = 2 * RESTORE_BATCH_SIZE + 1;
+        for i in 0..n {
+            space
+                .insert(&(i, format!("name-{}", i % 7), None::<u32>))
+                .unwrap();
+        }
+
+        let dump = space.dump_to(Cursor::new(vec![])).unwrap().into_inner();
+        space.drop().unwrap();
+
+        let restored = Space::restore_from(&dump[..]).unwrap();
+        assert_eq!(restored.len().unwrap(), n as usize);
+        let meta = restored.meta().unwrap();
+        assert_eq!(meta.name, name);
+        assert_eq!(meta.format.len(), 3);
+        assert_eq!(meta.format[2].get("is_nullable"), Some(&Value::Bool(true)));
+
+        let by_name = restored.index("name").unwrap();
+        assert_eq!(by_name.meta().unwrap().parts[0].field, NumOrStr::Num(1));
+        let count = by_name
+            .select(IteratorType::Eq, &("name-3",))
+            .unwrap()
+            .count();
+        assert_eq!(count, (0..n).filter(|i| i % 7 == 3).count());
+
+        let t = restored.get(&(42,)).unwrap().unwrap();
+        let t: (u32, String, Option<u32>) = t.decode().unwrap();
+        assert_eq!(t, (42, "name-0".into(), None));
+
+        // Restoring into an existing space loads the tuples into it.
+        restored.truncate().unwrap();
+        restored.insert(&(n, "extra", 1)).unwrap();
+        let restored = Space::restore_from(&dump[..]).unwrap();
+        assert_eq!(restored.len().unwrap(), n as usize + 1);
+
+        restored.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn restore_invalid_dump() {
+        let e = Space::restore_from(&b"\x90"[..]).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "box error: InvalidMsgpack: invalid space dump: header is missing"
+        );
+    }
+}
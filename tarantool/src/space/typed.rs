@@ -0,0 +1,445 @@
+//! Spaces and indexes bound to a rust type of the stored tuples.
+
+use super::Space;
+use crate::error::Error;
+use crate::index::{Index, IndexIterator, IteratorType};
+use crate::msgpack::{self, Decode, Encode};
+use crate::tuple::{Tuple, TupleBuffer};
+use std::marker::PhantomData;
+
+/// Encodes `value` into a tuple buffer via [`msgpack::Encode`].
+#[inline]
+fn encode<V: Encode>(value: &V) -> Result<TupleBuffer, Error> {
+    TupleBuffer::try_from_vec(msgpack::encode(value))
+}
+
+/// Decodes a tuple via [`msgpack::Decode`].
+#[inline]
+fn decode<T>(tuple: &Tuple) -> Result<T, Error>
+where
+    T: for<'de> Decode<'de>,
+{
+    #[cfg(feature = "picodata")]
+    return Ok(msgpack::decode(tuple.data())?);
+    #[cfg(not(feature = "picodata"))]
+    return Ok(msgpack::decode(&tuple.to_vec())?);
+}
+
+#[inline(always)]
+fn decode_opt<T>(tuple: Option<Tuple>) -> Result<Option<T>, Error>
+where
+    T: for<'de> Decode<'de>,
+{
+    tuple.as_ref().map(decode).transpose()
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TypedSpace
+////////////////////////////////////////////////////////////////////////////////
+
+/// A [`Space`] storing tuples of type `T` with primary keys of type `K`.
+///
+/// `TypedSpace` and [`TypedIndex<T, K>`] are thin wrappers around
+/// [`Space`] and [`Index`] which accept and return values of type `T` instead
+/// of raw [`Tuple`]s. Values are (de)serialized using [`msgpack::Encode`] and
+/// [`msgpack::Decode`], so `T` must be encoded as a msgpack array (which is the
+/// default for structs deriving these traits, unless `#[encode(as_map)]` is
+/// specified).
+///
+/// `K` is the type of the primary key (or the index key in case of
+/// [`TypedIndex`]), which also must be encoded as a msgpack array, e.g. `(u64,)`
+/// for an index with a single unsigned part.
+///
+/// # Example
+/// ```no_run
+/// use tarantool::msgpack::{Decode, Encode};
+/// use tarantool::space::TypedSpace;
+///
+/// #[derive(Encode, Decode, Debug, PartialEq)]
+/// struct User {
+///     id: u64,
+///     name: String,
+/// }
+///
+/// let users = TypedSpace::<User, (u64,)>::find("users").unwrap();
+/// users.insert(&User { id: 1, name: "Alice".into() }).unwrap();
+///
+/// let user = users.get(&(1,)).unwrap().unwrap();
+/// assert_eq!(user.name, "Alice");
+///
+/// let by_name = users.index::<(String,)>("name").unwrap();
+/// for user in by_name.select_all().unwrap() {
+///     println!("{:?}", user.unwrap());
+/// }
+/// ```
+pub struct TypedSpace<T, K> {
+    space: Space,
+    marker: PhantomData<fn(&K) -> T>,
+}
+
+impl<T, K> TypedSpace<T, K>
+where
+    T: Encode + for<'de> Decode<'de>,
+    K: Encode,
+{
+    /// Wraps the `space`. The caller is responsible for `T` matching the
+    /// space format and `K` matching the primary key parts.
+    #[inline(always)]
+    pub fn new(space: Space) -> Self {
+        Self {
+            space,
+            marker: PhantomData,
+        }
+    }
+
+    /// Finds a space by name. See [`Space::find`].
+    #[inline(always)]
+    pub fn find(name: &str) -> Option<Self> {
+        Space::find(name).map(Self::new)
+    }
+
+    /// Finds a space by name using the cache. See [`Space::find_cached`].
+    #[inline(always)]
+    pub fn find_cached(name: &str) -> Option<Self> {
+        Space::find_cached(name).map(Self::new)
+    }
+
+    /// Returns the underlying untyped space.
+    #[inline(always)]
+    pub fn space(&self) -> &Space {
+        &self.space
+    }
+
+    /// Returns the primary index of the space.
+    #[inline(always)]
+    pub fn primary_key(&self) -> TypedIndex<T, K> {
+        TypedIndex::new(self.space.primary_key())
+    }
+
+    /// Finds an index by name, `I` is the type of the index key.
+    /// See [`Space::index`].
+    #[inline(always)]
+    pub fn index<I>(&self, name: &str) -> Option<TypedIndex<T, I>>
+    where
+        I: Encode,
+    {
+        self.space.index(name).map(TypedIndex::new)
+    }
+
+    /// Inserts a `value` into the space. Returns the inserted value as it's
+    /// stored in the space. See [`Space::insert`].
+    #[inline]
+    pub fn insert(&self, value: &T) -> Result<T, Error> {
+        decode(&self.space.insert(&encode(value)?)?)
+    }
+
+    /// Inserts or replaces a `value` in the space. Returns the new value as
+    /// it's stored in the space. See [`Space::replace`].
+    #[inline]
+    pub fn replace(&self, value: &T) -> Result<T, Error> {
+        decode(&self.space.replace(&encode(value)?)?)
+    }
+
+    /// Searches for a value by the primary `key`. See [`Space::get`].
+    #[inline]
+    pub fn get(&self, key: &K) -> Result<Option<T>, Error> {
+        decode_opt(self.space.get(&encode(key)?)?)
+    }
+
+    /// Searches for values by the primary `key`. See [`Space::select`].
+    #[inline]
+    pub fn select(&self, iterator_type: IteratorType, key: &K) -> Result<TypedIter<T>, Error> {
+        let inner = self.space.select(iterator_type, &encode(key)?)?;
+        Ok(TypedIter::new(inner))
+    }
+
+    /// Returns an iterator over all the values in the space in the order of
+    /// the primary key.
+    #[inline]
+    pub fn select_all(&self) -> Result<TypedIter<T>, Error> {
+        let inner = self.space.select(IteratorType::All, &())?;
+        Ok(TypedIter::new(inner))
+    }
+
+    /// Deletes a value by the primary `key`. Returns the deleted value if it
+    /// was found. See [`Space::delete`].
+    #[inline]
+    pub fn delete(&self, key: &K) -> Result<Option<T>, Error> {
+        decode_opt(self.space.delete(&encode(key)?)?)
+    }
+
+    /// Returns the number of tuples in the space. See [`Space::len`].
+    #[inline(always)]
+    pub fn len(&self) -> Result<usize, Error> {
+        self.space.len()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> Result<bool, Error> {
+        self.space.is_empty()
+    }
+}
+
+impl<T, K> Clone for TypedSpace<T, K> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self {
+            space: self.space.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, K> std::fmt::Debug for TypedSpace<T, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TypedSpace")
+            .field("space", &self.space)
+            .field("type", &std::any::type_name::<T>())
+            .field("key", &std::any::type_name::<K>())
+            .finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TypedIndex
+////////////////////////////////////////////////////////////////////////////////
+
+/// An [`Index`] of a space storing tuples of type `T` with keys of type `K`.
+///
+/// See [`TypedSpace`] for details.
+pub struct TypedIndex<T, K> {
+    index: Index,
+    marker: PhantomData<fn(&K) -> T>,
+}
+
+impl<T, K> TypedIndex<T, K>
+where
+    T: Encode + for<'de> Decode<'de>,
+    K: Encode,
+{
+    /// Wraps the `index`. The caller is responsible for `T` matching the
+    /// space format and `K` matching the index parts.
+    #[inline(always)]
+    pub fn new(index: Index) -> Self {
+        Self {
+            index,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the underlying untyped index.
+    #[inline(always)]
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Searches for a value by the `key`. See [`Index::get`].
+    #[inline]
+    pub fn get(&self, key: &K) -> Result<Option<T>, Error> {
+        decode_opt(self.index.get(&encode(key)?)?)
+    }
+
+    /// Searches for values by the `key`. See [`Index::select`].
+    #[inline]
+    pub fn select(&self, iterator_type: IteratorType, key: &K) -> Result<TypedIter<T>, Error> {
+        let inner = self.index.select(iterator_type, &encode(key)?)?;
+        Ok(TypedIter::new(inner))
+    }
+
+    /// Returns an iterator over all the values in the order of the index.
+    #[inline]
+    pub fn select_all(&self) -> Result<TypedIter<T>, Error> {
+        let inner = self.index.select(IteratorType::All, &())?;
+        Ok(TypedIter::new(inner))
+    }
+
+    /// Deletes a value by the `key`. The index must be unique. Returns the
+    /// deleted value if it was found. See [`Index::delete`].
+    #[inline]
+    pub fn delete(&self, key: &K) -> Result<Option<T>, Error> {
+        decode_opt(self.index.delete(&encode(key)?)?)
+    }
+
+    /// Returns the first value in the index. See [`Index::min`].
+    #[inline]
+    pub fn min(&self) -> Result<Option<T>, Error> {
+        decode_opt(self.index.min(&())?)
+    }
+
+    /// Returns the last value in the index. See [`Index::max`].
+    #[inline]
+    pub fn max(&self) -> Result<Option<T>, Error> {
+        decode_opt(self.index.max(&())?)
+    }
+
+    /// Returns the number of values matching the `key`. See [`Index::count`].
+    #[inline]
+    pub fn count(&self, iterator_type: IteratorType, key: &K) -> Result<usize, Error> {
+        self.index.count(iterator_type, &encode(key)?)
+    }
+}
+
+impl<T, K> Clone for TypedIndex<T, K> {
+    #[inline(always)]
+    fn clone(&self) -> Self {
+        Self {
+            index: self.index.clone(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T, K> std::fmt::Debug for TypedIndex<T, K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TypedIndex")
+            .field("index", &self.index)
+            .field("type", &std::any::type_name::<T>())
+            .field("key", &std::any::type_name::<K>())
+            .finish()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// TypedIter
+////////////////////////////////////////////////////////////////////////////////
+
+/// An iterator over the values of a [`TypedSpace`] or [`TypedIndex`].
+///
+/// Yields an error if a tuple can't be decoded into `T`.
+pub struct TypedIter<T> {
+    inner: IndexIterator,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TypedIter<T> {
+    #[inline(always)]
+    fn new(inner: IndexIterator) -> Self {
+        Self {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    /// Returns the underlying iterator over raw tuples.
+    #[inline(always)]
+    pub fn into_inner(self) -> IndexIterator {
+        self.inner
+    }
+}
+
+impl<T> Iterator for TypedIter<T>
+where
+    T: for<'de> Decode<'de>,
+{
+    type Item = Result<T, Error>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let tuple = self.inner.next()?;
+        Some(decode(&tuple))
+    }
+}
+
+impl<T> std::fmt::Debug for TypedIter<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("TypedIter")
+            .field("type", &std::any::type_name::<T>())
+            .finish_non_exhaustive()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::index::Part;
+    use crate::space::FieldType;
+
+    #[derive(Encode, Decode, Debug, Clone, PartialEq)]
+    #[encode(tarantool = "crate")]
+    struct User {
+        id: u64,
+        name: String,
+        age: u32,
+    }
+
+    fn user(id: u64, name: &str, age: u32) -> User {
+        User {
+            id,
+            name: name.into(),
+            age,
+        }
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn crud() {
+        let space = Space::builder(&crate::temp_space_name!())
+            .field(("id", FieldType::Unsigned))
+            .field(("name", FieldType::String))
+            .field(("age", FieldType::Unsigned))
+            .create()
+            .unwrap();
+        space.index_builder("pk").create().unwrap();
+        space
+            .index_builder("age")
+            .unique(false)
+            .part(Part::field("age"))
+            .create()
+            .unwrap();
+
+        let users = TypedSpace::<User, (u64,)>::new(space.clone());
+        assert_eq!(
+            users.insert(&user(1, "Alice", 30)).unwrap(),
+            user(1, "Alice", 30)
+        );
+        users.insert(&user(2, "Bob", 25)).unwrap();
+        users.insert(&user(3, "Carol", 30)).unwrap();
+        assert!(users.insert(&user(1, "Alice again", 31)).is_err());
+        assert_eq!(users.len().unwrap(), 3);
+
+        assert_eq!(users.get(&(2,)).unwrap(), Some(user(2, "Bob", 25)));
+        assert_eq!(users.get(&(4,)).unwrap(), None);
+
+        users.replace(&user(2, "Bob", 26)).unwrap();
+        assert_eq!(users.get(&(2,)).unwrap(), Some(user(2, "Bob", 26)));
+
+        let all: Vec<_> = users.select_all().unwrap().map(Result::unwrap).collect();
+        assert_eq!(all.iter().map(|u| u.id).collect::<Vec<_>>(), [1, 2, 3]);
+
+        let by_age = users.index::<(u32,)>("age").unwrap();
+        let thirty: Vec<_> = by_age
+            .select(IteratorType::Eq, &(30,))
+            .unwrap()
+            .map(|u| u.unwrap().name)
+            .collect();
+        assert_eq!(thirty, ["Alice", "Carol"]);
+        assert_eq!(by_age.count(IteratorType::Eq, &(30,)).unwrap(), 2);
+        assert_eq!(by_age.min().unwrap(), Some(user(2, "Bob", 26)));
+
+        let pk = users.primary_key();
+        assert_eq!(pk.max().unwrap().map(|u| u.id), Some(3));
+        assert_eq!(pk.delete(&(3,)).unwrap(), Some(user(3, "Carol", 30)));
+        assert_eq!(users.delete(&(3,)).unwrap(), None);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn decode_error() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+        space.insert(&(1, "not a user")).unwrap();
+
+        let users = TypedSpace::<User, (u64,)>::new(space.clone());
+        let err = users.get(&(1,)).unwrap_err();
+        assert!(matches!(err, Error::MsgpackDecode(_)), "{}", err);
+        let mut iter = users.select_all().unwrap();
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        space.drop().unwrap();
+    }
+}
@@ -0,0 +1,511 @@
+//! Statistics of the instance, spaces and indexes.
+//!
+//! The data is obtained via the lua `box.stat()`, `space_object:stat()` and
+//! `index_object:stat()` functions, so these apis are relatively expensive and
+//! are intended for monitoring, not for the hot path.
+//!
+//! All of the structs implement [`Default`] and any statistics not reported by
+//! the current version of tarantool are left with default values.
+//!
+//! See also:
+//! - [Lua reference: box.stat](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_stat/)
+//! - [Lua reference: index_object:stat](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_index/stat/)
+//! - [Lua reference: box.stat.vinyl](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_stat/vinyl/)
+
+use crate::error::Error;
+use crate::index::IndexId;
+use crate::space::{SpaceEngineType, SpaceId};
+use crate::tuple::Tuple;
+use serde::{Deserialize, Serialize};
+
+////////////////////////////////////////////////////////////////////////////////
+// box.stat
+////////////////////////////////////////////////////////////////////////////////
+
+/// Total number of events and the average number of events per second over
+/// the last 5 seconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rate {
+    pub total: u64,
+    pub rps: u64,
+}
+
+/// Same as [`Rate`] but also with the number of events currently in progress.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateWithCurrent {
+    pub total: u64,
+    pub rps: u64,
+    pub current: u64,
+}
+
+/// A snapshot of the instance statistics. See [`box_stat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoxStat {
+    /// Number of requests of each type processed by the instance
+    /// (see `box.stat()`).
+    pub requests: RequestStat,
+    /// Network statistics (see `box.stat.net()`).
+    pub net: NetStat,
+    /// Statistics of the vinyl engine (see `box.stat.vinyl()`).
+    pub vinyl: VinylStat,
+}
+
+/// Number of requests of each type. See [`BoxStat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "UPPERCASE")]
+pub struct RequestStat {
+    pub select: Rate,
+    pub insert: Rate,
+    pub replace: Rate,
+    pub update: Rate,
+    pub upsert: Rate,
+    pub delete: Rate,
+    pub call: Rate,
+    pub eval: Rate,
+    pub auth: Rate,
+    pub execute: Rate,
+    pub prepare: Rate,
+    pub begin: Rate,
+    pub commit: Rate,
+    pub rollback: Rate,
+    /// Number of requests which resulted in an error.
+    pub error: Rate,
+}
+
+/// Network statistics. See [`BoxStat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "UPPERCASE")]
+pub struct NetStat {
+    /// Bytes sent to the clients.
+    pub sent: Rate,
+    /// Bytes received from the clients.
+    pub received: Rate,
+    /// Client connections.
+    pub connections: RateWithCurrent,
+    /// Client requests.
+    pub requests: RateWithCurrent,
+}
+
+/// Statistics of the vinyl engine. See [`BoxStat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylStat {
+    pub tx: VinylTxStat,
+    pub memory: VinylMemoryStat,
+    pub disk: VinylDiskStat,
+}
+
+/// Vinyl transaction statistics. See [`VinylStat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylTxStat {
+    /// Number of commits.
+    pub commit: u64,
+    /// Number of rollbacks.
+    pub rollback: u64,
+    /// Number of transactions aborted due to a conflict.
+    pub conflict: u64,
+    /// Number of active transactions.
+    pub transactions: u64,
+    /// Number of statements in the active transactions.
+    pub statements: u64,
+    /// Number of gap locks held by the active transactions.
+    pub gap_locks: u64,
+    /// Number of open read views.
+    pub read_views: u64,
+}
+
+/// Memory in bytes used by the vinyl engine. See [`VinylStat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylMemoryStat {
+    pub tuple_cache: u64,
+    pub tx: u64,
+    pub level0: u64,
+    pub page_index: u64,
+    pub bloom_filter: u64,
+    pub tuple: u64,
+}
+
+/// Disk space in bytes used by the vinyl engine. See [`VinylStat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylDiskStat {
+    pub data: u64,
+    pub index: u64,
+    pub data_compacted: u64,
+}
+
+/// Returns a snapshot of the instance statistics.
+///
+/// This is the equivalent of lua's `box.stat()`, `box.stat.net()` and
+/// `box.stat.vinyl()` combined.
+pub fn box_stat() -> Result<BoxStat, Error> {
+    let lua = crate::lua_state();
+    let tuple: Tuple = lua.eval(
+        "return box.tuple.new({{
+            requests = box.stat(),
+            net = box.stat.net(),
+            vinyl = box.stat.vinyl(),
+        }})",
+    )?;
+    let (stat,) = tuple.decode()?;
+    Ok(stat)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// space:stat
+////////////////////////////////////////////////////////////////////////////////
+
+/// Statistics of a space. See [`Space::stat`].
+///
+/// [`Space::stat`]: crate::space::Space::stat
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpaceStat {
+    pub engine: SpaceEngineType,
+    /// Number of tuples in the space. For vinyl spaces this is an estimate.
+    pub len: u64,
+    /// Number of bytes in all the tuples of the space.
+    pub bsize: u64,
+    /// Memory used by memtx tuples. Is `None` for vinyl spaces or if not
+    /// supported by the current version of tarantool.
+    pub memtx: Option<MemtxTupleStat>,
+}
+
+/// Memory used by memtx tuples of a space. See [`SpaceStat`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemtxTupleStat {
+    /// Memory used by the tuple data.
+    pub data: MemtxMemoryStat,
+    /// Memory used by the tuples which are referenced only by indexes
+    /// (e.g. in the functional indexes).
+    pub index: MemtxMemoryStat,
+}
+
+/// Memory in bytes used by memtx tuples. See [`MemtxTupleStat`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemtxMemoryStat {
+    /// Total memory used by the tuples.
+    pub total: u64,
+    /// Memory used by the tuples which are only referenced by read views.
+    pub read_view: u64,
+    /// Memory used by the tuples which are waiting to be freed.
+    pub garbage: u64,
+}
+
+/// Returns the statistics of the space with the given `space_id`.
+pub(crate) fn space_stat(space_id: SpaceId) -> Result<SpaceStat, Error> {
+    let lua = crate::lua_state();
+    let tuple: Tuple = lua
+        .eval_with(
+            "local space_id = ...
+            local space = box.space[space_id]
+            if space == nil then
+                error(('Space #%d does not exist'):format(space_id))
+            end
+            local memtx
+            if space.engine == 'memtx' and space.stat ~= nil then
+                local stat = space:stat()
+                memtx = stat.tuple and stat.tuple.memtx
+            end
+            return box.tuple.new({{
+                engine = space.engine,
+                len = space:len(),
+                bsize = space:bsize(),
+                memtx = memtx,
+            }})",
+            space_id,
+        )
+        .map_err(tlua::LuaError::from)?;
+    let (stat,) = tuple.decode()?;
+    Ok(stat)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// index:stat
+////////////////////////////////////////////////////////////////////////////////
+
+/// Statistics of an index. See [`Index::stat`].
+///
+/// [`Index::stat`]: crate::index::Index::stat
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexStat {
+    /// Number of tuples in the index. For vinyl indexes this is an estimate.
+    pub len: u64,
+    /// Number of bytes used in memory by the index.
+    pub bsize: u64,
+    /// Statistics of the vinyl LSM tree. Is `None` for memtx indexes.
+    pub vinyl: Option<VinylIndexStat>,
+}
+
+/// Number of rows and their size in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RowsAndBytes {
+    pub rows: u64,
+    pub bytes: u64,
+}
+
+/// Statistics of a vinyl index (LSM tree). See [`IndexStat`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylIndexStat {
+    /// Number of rows in the LSM tree including the overwritten ones.
+    pub rows: u64,
+    /// Size of the rows in bytes.
+    pub bytes: u64,
+    /// Number of lookups in the LSM tree.
+    pub lookup: u64,
+    /// Rows read from the LSM tree.
+    pub get: RowsAndBytes,
+    /// Rows written to the LSM tree.
+    pub put: RowsAndBytes,
+    /// Number of key ranges.
+    pub range_count: u64,
+    /// Number of runs on disk.
+    pub run_count: u64,
+    /// Average number of runs per range.
+    pub run_avg: f64,
+    /// Average number of dumps per compaction.
+    pub dumps_per_compaction: u64,
+    /// The in-memory level of the LSM tree.
+    pub memory: VinylIndexMemoryStat,
+    /// The on-disk levels of the LSM tree.
+    pub disk: VinylIndexDiskStat,
+    /// The tuple cache.
+    pub cache: VinylCacheStat,
+    /// The transaction write set.
+    pub txw: VinylTxwStat,
+}
+
+/// Statistics of an iterator over a part of a vinyl LSM tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylIteratorStat {
+    /// Number of lookups.
+    pub lookup: u64,
+    /// Rows returned.
+    pub get: RowsAndBytes,
+}
+
+/// The in-memory level of a vinyl LSM tree. See [`VinylIndexStat`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylIndexMemoryStat {
+    pub rows: u64,
+    pub bytes: u64,
+    /// Size of the in-memory index in bytes.
+    pub index_size: u64,
+    pub iterator: VinylIteratorStat,
+}
+
+/// The on-disk levels of a vinyl LSM tree. See [`VinylIndexStat`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylIndexDiskStat {
+    pub rows: u64,
+    pub bytes: u64,
+    /// Size of the rows on disk after compression.
+    pub bytes_compressed: u64,
+    /// Size of the page index in bytes.
+    pub index_size: u64,
+    /// Size of the bloom filters in bytes.
+    pub bloom_size: u64,
+    /// Number of pages.
+    pub pages: u64,
+    /// Number of statements of each type stored on disk.
+    pub statement: VinylStatementStat,
+    pub iterator: VinylDiskIteratorStat,
+    pub dump: VinylTaskStat,
+    pub compaction: VinylTaskStat,
+}
+
+/// Number of statements of each type. See [`VinylIndexDiskStat`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylStatementStat {
+    pub inserts: u64,
+    pub replaces: u64,
+    pub upserts: u64,
+    pub deletes: u64,
+}
+
+/// Statistics of reading the on-disk levels of a vinyl LSM tree.
+/// See [`VinylIndexDiskStat`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylDiskIteratorStat {
+    /// Number of lookups.
+    pub lookup: u64,
+    /// Rows read from disk.
+    pub read: RowsAndBytes,
+    /// Rows returned.
+    pub get: RowsAndBytes,
+    /// Rows skipped because they were overwritten.
+    pub skip: RowsAndBytes,
+    /// Number of lookups avoided thanks to the bloom filter.
+    pub bloom_hit: u64,
+    /// Number of lookups not avoided by the bloom filter.
+    pub bloom_miss: u64,
+}
+
+/// Statistics of dumps or compactions of a vinyl LSM tree.
+/// See [`VinylIndexDiskStat`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylTaskStat {
+    /// Number of completed tasks.
+    pub count: u64,
+    /// Total time spent on the tasks in seconds.
+    pub time: f64,
+    /// Rows read by the tasks.
+    pub input: RowsAndBytes,
+    /// Rows written by the tasks.
+    pub output: RowsAndBytes,
+    /// Rows waiting to be processed. Is only reported for compactions.
+    pub queue: RowsAndBytes,
+}
+
+/// Statistics of the tuple cache of a vinyl index. See [`VinylIndexStat`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylCacheStat {
+    pub rows: u64,
+    pub bytes: u64,
+    /// Size of the cache index in bytes.
+    pub index_size: u64,
+    /// Number of lookups in the cache.
+    pub lookup: u64,
+    /// Rows returned from the cache.
+    pub get: RowsAndBytes,
+    /// Rows added to the cache.
+    pub put: RowsAndBytes,
+    /// Rows removed from the cache because they were overwritten.
+    pub invalidate: RowsAndBytes,
+    /// Rows evicted from the cache due to the memory limit.
+    pub evict: RowsAndBytes,
+}
+
+/// Statistics of the transaction write set of a vinyl index.
+/// See [`VinylIndexStat`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VinylTxwStat {
+    pub rows: u64,
+    pub bytes: u64,
+    pub iterator: VinylIteratorStat,
+}
+
+/// Returns the statistics of the index with the given `index_id` of the space
+/// with the given `space_id`.
+pub(crate) fn index_stat(space_id: SpaceId, index_id: IndexId) -> Result<IndexStat, Error> {
+    let lua = crate::lua_state();
+    let tuple: Tuple = lua
+        .eval_with(
+            "local space_id, index_id = ...
+            local space = box.space[space_id]
+            if space == nil then
+                error(('Space #%d does not exist'):format(space_id))
+            end
+            local index = space.index[index_id]
+            if index == nil then
+                error(('No index #%d is defined in space %q'):format(index_id, space.name))
+            end
+            local vinyl
+            if space.engine == 'vinyl' then
+                vinyl = index:stat()
+            end
+            return box.tuple.new({{
+                len = index:len(),
+                bsize = index:bsize(),
+                vinyl = vinyl,
+            }})",
+            (space_id, index_id),
+        )
+        .map_err(tlua::LuaError::from)?;
+    let (stat,) = tuple.decode()?;
+    Ok(stat)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::space::Space;
+
+    #[crate::test(tarantool = "crate")]
+    fn box_stat_requests() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        space.index_builder("pk").create().unwrap();
+
+        let before = box_stat().unwrap();
+        space.insert(&(1,)).unwrap();
+        space.insert(&(2,)).unwrap();
+        let after = box_stat().unwrap();
+        assert!(after.requests.insert.total >= before.requests.insert.total + 2);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn memtx_stat() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        let pk = space.index_builder("pk").create().unwrap();
+        for i in 0..3 {
+            space.insert(&(i, "foo")).unwrap();
+        }
+
+        let stat = space.stat().unwrap();
+        assert_eq!(stat.engine, SpaceEngineType::Memtx);
+        assert_eq!(stat.len, 3);
+        assert_eq!(stat.bsize, space.bsize().unwrap() as u64);
+        if let Some(memtx) = stat.memtx {
+            assert!(memtx.data.total > 0);
+        }
+
+        let stat = pk.stat().unwrap();
+        assert_eq!(stat.len, 3);
+        assert!(stat.bsize > 0);
+        assert_eq!(stat.vinyl, None);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn vinyl_stat() {
+        let space = Space::builder(&crate::temp_space_name!())
+            .engine(SpaceEngineType::Vinyl)
+            .create()
+            .unwrap();
+        let pk = space.index_builder("pk").create().unwrap();
+        for i in 0..3 {
+            space.insert(&(i, "foo")).unwrap();
+        }
+        space.get(&(1,)).unwrap().unwrap();
+
+        let stat = space.stat().unwrap();
+        assert_eq!(stat.engine, SpaceEngineType::Vinyl);
+        assert_eq!(stat.memtx, None);
+
+        let stat = pk.stat().unwrap();
+        let vinyl = stat.vinyl.unwrap();
+        assert_eq!(vinyl.put.rows, 3);
+        assert_eq!(vinyl.memory.rows, 3);
+        assert!(vinyl.lookup >= 1);
+
+        let e = crate::stat::index_stat(space.id(), 42).unwrap_err();
+        assert!(e.to_string().contains("No index #42"), "{}", e);
+
+        space.drop().unwrap();
+    }
+}
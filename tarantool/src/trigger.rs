@@ -1,7 +1,15 @@
 use crate::error::{TarantoolError, TarantoolErrorCode};
 use crate::ffi::tarantool as ffi;
+use crate::fiber::unwind::panic_message;
+use crate::fiber::NoYieldsGuard;
 use crate::set_error;
+use crate::space::SpaceId;
+use crate::tuple::Tuple;
 use std::io;
+use std::marker::PhantomData;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tlua::{Function, Throw};
 
 /// Set a callback to be called on Tarantool shutdown.
 pub fn on_shutdown<F: FnOnce() + 'static>(cb: F) -> Result<(), TarantoolError> {
@@ -25,3 +33,200 @@ pub fn on_shutdown<F: FnOnce() + 'static>(cb: F) -> Result<(), TarantoolError> {
         0
     }
 }
+
+////////////////////////////////////////////////////////////////////////////////
+// space triggers
+////////////////////////////////////////////////////////////////////////////////
+
+/// Name of the lua table in `package.loaded` where the registered space
+/// triggers are stored so that they can be unregistered later.
+const SPACE_TRIGGERS: &str = "tarantool.rust_space_triggers";
+
+static NEXT_SPACE_TRIGGER_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum SpaceTriggerKind {
+    OnReplace,
+    BeforeReplace,
+}
+
+impl SpaceTriggerKind {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::OnReplace => "on_replace",
+            Self::BeforeReplace => "before_replace",
+        }
+    }
+}
+
+/// A handle to a trigger set with [`on_replace`] or [`before_replace`].
+///
+/// The trigger is unregistered when the handle is dropped, use
+/// [`SpaceTrigger::forget`] to keep it registered for as long as the space
+/// exists.
+#[must_use = "the trigger is unregistered when the handle is dropped"]
+#[derive(Debug)]
+pub struct SpaceTrigger {
+    space_id: SpaceId,
+    kind: SpaceTriggerKind,
+    id: u64,
+    /// The trigger is unregistered via the lua state of the TX thread, so the
+    /// handle must not be sent to other threads.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl SpaceTrigger {
+    /// Returns the id of the space the trigger is set on.
+    #[inline(always)]
+    pub fn space_id(&self) -> SpaceId {
+        self.space_id
+    }
+
+    /// Consumes the handle without unregistering the trigger.
+    #[inline(always)]
+    pub fn forget(self) {
+        std::mem::forget(self)
+    }
+}
+
+impl Drop for SpaceTrigger {
+    fn drop(&mut self) {
+        let lua = crate::lua_state();
+        let res = lua.exec_with(
+            "local loaded_key, space_id, kind, id = ...
+            local triggers = package.loaded[loaded_key]
+            local trigger = triggers and triggers[id]
+            if trigger == nil then
+                return
+            end
+            triggers[id] = nil
+            local space = box.space[space_id]
+            -- The space may have already been dropped along with its triggers.
+            if space ~= nil then
+                pcall(space[kind], space, nil, trigger)
+            end",
+            (SPACE_TRIGGERS, self.space_id, self.kind.as_str(), self.id),
+        );
+        if let Err(e) = res.map_err(tlua::LuaError::from) {
+            crate::say_warn!(
+                "failed to unregister {} trigger on space #{}: {e}",
+                self.kind.as_str(),
+                self.space_id
+            );
+        }
+    }
+}
+
+/// Set a trigger which is called after a tuple is inserted, replaced,
+/// updated or deleted in the space.
+/// (for details see [space_object:on_replace()](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/on_replace/)).
+///
+/// The callback receives the old and the new tuples, either of which may be
+/// `None` (e.g. there's no old tuple on insert and no new tuple on delete).
+/// If the callback returns an error, the operation fails with this error and
+/// the transaction is rolled back.
+///
+/// The callback is called from within the transaction, so it must not yield.
+/// A yield is detected via [`NoYieldsGuard`] and is reported as an error in
+/// the log (or a panic in debug builds). Panics in the callback are caught and
+/// reported as errors of the operation.
+///
+/// The trigger is unregistered when the returned handle is dropped.
+///
+/// [`NoYieldsGuard`]: crate::fiber::NoYieldsGuard
+pub fn on_replace<F>(space_id: SpaceId, mut f: F) -> crate::Result<SpaceTrigger>
+where
+    F: FnMut(Option<Tuple>, Option<Tuple>) -> crate::Result<()> + 'static,
+{
+    let handler = move |old: Option<Tuple>, new: Option<Tuple>| {
+        call_space_trigger("on_replace", || f(old, new).map(|()| None))
+    };
+    set_space_trigger(space_id, SpaceTriggerKind::OnReplace, Box::new(handler))
+}
+
+/// Set a trigger which is called before a tuple is inserted, replaced,
+/// updated or deleted in the space.
+/// (for details see [space_object:before_replace()](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_space/before_replace/)).
+///
+/// The callback receives the old and the new tuples, either of which may be
+/// `None`, and returns the tuple which will actually be stored:
+/// - the `new` tuple to proceed with the operation as is,
+/// - a modified tuple to store it instead of the `new` one,
+/// - the `old` tuple to silently skip the operation,
+/// - `None` to delete the `old` tuple.
+///
+/// If the callback returns an error, the operation fails with this error.
+///
+/// The same rules regarding yields and panics apply as for [`on_replace`].
+///
+/// The trigger is unregistered when the returned handle is dropped.
+pub fn before_replace<F>(space_id: SpaceId, mut f: F) -> crate::Result<SpaceTrigger>
+where
+    F: FnMut(Option<Tuple>, Option<Tuple>) -> crate::Result<Option<Tuple>> + 'static,
+{
+    let handler = move |old: Option<Tuple>, new: Option<Tuple>| {
+        call_space_trigger("before_replace", || f(old, new))
+    };
+    set_space_trigger(space_id, SpaceTriggerKind::BeforeReplace, Box::new(handler))
+}
+
+type SpaceTriggerHandler =
+    Box<dyn FnMut(Option<Tuple>, Option<Tuple>) -> Result<Option<Tuple>, Throw<String>>>;
+
+fn set_space_trigger(
+    space_id: SpaceId,
+    kind: SpaceTriggerKind,
+    handler: SpaceTriggerHandler,
+) -> crate::Result<SpaceTrigger> {
+    let id = NEXT_SPACE_TRIGGER_ID.fetch_add(1, Ordering::Relaxed);
+    let lua = crate::lua_state();
+    lua.exec_with(
+        "local loaded_key, space_id, kind, id, handler = ...
+        local space = box.space[space_id]
+        if space == nil then
+            error(('Space #%d does not exist'):format(space_id))
+        end
+        -- Wrap the handler to pass exactly 2 arguments to it.
+        local trigger = function(old, new)
+            return handler(old, new)
+        end
+        space[kind](space, trigger)
+        local triggers = package.loaded[loaded_key]
+        if triggers == nil then
+            triggers = {}
+            package.loaded[loaded_key] = triggers
+        end
+        triggers[id] = trigger",
+        (
+            SPACE_TRIGGERS,
+            space_id,
+            kind.as_str(),
+            id,
+            Function::new(handler),
+        ),
+    )
+    .map_err(tlua::LuaError::from)?;
+    Ok(SpaceTrigger {
+        space_id,
+        kind,
+        id,
+        _not_send: PhantomData,
+    })
+}
+
+/// Calls the trigger callback `f` making sure it doesn't yield and converting
+/// errors and panics into lua errors.
+fn call_space_trigger<T>(
+    kind: &str,
+    f: impl FnOnce() -> crate::Result<T>,
+) -> Result<T, Throw<String>> {
+    let _guard = NoYieldsGuard::with_message("space trigger yielded");
+    match std::panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(v)) => Ok(v),
+        Ok(Err(e)) => Err(Throw(e.to_string())),
+        Err(payload) => {
+            let message = panic_message(&*payload).unwrap_or("Box<dyn Any>");
+            Err(Throw(format!("{kind} trigger panicked: {message}")))
+        }
+    }
+}
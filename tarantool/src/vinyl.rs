@@ -0,0 +1,367 @@
+//! Maintenance and tuning of the vinyl engine.
+//!
+//! This module provides:
+//! - manual compaction of vinyl indexes, see [`compact`],
+//! - the state of the LSM tree of a vinyl index, see [`lsm_stat`],
+//! - the state of the dump and compaction scheduler, see [`scheduler_stat`],
+//! - the `box.cfg.vinyl_*` settings, see [`config`] and [`set_config`].
+//!
+//! Space level vinyl options are set with [`space::Builder::defer_deletes`]
+//! and index level ones with [`index::Builder`], e.g.
+//! [`index::Builder::bloom_fpr`].
+//!
+//! The data is obtained via lua, so these apis are relatively expensive and
+//! are intended for monitoring and maintenance, not for the hot path.
+//!
+//! See also:
+//! - [Vinyl storage engine](https://www.tarantool.io/en/doc/latest/concepts/engines/vinyl/)
+//! - [Lua reference: index_object:compact](https://www.tarantool.io/en/doc/latest/reference/reference_lua/box_index/compact/)
+//!
+//! [`space::Builder::defer_deletes`]: crate::space::Builder::defer_deletes
+//! [`index::Builder`]: crate::index::Builder
+//! [`index::Builder::bloom_fpr`]: crate::index::Builder::bloom_fpr
+
+use crate::error::Error;
+use crate::index::IndexId;
+use crate::space::SpaceId;
+use crate::stat::{RowsAndBytes, VinylTaskStat};
+use crate::tuple::Tuple;
+use serde::{Deserialize, Serialize};
+
+////////////////////////////////////////////////////////////////////////////////
+// compaction
+////////////////////////////////////////////////////////////////////////////////
+
+/// Schedule a compaction of all of the runs of the index with the given
+/// `index_id` of the space with the given `space_id`.
+///
+/// The compaction is performed in the background, this function doesn't wait
+/// for it to complete. Use [`lsm_stat`] to check the progress.
+///
+/// This is the equivalent of lua's `index_object:compact()`.
+pub fn compact(space_id: SpaceId, index_id: IndexId) -> Result<(), Error> {
+    let lua = crate::lua_state();
+    lua.exec_with(
+        "local space_id, index_id = ...
+        local space = box.space[space_id]
+        if space == nil then
+            error(('Space #%d does not exist'):format(space_id))
+        end
+        local index = space.index[index_id]
+        if index == nil then
+            error(('No index #%d is defined in space %q'):format(index_id, space.name))
+        end
+        index:compact()",
+        (space_id, index_id),
+    )
+    .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// LSM tree
+////////////////////////////////////////////////////////////////////////////////
+
+/// State of the LSM tree of a vinyl index. See [`lsm_stat`].
+///
+/// See [`VinylIndexStat`] for the full statistics of a vinyl index.
+///
+/// [`VinylIndexStat`]: crate::stat::VinylIndexStat
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LsmStat {
+    /// Number of key ranges.
+    pub range_count: u64,
+    /// Number of runs on disk.
+    pub run_count: u64,
+    /// Average number of runs per range.
+    pub run_avg: f64,
+    /// Number of ranges by the number of runs in them, ordered by the
+    /// number of runs.
+    pub run_histogram: Vec<RunHistogramBucket>,
+    /// Average number of dumps per compaction.
+    pub dumps_per_compaction: u64,
+    /// The in-memory level of the LSM tree, i.e. the data waiting to be
+    /// dumped to disk.
+    pub memory: RowsAndBytes,
+    /// The on-disk levels of the LSM tree.
+    pub disk: RowsAndBytes,
+    /// Dumps of the in-memory level to disk.
+    pub dump: VinylTaskStat,
+    /// Compactions of the on-disk runs, including the
+    /// [`queue`](VinylTaskStat::queue) of the data waiting to be compacted.
+    pub compaction: VinylTaskStat,
+}
+
+/// A bucket of [`LsmStat::run_histogram`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RunHistogramBucket {
+    /// Number of runs in a range.
+    pub runs: u64,
+    /// Number of ranges with this many runs.
+    pub ranges: u64,
+}
+
+/// Returns the state of the LSM tree of the index with the given `index_id`
+/// of the space with the given `space_id`.
+///
+/// Returns an error if the space is not a vinyl space.
+pub fn lsm_stat(space_id: SpaceId, index_id: IndexId) -> Result<LsmStat, Error> {
+    let lua = crate::lua_state();
+    let tuple: Tuple = lua
+        .eval_with(
+            "local space_id, index_id = ...
+            local space = box.space[space_id]
+            if space == nil then
+                error(('Space #%d does not exist'):format(space_id))
+            end
+            if space.engine ~= 'vinyl' then
+                error(('Space %q is not a vinyl space'):format(space.name))
+            end
+            local index = space.index[index_id]
+            if index == nil then
+                error(('No index #%d is defined in space %q'):format(index_id, space.name))
+            end
+            local stat = index:stat()
+            -- run_histogram is a string like '[1]:10 [2]:3'
+            local run_histogram = {}
+            for runs, ranges in (stat.run_histogram or ''):gmatch('%[(%d+)%]:(%d+)') do
+                table.insert(run_histogram, {
+                    runs = tonumber(runs),
+                    ranges = tonumber(ranges),
+                })
+            end
+            return box.tuple.new({{
+                range_count = stat.range_count,
+                run_count = stat.run_count,
+                run_avg = stat.run_avg,
+                run_histogram = run_histogram,
+                dumps_per_compaction = stat.dumps_per_compaction,
+                memory = stat.memory,
+                disk = stat.disk,
+                dump = stat.disk.dump,
+                compaction = stat.disk.compaction,
+            }})",
+            (space_id, index_id),
+        )
+        .map_err(tlua::LuaError::from)?;
+    let (stat,) = tuple.decode()?;
+    Ok(stat)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// scheduler
+////////////////////////////////////////////////////////////////////////////////
+
+/// State of the vinyl scheduler, which runs dumps and compactions of all
+/// vinyl indexes. See [`scheduler_stat`].
+///
+/// All sizes are in bytes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SchedulerStat {
+    /// Number of dump and compaction tasks in progress.
+    pub tasks_inprogress: u64,
+    /// Number of completed dump and compaction tasks.
+    pub tasks_completed: u64,
+    /// Number of failed dump and compaction tasks.
+    pub tasks_failed: u64,
+    /// Number of completed dumps.
+    pub dump_count: u64,
+    /// Total time spent on dumps in seconds.
+    pub dump_time: f64,
+    /// Amount of data read by dumps.
+    pub dump_input: u64,
+    /// Amount of data written by dumps.
+    pub dump_output: u64,
+    /// Total time spent on compactions in seconds.
+    pub compaction_time: f64,
+    /// Amount of data read by compactions.
+    pub compaction_input: u64,
+    /// Amount of data written by compactions.
+    pub compaction_output: u64,
+    /// Amount of data waiting to be compacted.
+    pub compaction_queue: u64,
+}
+
+/// Returns the state of the vinyl scheduler.
+///
+/// This is the equivalent of lua's `box.stat.vinyl().scheduler`.
+pub fn scheduler_stat() -> Result<SchedulerStat, Error> {
+    let lua = crate::lua_state();
+    let tuple: Tuple = lua.eval("return box.tuple.new({box.stat.vinyl().scheduler})")?;
+    let (stat,) = tuple.decode()?;
+    Ok(stat)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// box.cfg
+////////////////////////////////////////////////////////////////////////////////
+
+/// The `box.cfg.vinyl_*` settings. See [`config`] and [`set_config`].
+///
+/// Each field corresponds to the `box.cfg` option with the `vinyl_` prefix,
+/// e.g. [`Config::memory`] is `box.cfg.vinyl_memory`. Sizes are in bytes.
+///
+/// For details see [Storage configuration](https://www.tarantool.io/en/doc/latest/reference/configuration/#cfg-storage).
+#[derive(Clone, Debug, Default, PartialEq, tlua::Push, tlua::LuaRead)]
+pub struct Config {
+    /// Directory where vinyl files are stored.
+    pub dir: Option<String>,
+    /// Max amount of memory used by the in-memory levels of LSM trees.
+    pub memory: Option<u64>,
+    /// Max amount of memory used by the tuple cache.
+    pub cache: Option<u64>,
+    /// Max size of a tuple.
+    pub max_tuple_size: Option<u64>,
+    /// Number of threads used for reading from disk.
+    pub read_threads: Option<u32>,
+    /// Number of threads used for dumps and compactions.
+    pub write_threads: Option<u32>,
+    /// Max time in seconds a transaction waits for the memory to be freed
+    /// by a dump.
+    pub timeout: Option<f64>,
+    /// Default bloom filter false positive rate of new indexes.
+    pub bloom_fpr: Option<f32>,
+    /// Default page size of new indexes.
+    pub page_size: Option<u32>,
+    /// Default range size of new indexes.
+    pub range_size: Option<u32>,
+    /// Default max number of runs per level of new indexes.
+    pub run_count_per_level: Option<u32>,
+    /// Default ratio between the sizes of adjacent levels of new indexes.
+    pub run_size_ratio: Option<f32>,
+}
+
+/// Returns the current `box.cfg.vinyl_*` settings.
+pub fn config() -> Result<Config, Error> {
+    let lua = crate::lua_state();
+    let config = lua.eval(
+        "local config = {}
+        for k, v in pairs(box.cfg) do
+            local name = k:match('^vinyl_(.+)$')
+            if name ~= nil then
+                config[name] = v
+            end
+        end
+        return config",
+    )?;
+    Ok(config)
+}
+
+/// Change the `box.cfg.vinyl_*` settings. Only the fields which are set are
+/// changed.
+///
+/// Some of the settings (e.g. `dir`, `read_threads`, `write_threads`) can
+/// only be set in the initial `box.cfg` call, changing them afterwards
+/// results in an error. Settings which are defaults for new indexes don't
+/// affect the existing indexes.
+pub fn set_config(config: &Config) -> Result<(), Error> {
+    let lua = crate::lua_state();
+    lua.exec_with(
+        "local config = {}
+        for k, v in pairs(...) do
+            config['vinyl_' .. k] = v
+        end
+        box.cfg(config)",
+        config,
+    )
+    .map_err(tlua::LuaError::from)?;
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// tests
+////////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "internal_test")]
+mod tests {
+    use super::*;
+    use crate::fiber;
+    use crate::space::{Space, SpaceEngineType};
+    use crate::time::Instant;
+    use crate::util::Value;
+    use std::time::Duration;
+
+    #[crate::test(tarantool = "crate")]
+    fn compaction_and_lsm_stat() {
+        let space = Space::builder(&crate::temp_space_name!())
+            .engine(SpaceEngineType::Vinyl)
+            .defer_deletes(true)
+            .create()
+            .unwrap();
+        let meta = space.meta().unwrap();
+        assert_eq!(meta.flags.get("defer_deletes"), Some(&Value::Bool(true)));
+        let pk = space.index_builder("pk").create().unwrap();
+
+        for i in 0..10 {
+            space.insert(&(i, "foo")).unwrap();
+        }
+        let stat = pk.lsm_stat().unwrap();
+        assert_eq!(stat.memory.rows, 10);
+        assert_eq!(stat.run_count, 0);
+
+        // Each snapshot dumps the in-memory level into a new run.
+        let lua = crate::lua_state();
+        for i in 0..10 {
+            space.replace(&(i, "bar")).unwrap();
+            lua.exec("box.snapshot()").unwrap();
+        }
+        let stat = pk.lsm_stat().unwrap();
+        assert_eq!(stat.memory.rows, 0);
+        assert!(stat.dump.count >= 1);
+        assert!(stat.run_count >= 1);
+        assert!(!stat.run_histogram.is_empty());
+        let ranges: u64 = stat.run_histogram.iter().map(|b| b.ranges).sum();
+        assert_eq!(ranges, stat.range_count);
+
+        pk.compact().unwrap();
+        let deadline = Instant::now_accurate() + Duration::from_secs(10);
+        let stat = loop {
+            let stat = pk.lsm_stat().unwrap();
+            if stat.compaction.count >= 1 || Instant::now_accurate() > deadline {
+                break stat;
+            }
+            fiber::sleep(Duration::from_millis(10));
+        };
+        assert!(stat.compaction.count >= 1);
+        assert_eq!(stat.run_count, stat.range_count);
+        assert!(scheduler_stat().unwrap().compaction_output > 0);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn lsm_stat_memtx() {
+        let space = Space::builder(&crate::temp_space_name!()).create().unwrap();
+        let pk = space.index_builder("pk").create().unwrap();
+
+        let e = pk.lsm_stat().unwrap_err();
+        assert!(e.to_string().contains("is not a vinyl space"), "{}", e);
+
+        space.drop().unwrap();
+    }
+
+    #[crate::test(tarantool = "crate")]
+    fn vinyl_config() {
+        let old = config().unwrap();
+        assert!(old.memory.unwrap() > 0);
+        assert!(old.dir.is_some());
+
+        let cache = old.cache.unwrap() + 1024 * 1024;
+        set_config(&Config {
+            cache: Some(cache),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(config().unwrap().cache, Some(cache));
+
+        set_config(&Config {
+            cache: old.cache,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(config().unwrap(), old);
+    }
+}
@@ -5,7 +5,7 @@ use std::collections::BTreeMap;
 use tarantool::index::{self, IndexOptions, IteratorType};
 use tarantool::sequence::Sequence;
 use tarantool::space::UpdateOps;
-use tarantool::space::{self, Field, Space, SystemSpace};
+use tarantool::space::{Field, Space, SystemSpace};
 use tarantool::space::{SpaceCreateOptions, SpaceEngineType, SpaceType};
 use tarantool::test::util::on_scope_exit;
 use tarantool::tuple::Tuple;
@@ -927,11 +927,7 @@ pub fn space_meta() {
         format: Some(vec![
             Field::unsigned("f1"),
             Field::boolean("f2"),
-            Field {
-                name: "f3".to_string(),
-                field_type: space::FieldType::String,
-                is_nullable: true,
-            },
+            Field::string("f3").is_nullable(true),
         ]),
         ..Default::default()
     };
@@ -5,7 +5,7 @@ use std::os::fd::OwnedFd;
 use std::os::unix::net::UnixStream;
 use std::time::Duration;
 
-use tarantool::coio::{self, channel, CoIOListener, CoIOStream};
+use tarantool::coio::{self, channel, CoIOListener, CoIOStream, CoIOUdpSocket, ResolveAddrs};
 use tarantool::fiber;
 
 pub fn coio_accept() {
@@ -19,7 +19,7 @@ pub fn coio_accept() {
     });
     let accept_result = coio_listener.accept();
     assert!(accept_result.is_ok());
-    client_fiber.join();
+    client_fiber.join().unwrap();
 }
 
 pub fn coio_read_write() {
@@ -37,8 +37,8 @@ pub fn coio_read_write() {
         stream.write_all(&[1, 2, 3, 4]).unwrap();
     });
 
-    reader_fiber.join();
-    writer_fiber.join();
+    reader_fiber.join().unwrap();
+    writer_fiber.join().unwrap();
 }
 
 pub fn coio_call() {
@@ -64,8 +64,8 @@ pub fn coio_channel() {
         assert_eq!(value, 99);
     });
 
-    fiber_a.join();
-    fiber_b.join();
+    fiber_a.join().unwrap();
+    fiber_b.join().unwrap();
 }
 
 pub fn channel_rx_closed() {
@@ -74,7 +74,7 @@ pub fn channel_rx_closed() {
     let fiber = fiber::start(move || {
         assert!(tx.send(99).is_err());
     });
-    fiber.join();
+    fiber.join().unwrap();
 }
 
 pub fn channel_tx_closed() {
@@ -83,5 +83,133 @@ pub fn channel_tx_closed() {
     let fiber = fiber::start(move || {
         assert!(rx.recv().is_none());
     });
-    fiber.join();
+    fiber.join().unwrap();
+}
+
+pub fn spawn_blocking() {
+    let main_thread = std::thread::current().id();
+    let handle = coio::spawn_blocking(move || {
+        assert_ne!(std::thread::current().id(), main_thread);
+        std::thread::sleep(Duration::from_millis(10));
+        42
+    });
+
+    // Other fibers keep running while the closure is in progress.
+    let other = fiber::start(|| 13);
+    assert_eq!(other.join().unwrap(), 13);
+
+    assert_eq!(handle.join(), 42);
+
+    let handle = coio::spawn_blocking(|| String::from("async"));
+    assert_eq!(fiber::block_on(async { handle.await }), "async");
+}
+
+pub fn spawn_blocking_panic() {
+    let jh = fiber::start(|| coio::spawn_blocking(|| -> i32 { panic!("oops") }).join());
+    let e = jh.join().unwrap_err();
+    assert_eq!(e.message(), Some("oops"));
+}
+
+pub fn fs_read_write() {
+    use std::io::{Seek, SeekFrom};
+    use tarantool::coio::fs::{self, File};
+
+    let dir = std::env::temp_dir().join(format!("coio-fs-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("data.txt");
+
+    let mut file = File::create(&path).unwrap();
+    file.write_all(b"hello").unwrap();
+    file.sync_all().unwrap();
+    assert_eq!(file.metadata().unwrap().len(), 5);
+    drop(file);
+
+    let mut file = File::open(&path).unwrap();
+    let mut buf = String::new();
+    file.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "hello");
+    file.seek(SeekFrom::Start(1)).unwrap();
+    let mut buf = [0; 2];
+    file.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"el");
+
+    let new_path = dir.join("renamed.txt");
+    fs::rename(&path, &new_path).unwrap();
+    assert!(fs::metadata(&path).is_err());
+    assert_eq!(fs::read(&new_path).unwrap(), b"hello");
+
+    fiber::block_on(async {
+        fs::write_async(&path, "async").await.unwrap();
+        assert_eq!(fs::read_to_string_async(&path).await.unwrap(), "async");
+
+        let file = File::open_async(&path).await.unwrap();
+        assert_eq!(file.read_async(2).await.unwrap(), b"as");
+        assert_eq!(file.read_to_end_async().await.unwrap(), b"ync");
+    });
+
+    let mut names: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["data.txt", "renamed.txt"]);
+
+    for name in names {
+        fs::remove_file(dir.join(name)).unwrap();
+    }
+    fs::remove_dir(&dir).unwrap();
+}
+
+pub fn coio_udp_send_recv() {
+    let receiver = CoIOUdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = receiver.local_addr().unwrap();
+
+    let receiver_fiber = fiber::start(move || {
+        let mut buf = [0; 16];
+        let (len, _) = receiver.recv_from(&mut buf).unwrap();
+        buf[..len].to_vec()
+    });
+
+    let sender = CoIOUdpSocket::bind("127.0.0.1:0").unwrap();
+    assert_eq!(sender.send_to(b"ping", addr).unwrap(), 4);
+    assert_eq!(receiver_fiber.join().unwrap(), b"ping");
+}
+
+pub fn coio_udp_recv_timeout() {
+    let socket = CoIOUdpSocket::bind("127.0.0.1:0").unwrap();
+    let mut buf = [0; 16];
+    let err = socket
+        .recv_from_with_timeout(&mut buf, Some(Duration::from_millis(10)))
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+pub fn resolve() {
+    let addrs = coio::resolve("localhost", 3301, Duration::from_secs(10)).unwrap();
+    assert!(!addrs.is_empty());
+    assert!(addrs
+        .iter()
+        .all(|a| a.ip().is_loopback() && a.port() == 3301));
+
+    let addrs = coio::resolve("127.0.0.1", 80, Duration::from_secs(10)).unwrap();
+    assert_eq!(addrs, ["127.0.0.1:80".parse().unwrap()]);
+
+    let addrs = fiber::block_on(coio::resolve_async(
+        "localhost",
+        3301,
+        Duration::from_secs(10),
+    ))
+    .unwrap();
+    assert!(!addrs.is_empty());
+
+    let addrs = coio::to_socket_addrs(("localhost", 3301)).unwrap();
+    assert!(!addrs.is_empty());
+
+    let addrs = ("localhost", 3301).resolve_addrs().unwrap();
+    assert!(addrs.iter().all(|a| a.ip().is_loopback()));
+    let addrs = "127.0.0.1:3301".resolve_addrs().unwrap();
+    assert_eq!(addrs, ["127.0.0.1:3301".parse().unwrap()]);
+
+    assert!(coio::resolve("invalid domain name", 80, Duration::from_secs(10)).is_err());
 }
@@ -51,7 +51,7 @@ pub fn unbuffered() {
 
     assert_eq!(check_yield(|| tx.send("hello").unwrap()), Yielded(()));
 
-    assert_eq!(f.join(), "hello")
+    assert_eq!(f.join().unwrap(), "hello")
 }
 
 pub fn drop_sender() {
@@ -61,7 +61,7 @@ pub fn drop_sender() {
 
     tx.close();
 
-    assert_eq!(f.join(), None);
+    assert_eq!(f.join().unwrap(), None);
 }
 
 pub fn dont_drop_msg() {
@@ -93,9 +93,9 @@ pub fn one_v_two() {
     let f1 = fiber::defer(move || rx1.recv().unwrap());
     let f2 = fiber::defer(move || rx2.recv().unwrap());
     tx.send("hello").unwrap();
-    assert_eq!(f1.join(), "hello");
+    assert_eq!(f1.join().unwrap(), "hello");
     tx.send("what's up").unwrap();
-    assert_eq!(f2.join(), "what's up");
+    assert_eq!(f2.join().unwrap(), "what's up");
 }
 
 pub fn two_v_one() {
@@ -104,8 +104,8 @@ pub fn two_v_one() {
     let f2 = fiber::defer(move || tx2.send("what's good").unwrap());
     assert_eq!(rx.recv(), Some("how ya doin?"));
     assert_eq!(rx.recv(), Some("what's good"));
-    f1.join();
-    f2.join();
+    f1.join().unwrap();
+    f2.join().unwrap();
 }
 
 pub fn drop_msgs() {
@@ -140,8 +140,8 @@ pub fn circle() {
     msg.push(2);
     tx3.send(msg).unwrap();
 
-    assert_eq!(f2.join(), vec![1, 2, 3]);
-    let () = f1.join();
+    assert_eq!(f2.join().unwrap(), vec![1, 2, 3]);
+    let () = f1.join().unwrap();
 }
 
 pub fn iter() {
@@ -157,9 +157,9 @@ pub fn iter() {
             break;
         }
     }
-    f1.join();
-    f2.join();
-    f3.join();
+    f1.join().unwrap();
+    f2.join().unwrap();
+    f3.join().unwrap();
 }
 
 pub fn into_iter() {
@@ -175,9 +175,9 @@ pub fn into_iter() {
             break;
         }
     }
-    f1.join();
-    f2.join();
-    f3.join();
+    f1.join().unwrap();
+    f2.join().unwrap();
+    f3.join().unwrap();
 }
 
 pub fn try_iter() {
@@ -229,11 +229,11 @@ pub fn as_mutex() {
     fiber::sleep(Duration::ZERO);
 
     log0.send("main:join(f2)").unwrap();
-    f2.join();
+    f2.join().unwrap();
     log0.send("main:join(f1)").unwrap();
-    f1.join();
+    f1.join().unwrap();
     log0.send("main:join(f3)").unwrap();
-    f3.join();
+    f3.join().unwrap();
     log0.send("main:done").unwrap();
 
     assert_eq!(unsafe { &*sr }, &[1, 2, 3]);
@@ -278,10 +278,10 @@ pub fn demo() {
     fiber::sleep(Duration::from_millis(10));
     tx.close();
 
-    f.join();
+    f.join().unwrap();
 
     assert_eq!(
-        flog.join(),
+        flog.join().unwrap(),
         &[
             "job started".to_string(),
             "job got data: 1".to_string(),
@@ -294,7 +294,7 @@ pub fn drop_rx() {
     let (tx, rx) = fiber::Channel::new(0).into_clones();
     let f = fiber::defer_proc(move || rx.close());
     assert_eq!(tx.send("no block"), Err("no block"));
-    f.join();
+    f.join().unwrap();
 }
 
 pub fn into_clones() {
@@ -316,5 +316,5 @@ pub fn cannot_send_ref() {
         // tx.send(&v).unwrap(); <- must not compile anymore
         tx.send(v).unwrap();
     }
-    assert_eq!(f.join().unwrap(), &[1, 2, 3])
+    assert_eq!(f.join().unwrap().unwrap(), &[1, 2, 3])
 }
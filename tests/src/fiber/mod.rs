@@ -21,11 +21,11 @@ pub mod old;
 
 pub fn immediate() {
     let jh = fiber::Builder::new().func(|| 69).start().unwrap();
-    let res = jh.join();
+    let res = jh.join().unwrap();
     assert_eq!(res, 69);
 
     let jh = fiber::start(|| 420);
-    let res = jh.join();
+    let res = jh.join().unwrap();
     assert_eq!(res, 420);
 }
 
@@ -37,7 +37,7 @@ pub fn immediate_with_attrs() {
         .func(|| 42)
         .start()
         .unwrap();
-    let res = jh.join();
+    let res = jh.join().unwrap();
     assert_eq!(res, 42);
 }
 
@@ -49,16 +49,16 @@ pub fn multiple_immediate() {
         .map(|v| fiber::start(move || v.into_iter().map(|e| e + 1).collect::<Vec<_>>()))
         .collect::<Vec<_>>();
     res.push(1);
-    res.extend(fibers.into_iter().flat_map(fiber::JoinHandle::join));
+    res.extend(fibers.into_iter().flat_map(|f| f.join().unwrap()));
     res.push(8);
     assert_eq!(res, vec![1, 2, 3, 4, 5, 6, 7, 8]);
 }
 
 pub fn unit_immediate() {
     let jh = fiber::Builder::new().func(|| ()).start().unwrap();
-    let () = jh.join();
+    let () = jh.join().unwrap();
 
-    let () = fiber::start_proc(|| ()).join();
+    let () = fiber::start_proc(|| ()).join().unwrap();
 }
 
 pub fn unit_immediate_with_attrs() {
@@ -69,7 +69,7 @@ pub fn unit_immediate_with_attrs() {
         .proc(|| ())
         .start()
         .unwrap();
-    let () = jh.join();
+    let () = jh.join().unwrap();
 }
 
 pub fn multiple_unit_immediate() {
@@ -87,7 +87,7 @@ pub fn multiple_unit_immediate() {
         .collect::<Vec<_>>();
     res.borrow_mut().push(8);
     for f in fibers {
-        f.join()
+        f.join().unwrap()
     }
     res.borrow_mut().push(9);
     let res = res.borrow().iter().copied().collect::<Vec<_>>();
@@ -96,17 +96,17 @@ pub fn multiple_unit_immediate() {
 
 pub fn deferred() {
     let jh = fiber::Builder::new().func(|| 13).defer().unwrap();
-    assert_eq!(jh.join(), 13);
+    assert_eq!(jh.join().unwrap(), 13);
 
     let jh = fiber::defer(|| 42);
-    assert_eq!(jh.join(), 42);
+    assert_eq!(jh.join().unwrap(), 42);
 }
 
 pub fn deferred_ffi() {
     let csw_before = fiber::csw();
     let jh = fiber::Builder::new().func(|| 1337).defer_ffi().unwrap();
     assert_eq!(fiber::csw(), csw_before);
-    assert_eq!(jh.join(), 1337);
+    assert_eq!(jh.join().unwrap(), 1337);
     assert_eq!(fiber::csw(), csw_before + 1);
 }
 
@@ -118,7 +118,8 @@ pub fn deferred_with_attrs() {
         .func(|| 15)
         .defer()
         .unwrap()
-        .join();
+        .join()
+        .unwrap();
     assert_eq!(res, 15);
 }
 
@@ -130,14 +131,14 @@ pub fn multiple_deferred() {
         .map(|v| fiber::defer(move || v.into_iter().map(|e| e + 1).collect::<Vec<_>>()))
         .collect::<Vec<_>>();
     res.push(1);
-    res.extend(fibers.into_iter().flat_map(fiber::JoinHandle::join));
+    res.extend(fibers.into_iter().flat_map(|f| f.join().unwrap()));
     res.push(8);
     assert_eq!(res, vec![1, 2, 3, 4, 5, 6, 7, 8]);
 }
 
 pub fn unit_deferred() {
     let jh = fiber::Builder::new().proc(|| ()).defer().unwrap();
-    let () = jh.join();
+    let () = jh.join().unwrap();
 
     let res = Rc::new(Cell::new(0));
     let jh = {
@@ -145,7 +146,7 @@ pub fn unit_deferred() {
         fiber::defer_proc(move || res.set(42))
     };
     assert_eq!(res.get(), 0);
-    jh.join();
+    jh.join().unwrap();
     assert_eq!(res.get(), 42);
 }
 
@@ -157,7 +158,8 @@ pub fn unit_deferred_with_attrs() {
         .proc(|| ())
         .defer()
         .unwrap()
-        .join();
+        .join()
+        .unwrap();
 }
 
 pub fn multiple_unit_deferred() {
@@ -175,7 +177,7 @@ pub fn multiple_unit_deferred() {
         .collect::<Vec<_>>();
     res.borrow_mut().push(1);
     for f in fibers {
-        f.join()
+        f.join().unwrap()
     }
     res.borrow_mut().push(8);
     let res = res.borrow().iter().copied().collect::<Vec<_>>();
@@ -193,7 +195,7 @@ pub fn immediate_yields() {
     assert_eq!(rx.get(), 69);
     assert_eq!(csw2, csw1 + 1);
 
-    f.join();
+    f.join().unwrap();
 }
 
 pub fn deferred_doesnt_yield() {
@@ -210,7 +212,7 @@ pub fn deferred_doesnt_yield() {
     fiber::sleep(Duration::ZERO);
     assert_eq!(rx.get(), 96);
 
-    f.join();
+    f.join().unwrap();
 }
 
 pub fn start_error() {
@@ -336,7 +338,7 @@ pub fn immediate_with_cond() {
     assert_eq!(*msgs.borrow(), vec![1, 2, 3, 4, 5, 6]);
 
     for f in fibers {
-        f.join()
+        f.join().unwrap()
     }
 }
 
@@ -368,7 +370,7 @@ pub fn deferred_with_cond() {
     assert_eq!(*msgs.borrow(), vec![1, 2, 3, 4, 5, 6]);
 
     for f in fibers {
-        f.join()
+        f.join().unwrap()
     }
 }
 
@@ -392,8 +394,8 @@ pub fn lua_thread() {
         l.read::<String>().unwrap()
     });
 
-    assert_eq!(v1.join(), 42);
-    assert_eq!(v2.join(), "hello");
+    assert_eq!(v1.join().unwrap(), 42);
+    assert_eq!(v2.join().unwrap(), "hello");
     assert_eq!(
         log_out.try_iter().collect::<Vec<_>>(),
         vec!["t1:push", "t2:push", "t1:read", "t2:read"]
@@ -408,7 +410,7 @@ pub fn lifetime() {
     {
         let mut v = vec![1, 2, 3];
         let jh = fiber::start_proc(|| v[0] = 2);
-        jh.join();
+        jh.join().unwrap();
         assert_eq!(v, vec![2, 2, 3]);
     }
 
@@ -421,7 +423,7 @@ pub fn lifetime() {
     {
         let v = vec![1, 2, 3];
         let jh = fiber::start(|| v[0]);
-        assert_eq!(jh.join(), 1);
+        assert_eq!(jh.join().unwrap(), 1);
     }
 
     // Doesn't compile
@@ -433,7 +435,7 @@ pub fn lifetime() {
     {
         let mut v = vec![1, 2, 3];
         let jh = fiber::defer_proc(|| v[0] = 2);
-        jh.join();
+        jh.join().unwrap();
         assert_eq!(v, vec![2, 2, 3]);
     }
 
@@ -446,7 +448,7 @@ pub fn lifetime() {
     {
         let v = vec![1, 2, 3];
         let jh = fiber::defer(|| v[0]);
-        assert_eq!(jh.join(), 1);
+        assert_eq!(jh.join().unwrap(), 1);
     }
 
     // Doesn't compile
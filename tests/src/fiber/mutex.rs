@@ -9,7 +9,7 @@ pub fn simple() {
     let (sr1, sr2) = Rc::new(Mutex::new(0)).into_clones();
     let f = start_proc(move || *sr2.lock() = 69);
     assert_eq!(*sr1.lock(), 69);
-    f.join();
+    f.join().unwrap();
 }
 
 pub fn try_lock() {
@@ -20,7 +20,7 @@ pub fn try_lock() {
         *guard = 420;
     });
     assert!(sr1.try_lock().is_none());
-    f.join();
+    f.join().unwrap();
     assert_eq!(sr1.try_lock().map(|g| *g), Some(420))
 }
 
@@ -34,14 +34,14 @@ pub fn debug() {
     } else {
         "Mutex { data: <locked>, .. }".to_owned()
     };
-    let mutex_debug_repr = start(|| format!("{:?}", m)).join();
+    let mutex_debug_repr = start(|| format!("{:?}", m)).join().unwrap();
     assert_eq!(mutex_debug_repr, expected);
 
     *guard = 13;
     drop(guard);
 
     assert_eq!(
-        start(|| format!("{:?}", m)).join(),
+        start(|| format!("{:?}", m)).join().unwrap(),
         "Mutex { data: 13, .. }"
     );
 }
@@ -82,11 +82,11 @@ pub fn advanced() {
     sleep(Duration::ZERO);
 
     log0.send("main:join(f2)").unwrap();
-    f2.join();
+    f2.join().unwrap();
     log0.send("main:join(f1)").unwrap();
-    f1.join();
+    f1.join().unwrap();
     log0.send("main:join(f3)").unwrap();
-    f3.join();
+    f3.join().unwrap();
     log0.send("main:done").unwrap();
 
     assert_eq!(Rc::try_unwrap(sr0).unwrap().into_inner(), &[1, 2, 3]);
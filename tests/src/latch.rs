@@ -12,7 +12,7 @@ pub fn latch_lock() {
     });
 
     latch.lock();
-    fiber.join();
+    fiber.join().unwrap();
 }
 
 pub fn latch_try_lock() {
@@ -25,6 +25,6 @@ pub fn latch_try_lock() {
 
     assert!(latch.try_lock().is_none());
 
-    fiber.join();
+    fiber.join().unwrap();
     assert!(latch.try_lock().is_some());
 }
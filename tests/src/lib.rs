@@ -467,7 +467,13 @@ fn run_tests(cfg: TestConfig) -> Result<bool, io::Error> {
                 tuple::tuple_buffer_from_vec_fail,
                 coio::coio_accept,
                 coio::coio_read_write,
+                coio::coio_udp_send_recv,
+                coio::coio_udp_recv_timeout,
                 coio::coio_call,
+                coio::resolve,
+                coio::spawn_blocking,
+                coio::spawn_blocking_panic,
+                coio::fs_read_write,
                 coio::coio_channel,
                 coio::channel_rx_closed,
                 coio::channel_tx_closed,
@@ -107,8 +107,8 @@ pub fn ping_concurrent() {
         conn_b.ping(&Options::default()).unwrap();
     });
 
-    fiber_a.join();
-    fiber_b.join();
+    fiber_a.join().unwrap();
+    fiber_b.join().unwrap();
 }
 
 pub fn call() {
@@ -206,7 +206,7 @@ pub fn call_async_wait_disconnected() {
         drop(conn);
     });
     assert_eq!(p.wait().unwrap_err().to_string(), "io error: not connected");
-    jh.join();
+    jh.join().unwrap();
 }
 
 pub fn eval() {
@@ -543,7 +543,7 @@ pub fn cancel_recv() {
         }
     });
     conn.close();
-    fiber.join();
+    fiber.join().unwrap();
 }
 
 pub fn triggers_connect() {